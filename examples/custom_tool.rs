@@ -0,0 +1,71 @@
+//! A binary with a tool of its own, next to the built-in ones.
+//!
+//! Give an agent the tool in `config.toml`:
+//!
+//! ```toml
+//! [agents.Assistant]
+//! description = "Greets people."
+//! tools = [{ name = "GreeterTool", greeting = "Good morning" }]
+//! ```
+//!
+//! and run it with `cargo run --example custom_tool -- --task "Greet Ada"`.
+
+use anyhow::Result;
+use app::tools::{
+    registry::{RegisteredTool, ToolContext},
+    Tool,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// A tool that greets someone by name.
+struct GreeterTool {
+    greeting: String,
+}
+
+#[async_trait]
+impl Tool for GreeterTool {
+    fn name(&self) -> &str {
+        "GreeterTool"
+    }
+
+    fn description(&self) -> String {
+        "Greets someone. Args: the person's name.".to_string()
+    }
+
+    async fn execute(&self, args: &str) -> Result<String> {
+        Ok(format!("{}, {}!", self.greeting, args.trim()))
+    }
+}
+
+/// The options of `GreeterTool` in an agent's `tools`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GreeterOptions {
+    #[serde(default = "default_greeting")]
+    greeting: String,
+}
+
+fn default_greeting() -> String {
+    "Hello".to_string()
+}
+
+#[async_trait]
+impl RegisteredTool for GreeterTool {
+    const NAME: &'static str = "GreeterTool";
+    type Options = GreeterOptions;
+
+    async fn build(
+        options: GreeterOptions,
+        _agent: &str,
+        _context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        Ok(vec![Box::new(GreeterTool {
+            greeting: options.greeting,
+        })])
+    }
+}
+
+app::register_tools!(fn register => GreeterTool);
+
+app::main!(register);
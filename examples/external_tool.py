@@ -0,0 +1,56 @@
+#!/usr/bin/env python3
+"""An `ExternalTool` in Python: summary statistics of a CSV column.
+
+Give an agent the tool in `config.toml`:
+
+    [agents.Analyst]
+    description = "Answers questions about the CSV files in the workspace."
+    tools = [{ name = "ExternalTool", command = "python3", args = ["examples/external_tool.py"] }]
+
+The tool reads JSON-RPC 2.0 requests from its standard input, one per line,
+and writes a response line to its standard output for each. Logs go to
+standard error.
+"""
+
+import csv
+import json
+import statistics
+import sys
+
+
+def describe(_params):
+    return {
+        "name": "ColumnStatsTool",
+        "description": "Summary statistics of a numeric CSV column. "
+        'Args: a JSON object like {"path": "data.csv", "column": "price"}.',
+    }
+
+
+def execute(params):
+    args = json.loads(params["args"])
+    with open(args["path"], newline="") as file:
+        values = [float(row[args["column"]]) for row in csv.DictReader(file)]
+    if not values:
+        raise ValueError("The column is empty")
+    return {
+        "count": len(values),
+        "mean": statistics.fmean(values),
+        "median": statistics.median(values),
+        "min": min(values),
+        "max": max(values),
+    }
+
+
+METHODS = {"describe": describe, "execute": execute}
+
+
+for line in sys.stdin:
+    request = json.loads(line)
+    response = {"jsonrpc": "2.0", "id": request.get("id")}
+    try:
+        response["result"] = METHODS[request["method"]](request.get("params"))
+    except Exception as error:  # Any failure is reported to the agent.
+        message = f"{type(error).__name__}: {error}"
+        response["error"] = {"code": -32000, "message": message}
+    if "id" in request:
+        print(json.dumps(response), flush=True)
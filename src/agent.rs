@@ -21,4 +21,10 @@ pub trait Agent {
     /// A `Result` containing the final answer or result of the task, or an
     /// error if the agent fails to complete the task.
     async fn run(&self, task: &str) -> Result<String>;
+
+    /// Shuts down the agent's tools and any agents it delegates to.
+    ///
+    /// This is called by the `Orchestrator` when a run ends, whether or not
+    /// it succeeded. The default implementation does nothing.
+    async fn shutdown(&self) {}
 }
@@ -0,0 +1,508 @@
+//! The command line: running tasks, inspecting recorded runs and
+//! evaluating agents.
+
+use crate::{
+    agent::Agent,
+    eval::{self, EvalConfig},
+    llm::{Llm, MockLlm, OpenAiLlm},
+    mcp_server::{McpServer, ServedAgent},
+    memory::{Memory, MemoryConfig},
+    orchestrator::Orchestrator,
+    run_log::{RunLog, RunsConfig},
+    team::{self, TeamConfig},
+    tools::{
+        command_policy::CommandPolicyConfig,
+        database::DatabaseConfig,
+        git::GitConfig,
+        http::{HttpClient, HttpConfig},
+        mcp::McpServerConfig,
+        registry::{ToolContext, ToolRegistry},
+        rest_api::openapi::ApiConfig,
+        sandbox::SandboxConfig,
+        secrets::{SecretConfig, Secrets},
+        semantic_search::IndexConfig,
+        workspace::Workspace,
+    },
+    trajectory,
+};
+use clap::{Parser, Subcommand};
+use config::{Config, File, Source};
+use dotenv::dotenv;
+use opentelemetry::global;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace, Resource};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+use tracing::{error, info};
+use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+/// Sets up tracing, with logs written by `writer`.
+fn init_tracer(settings: &Settings, writer: BoxMakeWriter) -> Result<(), anyhow::Error> {
+    let endpoint = settings
+        .otlp_endpoint
+        .as_deref()
+        .unwrap_or("http://localhost:4317");
+
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint);
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(Resource::new(vec![
+            opentelemetry::KeyValue::new("service.name", "rust-multi-agent-framework"),
+        ])))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+    let telemetry_layer = tracing_opentelemetry::layer().with_tracer(tracer);
+
+    tracing_subscriber::Registry::default()
+        .with(telemetry_layer)
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .try_init()?;
+
+    Ok(())
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The task for the agent to perform
+    #[arg(short, long, required_unless_present = "resume")]
+    task: Option<String>,
+
+    /// Resume the run with this ID from where it stopped
+    #[arg(long, conflicts_with = "task")]
+    resume: Option<String>,
+
+    /// Use the mock LLM for testing
+    #[arg(long, global = true)]
+    mock: bool,
+
+    /// The configuration file to load, with or without its extension
+    #[arg(long, global = true, default_value = "config")]
+    config: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show a run's trajectory as a timeline
+    Trajectory {
+        /// A run ID, or the path of a run log or trajectory file
+        run: String,
+
+        /// Write a static HTML report to this file instead
+        #[arg(long)]
+        html: Option<PathBuf>,
+    },
+    /// Re-execute a run with its recorded LLM responses and tool
+    /// observations, to reproduce what happened
+    Replay {
+        /// A run ID, or the path of a run log or trajectory file
+        run: String,
+    },
+    /// Run a suite of eval cases against the agent configuration and report
+    /// pass rates, latency and cost
+    Eval {
+        /// The suite file
+        suite: PathBuf,
+
+        /// How many times to attempt each case, overriding the suite
+        #[arg(long)]
+        repeat: Option<usize>,
+
+        /// An earlier `report.json` of the same suite to compare against
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+    },
+    /// Serve agents and tools over the Model Context Protocol on stdio, for
+    /// other MCP clients to call. Serves the root agent when neither
+    /// `--agent` nor `--tool` is given
+    Serve {
+        /// An agent to serve as a tool that takes a task
+        #[arg(long = "agent")]
+        agents: Vec<String>,
+
+        /// A registered tool to serve, such as `FileReaderTool`, built
+        /// without options
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+    },
+}
+
+/// Runs a subcommand that inspects recorded runs or evaluates the agents.
+async fn run_command(
+    command: Command,
+    args: &Args,
+    settings: &Settings,
+    registry: &ToolRegistry,
+    team: Option<&TeamConfig>,
+) -> anyhow::Result<()> {
+    match command {
+        Command::Trajectory { run, html } => {
+            let trajectory = trajectory::load(&settings.runs.directory, &run)?;
+            match html {
+                Some(path) => {
+                    std::fs::write(&path, trajectory::render::html(&trajectory))?;
+                    println!("Wrote {}", path.display());
+                }
+                None => print!("{}", trajectory::render::timeline(&trajectory)),
+            }
+        }
+        Command::Replay { run } => {
+            let trajectory = trajectory::load(&settings.runs.directory, &run)?;
+            let replayed = trajectory::replay::replay(&trajectory).await;
+            match &replayed {
+                Ok(result) if trajectory.result.as_ref() == Some(result) => {
+                    println!("The replay returned the recorded result: {}", result);
+                    return Ok(());
+                }
+                Ok(result) => println!("The replay returned: {}", result),
+                Err(e) => println!("The replay failed: {}", e),
+            }
+            match (&trajectory.result, &trajectory.error) {
+                (Some(result), _) => println!("The run returned: {}", result),
+                (None, Some(error)) => println!("The run failed: {}", error),
+                (None, None) => println!("The run did not finish"),
+            }
+        }
+        Command::Eval {
+            suite,
+            repeat,
+            baseline,
+        } => {
+            let suite = eval::Suite::load(&suite)?;
+            let baseline: Option<eval::report::Report> = match baseline {
+                Some(path) => Some(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+                None => None,
+            };
+            let judge: Box<dyn Llm + Send + Sync> = if args.mock {
+                Box::new(MockLlm::new(r#"{"pass": true, "reason": "Mock judge."}"#))
+            } else {
+                Box::new(OpenAiLlm::new(&settings.model))
+            };
+            let options = eval::EvalOptions {
+                config: &settings.eval,
+                config_name: args.config.clone(),
+                model: settings.model.clone(),
+                repeat,
+                baseline: baseline.as_ref(),
+            };
+            let report = eval::run(
+                &suite,
+                &options,
+                judge.as_ref(),
+                |workspace, task| async move {
+                    build_team(settings, registry, team, workspace, args.mock, &task).await
+                },
+            )
+            .await?;
+            print!("{}", report.markdown(baseline.as_ref()));
+        }
+        Command::Serve { agents, tools } => {
+            serve(settings, registry, team, args.mock, agents, tools).await?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+struct Settings {
+    model: String,
+    otlp_endpoint: Option<String>,
+    #[serde(default)]
+    sandbox: SandboxConfig,
+    /// Command policies for `SystemTool`, keyed by agent name.
+    #[serde(default)]
+    command_policies: HashMap<String, CommandPolicyConfig>,
+    /// Limits for the tools that access the web.
+    #[serde(default)]
+    http: HttpConfig,
+    /// Secrets that tools can authenticate with, keyed by name.
+    #[serde(default)]
+    secrets: HashMap<String, SecretConfig>,
+    /// OpenAPI documents whose operations become tools, keyed by API name.
+    #[serde(default)]
+    apis: HashMap<String, ApiConfig>,
+    /// Databases for `DatabaseTool`, keyed by name.
+    #[serde(default)]
+    databases: HashMap<String, DatabaseConfig>,
+    /// The commit author and protected branches for `GitTool`.
+    #[serde(default)]
+    git: GitConfig,
+    /// MCP servers whose tools and resources become tools, keyed by server
+    /// name.
+    #[serde(default)]
+    mcp_servers: HashMap<String, McpServerConfig>,
+    /// Long-term memory for the `MemoryAgent`, which is only added when
+    /// this section is present.
+    memory: Option<MemoryConfig>,
+    /// The semantic index for `SemanticSearchTool`, which is only added
+    /// when this section is present.
+    index: Option<IndexConfig>,
+    /// Where run logs and trajectories are kept for `--resume`,
+    /// `trajectory` and `replay`.
+    #[serde(default)]
+    runs: RunsConfig,
+    /// Where `eval` writes its results, and token prices for its costs.
+    #[serde(default)]
+    eval: EvalConfig,
+    /// The agent that receives each task, one of those in `[agents]`.
+    root_agent: Option<String>,
+}
+
+impl Settings {
+    /// Reads and checks the `[agents]` section of the configuration file,
+    /// if there is one.
+    ///
+    /// The section is read from the file itself rather than from `Config`,
+    /// which lowercases keys, as the keys are the agents' names.
+    fn team(&self, config: &str, registry: &ToolRegistry) -> anyhow::Result<Option<TeamConfig>> {
+        let agents: BTreeMap<String, serde_json::Value> = match File::with_name(config)
+            .required(false)
+            .collect()?
+            .remove("agents")
+        {
+            Some(agents) => agents.try_deserialize()?,
+            None => BTreeMap::new(),
+        };
+        if agents.is_empty() {
+            return match &self.root_agent {
+                Some(_) => Err(anyhow::anyhow!(
+                    "root_agent: there are no agents, define them under [agents]"
+                )),
+                None => Ok(None),
+            };
+        }
+        TeamConfig::parse(&agents, self.root_agent.as_deref(), registry).map(Some)
+    }
+}
+
+/// Builds the agents of `team`, or the default team without one, with tools
+/// from `registry` confined to `workspace`. With `mock`, every agent uses a
+/// `MockLlm` that finishes `task` straight away.
+async fn build_team(
+    settings: &Settings,
+    registry: &ToolRegistry,
+    team: Option<&TeamConfig>,
+    workspace: Workspace,
+    mock: bool,
+    task: &str,
+) -> anyhow::Result<Box<dyn Agent + Send + Sync>> {
+    let context = build_context(settings, registry, workspace, mock.then_some(task))?;
+    match team {
+        Some(team) => team::build::build(team, &context).await,
+        None => team::build::build(&team::build::default_team(&context)?, &context).await,
+    }
+}
+
+/// Gathers what agents and tools are built from: the registry, the
+/// workspace and the sections of the configuration file.
+fn build_context<'a>(
+    settings: &'a Settings,
+    registry: &'a ToolRegistry,
+    workspace: Workspace,
+    mock_task: Option<&'a str>,
+) -> anyhow::Result<team::build::BuildContext<'a>> {
+    let memory = match &settings.memory {
+        Some(config) => Some(Memory::from_config(config)?),
+        None => None,
+    };
+    Ok(team::build::BuildContext {
+        registry,
+        tools: ToolContext {
+            workspace,
+            sandbox: &settings.sandbox,
+            command_policies: &settings.command_policies,
+            http: HttpClient::new(&settings.http)?,
+            secrets: Secrets::new(&settings.secrets)?,
+            apis: &settings.apis,
+            databases: &settings.databases,
+            git: &settings.git,
+            mcp_servers: &settings.mcp_servers,
+            memory,
+            index: settings.index.as_ref(),
+        },
+        model: &settings.model,
+        mock_task,
+    })
+}
+
+/// Serves agents of `team`, or of the default team without one, and tools
+/// from `registry` over MCP on stdio until the client closes stdin.
+///
+/// Each call to an agent builds the team afresh in the current directory,
+/// with the agent as its root, and is logged as a run.
+async fn serve(
+    settings: &Settings,
+    registry: &ToolRegistry,
+    team: Option<&TeamConfig>,
+    mock: bool,
+    mut agents: Vec<String>,
+    tools: Vec<String>,
+) -> anyhow::Result<()> {
+    let workspace = Workspace::current()?;
+    let context = build_context(settings, registry, workspace.clone(), None)?;
+    let team = match team {
+        Some(team) => team.clone(),
+        None => team::build::default_team(&context)?,
+    };
+    if agents.is_empty() && tools.is_empty() {
+        agents.push(team.root.clone());
+    }
+    let mut served = Vec::new();
+    for name in agents {
+        let config = team
+            .agents
+            .get(&name)
+            .ok_or_else(|| anyhow::anyhow!("--agent: there is no agent named {:?}", name))?;
+        served.push(ServedAgent {
+            description: config.description.clone(),
+            name,
+        });
+    }
+    let mut built = Vec::new();
+    for name in &tools {
+        let tool = registry
+            .build(name, Default::default(), "McpServer", &context.tools)
+            .await
+            .map_err(|e| anyhow::anyhow!("--tool {}: {}", name, e))?;
+        built.extend(tool);
+    }
+
+    let team = &team;
+    let workspace = &workspace;
+    let server = McpServer::new(served, built, |agent, task| async move {
+        let rooted = TeamConfig::new(team.agents.clone(), Some(&agent))?;
+        let context = build_context(settings, registry, workspace.clone(), mock.then_some(&task))?;
+        let agent = team::build::build(&rooted, &context).await?;
+        let run_log = std::sync::Arc::new(RunLog::create(&settings.runs.directory, &task)?);
+        info!("Run ID: {}", run_log.id());
+        Orchestrator::new(agent)
+            .with_run_log(run_log)
+            .run(&task)
+            .await
+    })?;
+    info!("Serving MCP on stdio");
+    server
+        .serve(
+            tokio::io::BufReader::new(tokio::io::stdin()),
+            tokio::io::stdout(),
+        )
+        .await
+}
+
+/// The main entry point for the application.
+///
+/// This function sets up the agent, including the LLM and the tools in
+/// `registry`, and then runs the agent with a specific task.
+pub fn main(registry: ToolRegistry) {
+    tokio::runtime::Runtime::new()
+        .expect("Failed to start the Tokio runtime")
+        .block_on(run(registry))
+}
+
+async fn run(registry: ToolRegistry) {
+    dotenv().ok();
+
+    let mut args = Args::parse();
+
+    let settings = Config::builder()
+        .add_source(File::with_name(&args.config).required(false))
+        .build()
+        .unwrap()
+        .try_deserialize::<Settings>()
+        .unwrap();
+
+    // When serving MCP, stdout carries the protocol, so logs go to stderr.
+    let writer = match args.command {
+        Some(Command::Serve { .. }) => BoxMakeWriter::new(std::io::stderr),
+        _ => BoxMakeWriter::new(std::io::stdout),
+    };
+    init_tracer(&settings, writer).expect("Failed to initialize tracer");
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let team = match settings.team(&args.config, &registry) {
+        Ok(team) => team,
+        Err(e) => {
+            error!("Invalid configuration: {}", e);
+            return;
+        }
+    };
+
+    if let Some(command) = args.command.take() {
+        if let Err(e) = run_command(command, &args, &settings, &registry, team.as_ref()).await {
+            error!("Error: {}", e);
+        }
+        return;
+    }
+
+    let workspace = Workspace::current().expect("Failed to determine the workspace root");
+
+    let resumed = args.resume.as_ref().map(|id| {
+        RunLog::resume(&settings.runs.directory, id).expect("Failed to open the run log")
+    });
+    let task = match (&resumed, &args.task) {
+        (Some(run_log), _) => run_log.task().to_string(),
+        (None, Some(task)) => task.clone(),
+        (None, None) => unreachable!("clap requires a task or a run to resume"),
+    };
+
+    // The agents are built before a new run is logged, so that a tool that
+    // cannot be built does not leave a run behind.
+    let agent = match build_team(
+        &settings,
+        &registry,
+        team.as_ref(),
+        workspace,
+        args.mock,
+        &task,
+    )
+    .await
+    {
+        Ok(agent) => agent,
+        Err(e) => {
+            error!("Failed to build the agents: {}", e);
+            return;
+        }
+    };
+
+    let run_log = match resumed {
+        Some(run_log) => run_log,
+        None => {
+            RunLog::create(&settings.runs.directory, &task).expect("Failed to open the run log")
+        }
+    };
+    let run_log = std::sync::Arc::new(run_log);
+    info!("Run ID: {}", run_log.id());
+
+    let orchestrator = Orchestrator::new(agent).with_run_log(run_log.clone());
+
+    info!("Task: {}\n", &task);
+
+    match orchestrator.run(&task).await {
+        Ok(result) => info!("\nFinal Answer: {}", result),
+        Err(e) => error!(
+            "Error: {}\nResume the run with --resume {}",
+            e,
+            run_log.id()
+        ),
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{llm::Llm, tools::workspace::Workspace};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, process::Stdio, time::Duration};
+use tokio::process::Command;
+
+/// Command output beyond this many characters is left out of results.
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+/// A check of the outcome of a case, listed under `[[case.check]]` in a
+/// suite file.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Check {
+    /// A file in the workspace exists, and contains or equals some text.
+    File {
+        path: String,
+        #[serde(default)]
+        contains: Option<String>,
+        #[serde(default)]
+        equals: Option<String>,
+    },
+    /// The agent's final answer contains some text.
+    Answer { contains: String },
+    /// A shell command run in the workspace exits with a code.
+    Command {
+        command: String,
+        #[serde(default)]
+        exit_code: i32,
+        #[serde(default = "default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// An LLM judge decides whether the final answer, and the contents of
+    /// the given workspace files, meet a rubric.
+    Rubric {
+        rubric: String,
+        #[serde(default)]
+        files: Vec<String>,
+    },
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::File {
+                path,
+                contains: Some(text),
+                ..
+            } => write!(f, "{} contains {:?}", path, text),
+            Self::File {
+                path,
+                equals: Some(_),
+                ..
+            } => write!(f, "{} has the expected content", path),
+            Self::File { path, .. } => write!(f, "{} exists", path),
+            Self::Answer { contains } => write!(f, "the answer contains {:?}", contains),
+            Self::Command {
+                command, exit_code, ..
+            } => write!(f, "`{}` exits with {}", command, exit_code),
+            Self::Rubric { rubric, .. } => write!(f, "judged: {}", rubric),
+        }
+    }
+}
+
+/// The outcome of a check.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    /// What was checked, as shown in reports.
+    pub check: String,
+    pub passed: bool,
+    /// Why the check failed, or the judge's reasoning.
+    pub detail: Option<String>,
+}
+
+/// The judge's verdict on a rubric.
+#[derive(Deserialize)]
+struct Judgement {
+    pass: bool,
+    reason: String,
+}
+
+impl Check {
+    /// Checks the outcome of an attempt at a case.
+    ///
+    /// # Arguments
+    ///
+    /// * `workspace` - The workspace the attempt ran in.
+    /// * `task` - The case's task.
+    /// * `answer` - The final answer, if the attempt finished.
+    /// * `judge` - The LLM that judges rubrics.
+    ///
+    /// # Returns
+    ///
+    /// The result of the check. A check that cannot be carried out, such as
+    /// a command that fails to start, fails with the reason as its detail.
+    pub async fn evaluate(
+        &self,
+        workspace: &Workspace,
+        task: &str,
+        answer: Option<&str>,
+        judge: &(dyn Llm + Send + Sync),
+    ) -> CheckResult {
+        let (passed, detail) = match self.run(workspace, task, answer, judge).await {
+            Ok(outcome) => outcome,
+            Err(e) => (false, Some(e.to_string())),
+        };
+        CheckResult {
+            check: self.to_string(),
+            passed,
+            detail,
+        }
+    }
+
+    async fn run(
+        &self,
+        workspace: &Workspace,
+        task: &str,
+        answer: Option<&str>,
+        judge: &(dyn Llm + Send + Sync),
+    ) -> Result<(bool, Option<String>)> {
+        match self {
+            Self::File {
+                path,
+                contains,
+                equals,
+            } => {
+                let content = fs::read_to_string(workspace.resolve(path)?)
+                    .map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+                if let Some(text) = contains {
+                    if !content.contains(text.as_str()) {
+                        return Ok((false, Some(format!("{} does not contain it", path))));
+                    }
+                }
+                if let Some(expected) = equals {
+                    if content != *expected {
+                        return Ok((false, Some(format!("{} has other content", path))));
+                    }
+                }
+                Ok((true, None))
+            }
+            Self::Answer { contains } => match answer {
+                Some(answer) if answer.contains(contains.as_str()) => Ok((true, None)),
+                Some(answer) => Ok((false, Some(format!("The answer was {:?}", answer)))),
+                None => Ok((false, Some("The agent gave no answer".to_string()))),
+            },
+            Self::Command {
+                command,
+                exit_code,
+                timeout_secs,
+            } => {
+                let child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .current_dir(workspace.root())
+                    .stdin(Stdio::null())
+                    .kill_on_drop(true)
+                    .output();
+                let output = tokio::time::timeout(Duration::from_secs(*timeout_secs), child)
+                    .await
+                    .map_err(|_| anyhow!("Timed out after {} seconds", timeout_secs))??;
+                let code = output.status.code().unwrap_or(-1);
+                if code == *exit_code {
+                    return Ok((true, None));
+                }
+                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                let text = text.trim();
+                let text = match text.char_indices().nth(MAX_OUTPUT_CHARS) {
+                    Some((cut, _)) => format!("{}...", &text[..cut]),
+                    None => text.to_string(),
+                };
+                let detail = match text.as_str() {
+                    "" => format!("Exited with {}", code),
+                    text => format!("Exited with {}: {}", code, text),
+                };
+                Ok((false, Some(detail)))
+            }
+            Self::Rubric { rubric, files } => {
+                let mut evidence = String::new();
+                for path in files {
+                    let content = fs::read_to_string(workspace.resolve(path)?)
+                        .unwrap_or_else(|e| format!("(could not be read: {})", e));
+                    evidence.push_str(&format!("\nThe file {}:\n```\n{}\n```\n", path, content));
+                }
+                let prompt = format!(
+                    "You are grading an agent's work on a task.
+
+The task was: {}
+
+The agent's final answer was: {}
+{}
+The rubric is: {}
+
+Please respond with a JSON object with a boolean `pass`, which is true only if the work meets the rubric, and a one-sentence `reason`.",
+                    task,
+                    answer.unwrap_or("(none, the agent failed)"),
+                    evidence,
+                    rubric
+                );
+                let response = judge.call(&prompt).await?;
+                let judgement: Judgement = serde_json::from_str(&response)
+                    .map_err(|e| anyhow!("The judge gave an invalid verdict: {}", e))?;
+                Ok((judgement.pass, Some(judgement.reason)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockLlm;
+
+    #[tokio::test]
+    async fn test_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("hello.txt"), "hello world\n").unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let judge = MockLlm::new(r#"{"pass": false, "reason": "It is too short."}"#);
+
+        let checks: Vec<Check> = serde_json::from_value(serde_json::json!([
+            { "kind": "file", "path": "hello.txt", "contains": "world" },
+            { "kind": "file", "path": "missing.txt" },
+            { "kind": "answer", "contains": "42" },
+            { "kind": "command", "command": "grep -q hello hello.txt" },
+            { "kind": "command", "command": "exit 3", "exit_code": 1 },
+            { "kind": "rubric", "rubric": "The answer is detailed" },
+        ]))
+        .unwrap();
+        let mut results = Vec::new();
+        for check in &checks {
+            let result = check
+                .evaluate(&workspace, "Greet", Some("41"), &judge)
+                .await;
+            results.push((result.passed, result.check, result.detail));
+        }
+        assert_eq!(
+            results[0],
+            (true, "hello.txt contains \"world\"".into(), None)
+        );
+        assert!(!results[1].0);
+        assert_eq!(
+            results[2],
+            (
+                false,
+                "the answer contains \"42\"".into(),
+                Some("The answer was \"41\"".into())
+            )
+        );
+        assert!(results[3].0);
+        assert_eq!(
+            results[4],
+            (
+                false,
+                "`exit 3` exits with 1".into(),
+                Some("Exited with 3".into())
+            )
+        );
+        assert_eq!(
+            results[5],
+            (
+                false,
+                "judged: The answer is detailed".into(),
+                Some("It is too short.".into())
+            )
+        );
+
+        let unknown = serde_json::from_value::<Check>(
+            serde_json::json!({ "kind": "answer", "contains": "x", "exactly": true }),
+        );
+        assert!(unknown.is_err());
+    }
+}
@@ -0,0 +1,384 @@
+pub mod checks;
+pub mod report;
+
+use crate::{
+    agent::Agent,
+    llm::{Llm, Usage},
+    orchestrator::Orchestrator,
+    run_log::RunLog,
+    tools::workspace::Workspace,
+    trajectory,
+};
+use anyhow::{anyhow, Result};
+use checks::Check;
+use config::{Config, File};
+use report::{Attempt, CaseReport, Report};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+/// The `[eval]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvalConfig {
+    /// The directory each eval run's workspaces, logs and reports are
+    /// written to.
+    pub directory: PathBuf,
+    /// The price of a million prompt tokens in US dollars, for reporting
+    /// costs.
+    pub prompt_token_price: Option<f64>,
+    /// The price of a million completion tokens in US dollars.
+    pub completion_token_price: Option<f64>,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from(".evals"),
+            prompt_token_price: None,
+            completion_token_price: None,
+        }
+    }
+}
+
+impl EvalConfig {
+    /// Returns the cost of `usage` in US dollars, if prices are configured.
+    fn cost(&self, usage: Usage) -> Option<f64> {
+        let prompt = self.prompt_token_price? * usage.prompt_tokens as f64;
+        let completion = self.completion_token_price? * usage.completion_tokens as f64;
+        Some((prompt + completion) / 1_000_000.0)
+    }
+}
+
+/// A suite of cases, loaded from a TOML, YAML or JSON file.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    pub name: String,
+    /// How many times each case is attempted, unless overridden.
+    #[serde(default = "default_repeat")]
+    pub repeat: usize,
+    #[serde(rename = "case")]
+    pub cases: Vec<Case>,
+    /// The directory of the suite file, which fixtures are relative to.
+    #[serde(skip)]
+    pub directory: PathBuf,
+}
+
+fn default_repeat() -> usize {
+    1
+}
+
+/// A task for the agents, with the workspace it starts from and the checks
+/// its outcome must pass.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub name: String,
+    pub task: String,
+    /// A directory copied into the workspace before each attempt. Without
+    /// one, attempts start from an empty workspace.
+    #[serde(default)]
+    pub fixture: Option<PathBuf>,
+    #[serde(default, rename = "check")]
+    pub checks: Vec<Check>,
+}
+
+impl Suite {
+    /// Loads a suite file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the suite, or an error if the file is invalid, a
+    /// case name is repeated or unusable as a directory name, or a fixture
+    /// does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut suite: Suite = Config::builder()
+            .add_source(File::from(path))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .map_err(|e| anyhow!("Invalid suite {}: {}", path.display(), e))?;
+        suite.directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        let mut names = HashSet::new();
+        for case in &suite.cases {
+            if case.name.is_empty()
+                || !case
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(anyhow!(
+                    "Invalid case name {:?}: use letters, digits, '-' and '_'",
+                    case.name
+                ));
+            }
+            if !names.insert(&case.name) {
+                return Err(anyhow!("The case {} is defined twice", case.name));
+            }
+            if let Some(fixture) = &case.fixture {
+                if !suite.directory.join(fixture).is_dir() {
+                    return Err(anyhow!(
+                        "The fixture of case {} is not a directory: {}",
+                        case.name,
+                        fixture.display()
+                    ));
+                }
+            }
+        }
+        Ok(suite)
+    }
+}
+
+/// What a suite is run against and how.
+pub struct EvalOptions<'a> {
+    pub config: &'a EvalConfig,
+    /// The name of the agent configuration, for the report.
+    pub config_name: String,
+    pub model: String,
+    /// Overrides the suite's number of attempts per case.
+    pub repeat: Option<usize>,
+    /// An earlier report of the same suite to compare against.
+    pub baseline: Option<&'a Report>,
+}
+
+/// Runs every case of a suite, attempting each several times.
+///
+/// Each attempt gets a fresh copy of the case's fixture as its workspace,
+/// in `<eval directory>/<suite>-<time>/<case>/<attempt>/workspace`, and
+/// agents built for it by `build`. The attempt's run log and trajectory are
+/// kept next to the workspace, for `trajectory` and `replay`. The report is
+/// written there as `report.json` and `report.md`.
+///
+/// # Arguments
+///
+/// * `suite` - The suite to run.
+/// * `options` - The configuration being evaluated.
+/// * `judge` - The LLM that judges rubric checks.
+/// * `build` - Builds the top agent for a task in a workspace.
+///
+/// # Returns
+///
+/// A `Result` with the report, or an error if a workspace cannot be set up
+/// or the report cannot be written. Agents failing a case is not an error.
+pub async fn run<F, Fut>(
+    suite: &Suite,
+    options: &EvalOptions<'_>,
+    judge: &(dyn Llm + Send + Sync),
+    build: F,
+) -> Result<Report>
+where
+    F: Fn(Workspace, String) -> Fut,
+    Fut: Future<Output = Result<Box<dyn Agent + Send + Sync>>>,
+{
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let directory = options
+        .config
+        .directory
+        .join(format!("{}-{}", suite.name, started_at));
+    let repeat = options.repeat.unwrap_or(suite.repeat).max(1);
+
+    let mut cases = Vec::new();
+    for case in &suite.cases {
+        let mut attempts = Vec::new();
+        for attempt in 1..=repeat {
+            info!("Eval case {}, attempt {} of {}", case.name, attempt, repeat);
+            let attempt_directory = directory.join(&case.name).join(attempt.to_string());
+            attempts.push(
+                run_attempt(
+                    suite,
+                    case,
+                    &attempt_directory,
+                    options.config,
+                    judge,
+                    &build,
+                )
+                .await?,
+            );
+        }
+        cases.push(CaseReport::new(&case.name, &case.task, attempts));
+    }
+
+    let report = Report::new(
+        &suite.name,
+        &options.config_name,
+        &options.model,
+        started_at,
+        repeat,
+        cases,
+    );
+    fs::write(
+        directory.join("report.json"),
+        serde_json::to_string_pretty(&report)?,
+    )?;
+    fs::write(
+        directory.join("report.md"),
+        report.markdown(options.baseline),
+    )?;
+    Ok(report)
+}
+
+async fn run_attempt<F, Fut>(
+    suite: &Suite,
+    case: &Case,
+    directory: &Path,
+    config: &EvalConfig,
+    judge: &(dyn Llm + Send + Sync),
+    build: &F,
+) -> Result<Attempt>
+where
+    F: Fn(Workspace, String) -> Fut,
+    Fut: Future<Output = Result<Box<dyn Agent + Send + Sync>>>,
+{
+    let root = directory.join("workspace");
+    fs::create_dir_all(&root)?;
+    if let Some(fixture) = &case.fixture {
+        copy_dir(&suite.directory.join(fixture), &root)?;
+    }
+    let workspace = Workspace::new(&root)?;
+
+    let agent = build(workspace.clone(), case.task.clone()).await?;
+    let run_log = Arc::new(RunLog::create(directory, &case.task)?);
+    let orchestrator = Orchestrator::new(agent).with_run_log(run_log.clone());
+    let started = Instant::now();
+    let result = orchestrator.run(&case.task).await;
+    let seconds = started.elapsed().as_secs_f64();
+
+    let trajectory = trajectory::load(directory, run_log.id())?;
+    let answer = result.as_ref().ok().cloned();
+    let mut checks = Vec::new();
+    for check in &case.checks {
+        checks.push(
+            check
+                .evaluate(&workspace, &case.task, answer.as_deref(), judge)
+                .await,
+        );
+    }
+    Ok(Attempt {
+        passed: result.is_ok() && checks.iter().all(|c| c.passed),
+        answer,
+        error: result.err().map(|e| e.to_string()),
+        checks,
+        seconds,
+        llm_calls: trajectory.llm_calls,
+        usage: trajectory.usage,
+        cost: config.cost(trajectory.usage),
+        directory: directory.to_path_buf(),
+    })
+}
+
+/// Copies the contents of the directory `from` into `to`.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{executor::ExecutorAgent, llm::MockLlm};
+
+    #[tokio::test]
+    async fn test_run_suite() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("fixtures/greeting")).unwrap();
+        fs::write(dir.path().join("fixtures/greeting/hello.txt"), "hello").unwrap();
+        fs::write(
+            dir.path().join("suite.toml"),
+            r#"
+name = "basics"
+repeat = 2
+
+[[case]]
+name = "answer"
+task = "Say 42"
+fixture = "fixtures/greeting"
+
+[[case.check]]
+kind = "answer"
+contains = "42"
+
+[[case.check]]
+kind = "command"
+command = "test -f hello.txt"
+
+[[case]]
+name = "missing-file"
+task = "Write out.txt"
+
+[[case.check]]
+kind = "file"
+path = "out.txt"
+"#,
+        )
+        .unwrap();
+        let suite = Suite::load(&dir.path().join("suite.toml")).unwrap();
+
+        let config = EvalConfig {
+            directory: dir.path().join(".evals"),
+            ..EvalConfig::default()
+        };
+        let options = EvalOptions {
+            config: &config,
+            config_name: "config.toml".to_string(),
+            model: "mock".to_string(),
+            repeat: None,
+            baseline: None,
+        };
+        let judge = MockLlm::new("{}");
+        let report = run(
+            &suite,
+            &options,
+            &judge,
+            |workspace: Workspace, _task| async move {
+                assert!(workspace.root().ends_with("workspace"));
+                let finish = r#"{"thought": "Easy.", "action": {"tool": "Finish", "args": "42"}}"#;
+                let agent =
+                    ExecutorAgent::new(Box::new(MockLlm::new(finish)), Vec::new(), "Worker", "");
+                Ok(Box::new(agent) as Box<dyn Agent + Send + Sync>)
+            },
+        )
+        .await
+        .unwrap();
+
+        let summary: Vec<(&str, usize, f64)> = report
+            .cases
+            .iter()
+            .map(|c| (c.name.as_str(), c.passes, c.pass_at_k[&2]))
+            .collect();
+        assert_eq!(summary, [("answer", 2, 1.0), ("missing-file", 0, 0.0)]);
+        assert_eq!(report.pass_at_k[&1], 0.5);
+        assert_eq!(report.cases[0].attempts[0].llm_calls, 1);
+
+        let directory = report.cases[1].attempts[1]
+            .directory
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap();
+        let json = fs::read_to_string(directory.join("report.json")).unwrap();
+        let written: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            (written.suite, written.cases.len()),
+            ("basics".to_string(), 2)
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use super::checks::CheckResult;
+use crate::llm::Usage;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Write, path::PathBuf};
+
+/// One run of a case's task.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attempt {
+    /// Whether the run finished and every check passed.
+    pub passed: bool,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+    pub checks: Vec<CheckResult>,
+    pub seconds: f64,
+    pub llm_calls: usize,
+    pub usage: Usage,
+    /// The cost in US dollars, if token prices are configured.
+    pub cost: Option<f64>,
+    /// The directory with the attempt's workspace, run log and trajectory.
+    pub directory: PathBuf,
+}
+
+/// The attempts at a case and their statistics.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CaseReport {
+    pub name: String,
+    pub task: String,
+    pub attempts: Vec<Attempt>,
+    pub passes: usize,
+    /// The chance that at least one of `k` attempts passes, for each `k` up
+    /// to the number of attempts.
+    pub pass_at_k: BTreeMap<usize, f64>,
+    pub latency: Latency,
+    pub mean_tokens: f64,
+    pub mean_cost: Option<f64>,
+}
+
+/// How long attempts took, in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Latency {
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+/// The results of running a suite against an agent configuration.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub suite: String,
+    /// The configuration file the agents were built from.
+    pub config: String,
+    pub model: String,
+    /// When the suite was run, in seconds since the Unix epoch.
+    pub started_at: u64,
+    pub repeat: usize,
+    pub cases: Vec<CaseReport>,
+    /// Each `pass@k`, averaged over the cases.
+    pub pass_at_k: BTreeMap<usize, f64>,
+    pub latency: Latency,
+    pub total_tokens: u64,
+    pub total_cost: Option<f64>,
+}
+
+impl CaseReport {
+    pub fn new(name: &str, task: &str, attempts: Vec<Attempt>) -> Self {
+        let n = attempts.len();
+        let passes = attempts.iter().filter(|a| a.passed).count();
+        let seconds: Vec<f64> = attempts.iter().map(|a| a.seconds).collect();
+        let tokens: Vec<f64> = attempts.iter().map(|a| total(a.usage) as f64).collect();
+        let costs: Option<Vec<f64>> = attempts.iter().map(|a| a.cost).collect();
+        Self {
+            name: name.to_string(),
+            task: task.to_string(),
+            passes,
+            pass_at_k: (1..=n).map(|k| (k, pass_at_k(n, passes, k))).collect(),
+            latency: Latency::of(&seconds),
+            mean_tokens: mean(&tokens),
+            mean_cost: costs.map(|costs| mean(&costs)),
+            attempts,
+        }
+    }
+}
+
+impl Report {
+    pub fn new(
+        suite: &str,
+        config: &str,
+        model: &str,
+        started_at: u64,
+        repeat: usize,
+        cases: Vec<CaseReport>,
+    ) -> Self {
+        let mut pass_at_k = BTreeMap::new();
+        for k in 1..=repeat {
+            let values: Vec<f64> = cases
+                .iter()
+                .filter_map(|c| c.pass_at_k.get(&k))
+                .copied()
+                .collect();
+            pass_at_k.insert(k, mean(&values));
+        }
+        let attempts = || cases.iter().flat_map(|c| &c.attempts);
+        let seconds: Vec<f64> = attempts().map(|a| a.seconds).collect();
+        Self {
+            suite: suite.to_string(),
+            config: config.to_string(),
+            model: model.to_string(),
+            started_at,
+            repeat,
+            pass_at_k,
+            latency: Latency::of(&seconds),
+            total_tokens: attempts().map(|a| total(a.usage)).sum(),
+            total_cost: attempts().map(|a| a.cost).sum(),
+            cases,
+        }
+    }
+
+    /// Renders the report as Markdown: a table of the cases, followed by
+    /// the checks that failed. With a `baseline` report of the same suite,
+    /// the table also shows how each case's pass@1 changed.
+    pub fn markdown(&self, baseline: Option<&Report>) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "# Eval: {}\n", self.suite);
+        let _ = writeln!(
+            output,
+            "Configuration `{}` with model `{}`, {} attempts per case.\n",
+            self.config, self.model, self.repeat
+        );
+
+        // pass@k is only shown for more than one attempt, where it differs
+        // from pass@1.
+        let k = self.repeat;
+        let mut header = "| Case | Passed | pass@1 |".to_string();
+        if k > 1 {
+            let _ = write!(header, " pass@{} |", k);
+        }
+        if baseline.is_some() {
+            header.push_str(" Baseline pass@1 |");
+        }
+        header.push_str(" Mean time | p90 time | Mean tokens | Mean cost |");
+        let columns = header.matches('|').count() - 1;
+        let _ = writeln!(output, "{}", header);
+        let _ = writeln!(output, "|{}", "---|".repeat(columns));
+        for case in &self.cases {
+            let mut row = format!(
+                "| {} | {}/{} | {:.2} |",
+                case.name,
+                case.passes,
+                case.attempts.len(),
+                case.pass_at_k.get(&1).copied().unwrap_or(0.0),
+            );
+            if k > 1 {
+                let _ = write!(
+                    row,
+                    " {:.2} |",
+                    case.pass_at_k.get(&k).copied().unwrap_or(0.0)
+                );
+            }
+            if let Some(baseline) = baseline {
+                let before = baseline
+                    .cases
+                    .iter()
+                    .find(|c| c.name == case.name)
+                    .and_then(|c| c.pass_at_k.get(&1));
+                match before {
+                    Some(before) => {
+                        let _ = write!(
+                            row,
+                            " {:.2} ({:+.2}) |",
+                            before,
+                            case.pass_at_k[&1] - before
+                        );
+                    }
+                    None => row.push_str(" - |"),
+                }
+            }
+            let _ = writeln!(
+                output,
+                "{} {:.1}s | {:.1}s | {:.0} | {} |",
+                row,
+                case.latency.mean,
+                case.latency.p90,
+                case.mean_tokens,
+                dollars(case.mean_cost)
+            );
+        }
+        let mut overall = format!(
+            "pass@1 {:.2}",
+            self.pass_at_k.get(&1).copied().unwrap_or(0.0)
+        );
+        if k > 1 {
+            let _ = write!(
+                overall,
+                ", pass@{} {:.2}",
+                k,
+                self.pass_at_k.get(&k).copied().unwrap_or(0.0)
+            );
+        }
+        let _ = writeln!(
+            output,
+            "\n**Overall:** {}, mean time {:.1}s, {} tokens, cost {}.",
+            overall,
+            self.latency.mean,
+            self.total_tokens,
+            dollars(self.total_cost)
+        );
+        if let Some(baseline) = baseline {
+            let _ = writeln!(
+                output,
+                "Baseline `{}` with model `{}`: pass@1 {:.2}.",
+                baseline.config,
+                baseline.model,
+                baseline.pass_at_k.get(&1).copied().unwrap_or(0.0)
+            );
+        }
+
+        let mut failures = String::new();
+        for case in &self.cases {
+            for (i, attempt) in case.attempts.iter().enumerate() {
+                if attempt.passed {
+                    continue;
+                }
+                let _ = writeln!(failures, "\n### {}, attempt {}\n", case.name, i + 1);
+                if let Some(error) = &attempt.error {
+                    let _ = writeln!(failures, "- The run failed: {}", error);
+                }
+                for check in attempt.checks.iter().filter(|c| !c.passed) {
+                    match &check.detail {
+                        Some(detail) => {
+                            let _ = writeln!(failures, "- Failed {}: {}", check.check, detail);
+                        }
+                        None => {
+                            let _ = writeln!(failures, "- Failed {}", check.check);
+                        }
+                    }
+                }
+                let _ = writeln!(failures, "- Files: `{}`", attempt.directory.display());
+            }
+        }
+        if !failures.is_empty() {
+            let _ = write!(output, "\n## Failures\n{}", failures);
+        }
+        output
+    }
+}
+
+impl Latency {
+    fn of(seconds: &[f64]) -> Self {
+        if seconds.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = seconds.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Self {
+            mean: mean(&sorted),
+            median: percentile(&sorted, 0.5),
+            p90: percentile(&sorted, 0.9),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// The unbiased estimate of the chance that at least one of `k` attempts
+/// passes, given that `c` of `n` attempts passed.
+pub fn pass_at_k(n: usize, c: usize, k: usize) -> f64 {
+    if n - c < k {
+        return 1.0;
+    }
+    // 1 - C(n - c, k) / C(n, k), computed as a product to avoid overflow.
+    1.0 - (n - c + 1..=n)
+        .map(|i| 1.0 - k as f64 / i as f64)
+        .product::<f64>()
+}
+
+/// The nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn total(usage: Usage) -> u64 {
+    usage.prompt_tokens + usage.completion_tokens
+}
+
+fn dollars(cost: Option<f64>) -> String {
+    match cost {
+        Some(cost) => format!("${:.4}", cost),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statistics_and_markdown() {
+        assert_eq!(pass_at_k(3, 0, 1), 0.0);
+        assert_eq!(pass_at_k(3, 3, 3), 1.0);
+        assert!((pass_at_k(3, 1, 1) - 1.0 / 3.0).abs() < 1e-9);
+        assert!((pass_at_k(3, 1, 2) - 2.0 / 3.0).abs() < 1e-9);
+        assert!((pass_at_k(5, 2, 2) - 0.7).abs() < 1e-9);
+
+        let attempt = |passed: bool, seconds: f64| Attempt {
+            passed,
+            answer: Some("done".to_string()),
+            error: None,
+            checks: vec![CheckResult {
+                check: "the answer contains \"42\"".to_string(),
+                passed,
+                detail: (!passed).then(|| "The answer was \"done\"".to_string()),
+            }],
+            seconds,
+            llm_calls: 2,
+            usage: Usage {
+                prompt_tokens: 100,
+                completion_tokens: 20,
+            },
+            cost: None,
+            directory: PathBuf::from(".evals/run/answer/2"),
+        };
+        let case = CaseReport::new(
+            "answer",
+            "Answer",
+            vec![attempt(true, 1.0), attempt(false, 3.0)],
+        );
+        assert_eq!(case.latency.median, 1.0);
+        let report = Report::new("basics", "config.toml", "gpt-4o", 0, 2, vec![case]);
+        let mut baseline = report.clone();
+        baseline.cases[0].pass_at_k.insert(1, 0.25);
+        assert_eq!(
+            report.markdown(Some(&baseline)),
+            "# Eval: basics
+
+Configuration `config.toml` with model `gpt-4o`, 2 attempts per case.
+
+| Case | Passed | pass@1 | pass@2 | Baseline pass@1 | Mean time | p90 time | Mean tokens | Mean cost |
+|---|---|---|---|---|---|---|---|---|
+| answer | 1/2 | 0.50 | 1.00 | 0.25 (+0.25) | 2.0s | 3.0s | 120 | - |
+
+**Overall:** pass@1 0.50, pass@2 1.00, mean time 2.0s, 240 tokens, cost -.
+Baseline `config.toml` with model `gpt-4o`: pass@1 0.50.
+
+## Failures
+
+### answer, attempt 2
+
+- Failed the answer contains \"42\": The answer was \"done\"
+- Files: `.evals/run/answer/2`
+"
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, time::Duration};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use serde::Deserialize;
 use tracing::info;
@@ -8,6 +8,7 @@ use tracing::info;
 use crate::{
     agent::Agent,
     llm::Llm,
+    run_log::{self, Event},
     tools::Tool,
 };
 
@@ -46,6 +47,9 @@ pub struct ExecutorAgent {
     tools: HashMap<String, Box<dyn Tool + Send + Sync>>,
     name: String,
     description: String,
+    system_prompt: Option<String>,
+    max_steps: Option<usize>,
+    timeout: Option<Duration>,
 }
 
 impl ExecutorAgent {
@@ -77,9 +81,32 @@ impl ExecutorAgent {
             tools: tool_map,
             name: name.to_string(),
             description: description.to_string(),
+            system_prompt: None,
+            max_steps: None,
+            timeout: None,
         }
     }
 
+    /// Opens the agent's prompt with `system_prompt` instead of the default
+    /// "You are a helpful assistant."
+    pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
+        self.system_prompt = Some(system_prompt.to_string());
+        self
+    }
+
+    /// Fails the task if the agent has not finished after `max_steps` LLM
+    /// calls.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Fails the task if the agent has not finished within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Constructs the initial prompt for the agent.
     ///
     /// This function creates a detailed prompt that includes the task, the
@@ -93,17 +120,22 @@ impl ExecutorAgent {
     ///
     /// A string containing the initial prompt.
     fn construct_initial_prompt(&self, task: &str) -> String {
-        let tool_names = self
-            .tools
-            .keys()
-            .map(|s| s.as_str())
-            .collect::<Vec<&str>>()
-            .join(", ");
+        let mut tools = self.tools.values().collect::<Vec<_>>();
+        tools.sort_by_key(|tool| tool.name());
+        let tool_list = tools
+            .iter()
+            .map(|tool| match tool.description() {
+                description if description.is_empty() => format!("- {}", tool.name()),
+                description => format!("- {}: {}", tool.name(), description),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
 
         format!(
-            "You are a helpful assistant. Your task is to {}.
+            "{} Your task is to {}.
 
-You have the following tools available: {}.
+You have the following tools available:
+{}
 
 Please respond with a JSON object containing your `thought` and the `action` you want to take. The `action` should have a `tool` and `args`.
 
@@ -119,9 +151,71 @@ Example:
 ```
 
 If you have completed the task, use the `Finish` tool with the final answer.",
-            task, tool_names
+            self.system_prompt
+                .as_deref()
+                .unwrap_or("You are a helpful assistant."),
+            task,
+            tool_list
         )
     }
+
+    /// Runs the ReAct loop until the LLM chooses the `Finish` tool or the
+    /// step limit is reached.
+    async fn react(&self, task: &str) -> Result<String> {
+        let mut prompt = self.construct_initial_prompt(task);
+        let mut steps = 0;
+        loop {
+            if self.max_steps.is_some_and(|max_steps| steps >= max_steps) {
+                return Err(anyhow!(
+                    "{} did not finish within {} steps",
+                    self.name,
+                    steps
+                ));
+            }
+            steps += 1;
+            info!("---PROMPT---\n{}---END---\n", prompt);
+
+            let llm_response =
+                run_log::llm(&self.name, &prompt, self.llm.complete(&prompt)).await?;
+            let thought: Thought = serde_json::from_str(&llm_response)?;
+            run_log::record(Event::Thought {
+                agent: self.name.clone(),
+                thought: thought.thought.clone(),
+                tool: thought.action.tool.clone(),
+                args: thought.action.args.clone(),
+            })?;
+
+            info!("---THOUGHT---\n{}---END---\n", thought.thought);
+            info!(
+                "---ACTION---\nTool: {}, Args: {}---END---\n",
+                thought.action.tool, thought.action.args
+            );
+
+            if thought.action.tool == "Finish" {
+                return Ok(thought.action.args);
+            }
+
+            let tool = self
+                .tools
+                .get(&thought.action.tool)
+                .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", thought.action.tool))?;
+
+            let observation = run_log::tool(
+                &self.name,
+                &thought.action.tool,
+                &thought.action.args,
+                tool.execute(&thought.action.args),
+            )
+            .await?;
+
+            info!("---OBSERVATION---\n{}---END---\n", observation);
+
+            prompt = format!(
+                "{}\nThought: {}\nAction: {}\nObservation: {}",
+                prompt, thought.thought, llm_response, observation
+            );
+        }
+    }
 }
 
 #[async_trait]
@@ -143,6 +237,11 @@ impl Agent for ExecutorAgent {
     /// 4. The result of the action (`Observation`) is added to the prompt history.
     /// 5. The loop continues until the LLM outputs a "Finish" action.
     ///
+    /// Within a run that has a `RunLog`, each step is recorded, and a resumed
+    /// run replays the recorded steps instead of repeating them.
+    ///
+    /// The task fails if the agent reaches its step limit or timeout first.
+    ///
     /// # Arguments
     ///
     /// * `task` - The task for the agent to solve.
@@ -153,36 +252,24 @@ impl Agent for ExecutorAgent {
     /// error if something goes wrong.
     #[tracing::instrument(skip(self))]
     async fn run(&self, task: &str) -> Result<String> {
-        let mut prompt = self.construct_initial_prompt(task);
-        loop {
-            info!("---PROMPT---\n{}---END---\n", prompt);
-
-            let llm_response = self.llm.call(&prompt).await?;
-            let thought: Thought = serde_json::from_str(&llm_response)?;
-
-            info!("---THOUGHT---\n{}---END---\n", thought.thought);
-            info!(
-                "---ACTION---\nTool: {}, Args: {}---END---\n",
-                thought.action.tool, thought.action.args
-            );
-
-            if thought.action.tool == "Finish" {
-                return Ok(thought.action.args);
-            }
-
-            let tool = self
-                .tools
-                .get(&thought.action.tool)
-                .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", thought.action.tool))?;
-
-            let observation = tool.execute(&thought.action.args).await?;
-
-            info!("---OBSERVATION---\n{}---END---\n", observation);
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.react(task))
+                .await
+                .map_err(|_| {
+                    anyhow!(
+                        "{} did not finish within {} seconds",
+                        self.name,
+                        timeout.as_secs()
+                    )
+                })?,
+            None => self.react(task).await,
+        }
+    }
 
-            prompt = format!(
-                "{}\nThought: {}\nAction: {}\nObservation: {}",
-                prompt, thought.thought, llm_response, observation
-            );
+    /// Shuts down all of the agent's tools.
+    async fn shutdown(&self) {
+        for tool in self.tools.values() {
+            tool.shutdown().await;
         }
     }
 }
@@ -192,7 +279,6 @@ mod tests {
     use super::*;
     use crate::{llm::MockLlm, tools::Tool};
     use serde_json::json;
-    use std::sync::Arc;
 
     // A mock tool for testing
     struct MockTool;
@@ -217,7 +303,7 @@ mod tests {
         })
         .to_string();
 
-        let llm = Arc::new(MockLlm::new(&response));
+        let llm = Box::new(MockLlm::new(&response));
         let tools: Vec<Box<dyn Tool + Send + Sync>> = vec![Box::new(MockTool)];
         let agent = ExecutorAgent::new(llm, tools, "TestExecutor", "A test executor agent");
 
@@ -226,4 +312,28 @@ mod tests {
 
         assert_eq!(result, "test");
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_executor_agent_step_limit() {
+        let response = json!({
+            "thought": "I should use the MockTool again.",
+            "action": {
+                "tool": "MockTool",
+                "args": "again"
+            }
+        })
+        .to_string();
+
+        let llm = Box::new(MockLlm::new(&response));
+        let tools: Vec<Box<dyn Tool + Send + Sync>> = vec![Box::new(MockTool)];
+        let agent = ExecutorAgent::new(llm, tools, "TestExecutor", "A test executor agent")
+            .with_max_steps(3);
+
+        let error = agent.run("Use the mock tool").await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "TestExecutor did not finish within 3 steps"
+        );
+    }
+}
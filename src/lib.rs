@@ -0,0 +1,21 @@
+//! A framework for multi-agent systems, in which supervisor agents route
+//! tasks to executor agents that work on them with tools.
+//!
+//! The `app` binary runs the command line with the built-in tools. To add
+//! tools of your own, implement `tools::registry::RegisteredTool` for them
+//! in a crate that depends on this one, and define its `main` with
+//! `app::main!`.
+
+pub mod agent;
+pub mod cli;
+pub mod eval;
+pub mod executor;
+pub mod llm;
+pub mod mcp_server;
+pub mod memory;
+pub mod orchestrator;
+pub mod run_log;
+pub mod supervisor;
+pub mod team;
+pub mod tools;
+pub mod trajectory;
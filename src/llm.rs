@@ -23,6 +23,28 @@ pub trait Llm {
     /// A `Result` containing the LLM's response as a string, or an error if
     /// the call fails.
     async fn call(&self, prompt: &str) -> Result<String>;
+
+    /// Like `call`, but also returns the number of tokens the call used, if
+    /// the LLM reports it. The default implementation reports no usage.
+    async fn complete(&self, prompt: &str) -> Result<LlmResponse> {
+        Ok(LlmResponse {
+            content: self.call(prompt).await?,
+            usage: None,
+        })
+    }
+}
+
+/// The number of tokens an LLM call used.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// An LLM's response and the tokens it used.
+pub struct LlmResponse {
+    pub content: String,
+    pub usage: Option<Usage>,
 }
 
 /// An implementation of the `Llm` trait that connects to the OpenAI API.
@@ -70,6 +92,12 @@ impl Llm for OpenAiLlm {
     ///
     /// A `Result` containing the LLM's response, or an error if the request fails.
     async fn call(&self, prompt: &str) -> Result<String> {
+        Ok(self.complete(prompt).await?.content)
+    }
+
+    /// Sends a prompt to the OpenAI API and returns the response along with
+    /// the token usage the API reports.
+    async fn complete(&self, prompt: &str) -> Result<LlmResponse> {
         let response = self
             .client
             .post("https://api.openai.com/v1/chat/completions")
@@ -92,7 +120,10 @@ impl Llm for OpenAiLlm {
 
         if response.status().is_success() {
             let completion: Completion = response.json().await?;
-            Ok(completion.choices[0].message.content.clone())
+            Ok(LlmResponse {
+                content: completion.choices[0].message.content.clone(),
+                usage: completion.usage,
+            })
         } else {
             let error_body = response.text().await?;
             Err(anyhow!("API call failed: {}", error_body))
@@ -104,6 +135,8 @@ impl Llm for OpenAiLlm {
 #[derive(Deserialize)]
 struct Completion {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
 }
 
 /// Represents a single "choice" or response generated by the LLM.
@@ -142,4 +175,4 @@ impl Llm for MockLlm {
     async fn call(&self, _prompt: &str) -> Result<String> {
         Ok(self.response.clone())
     }
-}
\ No newline at end of file
+}
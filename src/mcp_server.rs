@@ -0,0 +1,291 @@
+//! Serves agents and tools over the Model Context Protocol, so that other
+//! MCP clients can call them as tools.
+
+use crate::tools::{
+    jsonrpc::{Request, Response, METHOD_NOT_FOUND},
+    mcp::PROTOCOL_VERSION,
+    Tool,
+};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::{collections::HashSet, future::Future};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, info};
+
+/// The error code for a message that is not JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// The error code for params the method cannot take.
+const INVALID_PARAMS: i64 = -32602;
+
+/// The protocol versions the server speaks, newest first.
+const PROTOCOL_VERSIONS: [&str; 2] = [PROTOCOL_VERSION, "2024-11-05"];
+
+/// An agent the server offers as a tool that takes a task.
+pub struct ServedAgent {
+    pub name: String,
+    pub description: String,
+}
+
+/// What the server offers: agents and tools, each under its own name.
+pub struct McpServer<F> {
+    agents: Vec<ServedAgent>,
+    tools: Vec<Box<dyn Tool + Send + Sync>>,
+    run: F,
+}
+
+impl<F, Fut> McpServer<F>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    /// Creates a server.
+    ///
+    /// # Arguments
+    ///
+    /// * `agents` - The agents to offer.
+    /// * `tools` - The tools to offer, which are shut down when the server
+    ///   stops.
+    /// * `run` - Runs the agent with the given name on a task.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the server, or an error if two of the agents and
+    /// tools have the same name.
+    pub fn new(
+        agents: Vec<ServedAgent>,
+        tools: Vec<Box<dyn Tool + Send + Sync>>,
+        run: F,
+    ) -> Result<Self> {
+        let mut names = HashSet::new();
+        let all = agents
+            .iter()
+            .map(|agent| agent.name.as_str())
+            .chain(tools.iter().map(|tool| tool.name()));
+        for name in all {
+            if !names.insert(name) {
+                return Err(anyhow!("More than one agent or tool is named {}", name));
+            }
+        }
+        Ok(Self { agents, tools, run })
+    }
+
+    /// Answers requests, one message per line, until `input` ends.
+    ///
+    /// Requests are answered in the order they arrive, so a call to an
+    /// agent holds up the requests after it until the agent finishes.
+    pub async fn serve(
+        &self,
+        input: impl AsyncBufRead + Unpin,
+        mut output: impl AsyncWrite + Unpin,
+    ) -> Result<()> {
+        let mut lines = input.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => match request.id.clone() {
+                    Some(id) => Some(self.respond(id, request).await),
+                    None => {
+                        debug!("Received the notification {}", request.method);
+                        None
+                    }
+                },
+                // Responses to requests the server never sends.
+                Err(_) if serde_json::from_str::<Response>(&line).is_ok() => None,
+                Err(e) => Some(Response::error(
+                    Value::Null,
+                    PARSE_ERROR,
+                    &format!("Invalid request: {}", e),
+                )),
+            };
+            if let Some(response) = response {
+                let mut line = serde_json::to_string(&response)?;
+                line.push('\n');
+                output.write_all(line.as_bytes()).await?;
+                output.flush().await?;
+            }
+        }
+        for tool in &self.tools {
+            tool.shutdown().await;
+        }
+        Ok(())
+    }
+
+    async fn respond(&self, id: Value, request: Request) -> Response {
+        match request.method.as_str() {
+            "initialize" => {
+                let requested = request.params["protocolVersion"].as_str();
+                let version = PROTOCOL_VERSIONS
+                    .into_iter()
+                    .find(|version| Some(*version) == requested)
+                    .unwrap_or(PROTOCOL_VERSION);
+                Response::result(
+                    id,
+                    json!({
+                        "protocolVersion": version,
+                        "capabilities": { "tools": {} },
+                        "serverInfo": {
+                            "name": env!("CARGO_PKG_NAME"),
+                            "version": env!("CARGO_PKG_VERSION"),
+                        },
+                    }),
+                )
+            }
+            "ping" => Response::result(id, json!({})),
+            "tools/list" => Response::result(id, json!({ "tools": self.list() })),
+            "tools/call" => {
+                let name = request.params["name"].as_str().unwrap_or_default();
+                let arguments = match &request.params["arguments"] {
+                    Value::Object(arguments) => arguments.clone(),
+                    _ => Map::new(),
+                };
+                match self.call(name, arguments).await {
+                    Some(output) => {
+                        let (text, is_error) = match output {
+                            Ok(text) => (text, false),
+                            Err(e) => (e.to_string(), true),
+                        };
+                        Response::result(
+                            id,
+                            json!({
+                                "content": [{ "type": "text", "text": text }],
+                                "isError": is_error,
+                            }),
+                        )
+                    }
+                    None => {
+                        Response::error(id, INVALID_PARAMS, &format!("Unknown tool {:?}", name))
+                    }
+                }
+            }
+            method => Response::error(id, METHOD_NOT_FOUND, &format!("Unknown method {}", method)),
+        }
+    }
+
+    /// Describes the agents, which take a `task`, and the tools, which take
+    /// their `args`.
+    fn list(&self) -> Vec<Value> {
+        let agents = self.agents.iter().map(|agent| {
+            let description = match agent.description.as_str() {
+                "" => format!("Runs the {} agent on a task.", agent.name),
+                description => description.to_string(),
+            };
+            json!({
+                "name": agent.name,
+                "description": description,
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "task": {
+                            "type": "string",
+                            "description": "The task for the agent to perform.",
+                        },
+                    },
+                    "required": ["task"],
+                },
+            })
+        });
+        let tools = self.tools.iter().map(|tool| {
+            json!({
+                "name": tool.name(),
+                "description": tool.description(),
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "args": {
+                            "type": "string",
+                            "description": "The tool's arguments, as its description explains.",
+                        },
+                    },
+                },
+            })
+        });
+        agents.chain(tools).collect()
+    }
+
+    /// Runs the agent or tool with the given name, or returns `None` if
+    /// there is none.
+    async fn call(&self, name: &str, arguments: Map<String, Value>) -> Option<Result<String>> {
+        let argument = |key: &str| match arguments.get(key) {
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+        if self.agents.iter().any(|agent| agent.name == name) {
+            let task = argument("task");
+            info!("Running {} on the task: {}", name, task);
+            return Some((self.run)(name.to_string(), task).await);
+        }
+        let tool = self.tools.iter().find(|tool| tool.name() == name)?;
+        Some(tool.execute(&argument("args")).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{file_reader::FileReaderTool, workspace::Workspace};
+
+    #[tokio::test]
+    async fn test_serve_agents_and_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "Some notes.").unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let server = McpServer::new(
+            vec![ServedAgent {
+                name: "Lead".to_string(),
+                description: String::new(),
+            }],
+            vec![Box::new(FileReaderTool::new(workspace))],
+            |agent, task| async move { Ok(format!("{} did: {}", agent, task)) },
+        )
+        .unwrap();
+
+        let requests = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": { "protocolVersion": "2024-11-05", "capabilities": {} } }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                    "params": { "name": "Lead", "arguments": { "task": "Say hi" } } }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/call",
+                    "params": { "name": "FileReaderTool", "arguments": { "args": "notes.txt" } } }),
+            json!({ "jsonrpc": "2.0", "id": 5, "method": "tools/call",
+                    "params": { "name": "FileReaderTool", "arguments": { "args": "missing.txt" } } }),
+            json!({ "jsonrpc": "2.0", "id": 6, "method": "tools/call",
+                    "params": { "name": "Nope", "arguments": {} } }),
+            json!({ "jsonrpc": "2.0", "id": 7, "method": "resources/list" }),
+        ];
+        let input: String = requests
+            .iter()
+            .map(|request| format!("{}\n", request))
+            .collect();
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+
+        let responses: Vec<Response> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 7);
+        let result = |i: usize| responses[i].result.clone().unwrap();
+        assert_eq!(result(0)["protocolVersion"], "2024-11-05");
+        let tools = result(1)["tools"].clone();
+        assert_eq!(
+            (&tools[0]["name"], &tools[0]["description"]),
+            (&json!("Lead"), &json!("Runs the Lead agent on a task."))
+        );
+        assert_eq!(tools[1]["name"], "FileReaderTool");
+        assert_eq!(
+            result(2),
+            json!({ "content": [{ "type": "text", "text": "Lead did: Say hi" }], "isError": false })
+        );
+        assert_eq!(result(3)["content"][0]["text"], "Some notes.");
+        assert_eq!(result(4)["isError"], true);
+        assert_eq!(responses[5].error.as_ref().unwrap().code, INVALID_PARAMS);
+        assert_eq!(responses[6].error.as_ref().unwrap().code, METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::env;
+
+/// A trait for models that turn text into embedding vectors, whose cosine
+/// similarity reflects how related the texts are.
+#[async_trait]
+pub trait Embedder {
+    /// Embeds each of `texts`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with one vector per text, in the same order, or an error
+    /// if the call fails.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// An `Embedder` that calls an OpenAI-compatible `/embeddings` endpoint.
+///
+/// This works with OpenAI itself and with servers that mirror its API, such
+/// as Ollama, vLLM and most hosted inference providers.
+pub struct OpenAiEmbedder {
+    client: Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiEmbedder {
+    /// Creates a new `OpenAiEmbedder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The API's base URL, such as `https://api.openai.com/v1`.
+    /// * `model` - The embedding model, such as `text-embedding-3-small`.
+    /// * `api_key_env` - The environment variable holding the API key. A
+    ///   missing variable sends no key, for local servers that need none.
+    pub fn new(base_url: &str, model: &str, api_key_env: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: env::var(api_key_env).unwrap_or_default(),
+            model: model.to_string(),
+        }
+    }
+}
+
+/// The response of the `/embeddings` endpoint.
+#[derive(Deserialize)]
+struct Embeddings {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&json!({ "model": &self.model, "input": texts }));
+        if !self.api_key.is_empty() {
+            request = request.bearer_auth(&self.api_key);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let error_body = response.text().await?;
+            return Err(anyhow!("Embedding call failed: {}", error_body));
+        }
+        let mut embeddings: Embeddings = response.json().await?;
+        if embeddings.data.len() != texts.len() {
+            return Err(anyhow!(
+                "Expected {} embeddings but received {}",
+                texts.len(),
+                embeddings.data.len()
+            ));
+        }
+        embeddings.data.sort_by_key(|data| data.index);
+        Ok(embeddings.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+/// An `Embedder` that hashes words and word pairs into a fixed number of
+/// dimensions, with no model or network access.
+///
+/// Texts that share words come out similar, which is enough for tests and
+/// offline runs, but unlike a real model it knows nothing of synonyms. The
+/// hash is SHA-256, so vectors stay the same across builds and can be kept
+/// in an on-disk store.
+pub struct HashEmbedder {
+    dimensions: usize,
+}
+
+impl HashEmbedder {
+    /// Creates a new `HashEmbedder` producing vectors of `dimensions`
+    /// values.
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let pairs = words.windows(2).map(|pair| pair.join(" "));
+        let mut vector = vec![0.0; self.dimensions];
+        for feature in words.iter().cloned().chain(pairs) {
+            let hash = Sha256::digest(feature.as_bytes());
+            let bucket = u64::from_le_bytes(hash[..8].try_into().unwrap());
+            // A second bit of the hash picks the sign, so that collisions
+            // cancel out rather than pile up.
+            let sign = if hash[8] & 1 == 0 { 1.0 } else { -1.0 };
+            vector[(bucket % self.dimensions as u64) as usize] += sign;
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+#[async_trait]
+impl Embedder for HashEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// Scales `vector` to unit length, leaving zero vectors alone.
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Returns the cosine similarity of two vectors of the same length, or 0
+/// if either is zero.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
@@ -0,0 +1,243 @@
+//! Long-term memory that agents can store facts in and recall them from by
+//! meaning, across runs.
+
+pub mod embedding;
+pub mod store;
+
+use anyhow::{anyhow, Result};
+use embedding::{Embedder, HashEmbedder, OpenAiEmbedder};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use store::{Match, Record, VectorStore};
+
+/// The `[memory]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryConfig {
+    /// The JSON Lines file memories are kept in. Without one, memories last
+    /// only as long as the process.
+    pub path: Option<PathBuf>,
+    /// The namespace the team's memories are stored under, so that teams
+    /// sharing a store do not see each other's memories.
+    pub namespace: Option<String>,
+    pub embedder: EmbedderConfig,
+}
+
+/// The `[memory.embedder]` section, tagged by `kind`. The document index
+/// is configured the same way in `[index.embedder]`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum EmbedderConfig {
+    /// An OpenAI-compatible `/embeddings` endpoint.
+    OpenAi {
+        #[serde(default = "default_model")]
+        model: String,
+        #[serde(default = "default_base_url")]
+        base_url: String,
+        /// The environment variable holding the API key.
+        #[serde(default = "default_api_key_env")]
+        api_key_env: String,
+    },
+    /// The offline `HashEmbedder`.
+    Hash {
+        #[serde(default = "default_dimensions")]
+        dimensions: usize,
+    },
+}
+
+impl Default for EmbedderConfig {
+    fn default() -> Self {
+        Self::OpenAi {
+            model: default_model(),
+            base_url: default_base_url(),
+            api_key_env: default_api_key_env(),
+        }
+    }
+}
+
+impl EmbedderConfig {
+    /// Creates the configured embedder.
+    pub fn build(&self) -> Arc<dyn Embedder + Send + Sync> {
+        match self {
+            Self::OpenAi {
+                model,
+                base_url,
+                api_key_env,
+            } => Arc::new(OpenAiEmbedder::new(base_url, model, api_key_env)),
+            Self::Hash { dimensions } => Arc::new(HashEmbedder::new(*dimensions)),
+        }
+    }
+}
+
+fn default_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+fn default_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_api_key_env() -> String {
+    "OPENAI_API_KEY".to_string()
+}
+
+fn default_dimensions() -> usize {
+    256
+}
+
+/// A handle on long-term memory, scoped to one namespace.
+///
+/// Handles are cheap to clone, and clones made with `with_namespace` share
+/// the embedder and the store.
+#[derive(Clone)]
+pub struct Memory {
+    embedder: Arc<dyn Embedder + Send + Sync>,
+    store: Arc<dyn VectorStore + Send + Sync>,
+    namespace: String,
+}
+
+impl Memory {
+    /// Creates a new `Memory` in the `default` namespace.
+    pub fn new(
+        embedder: Arc<dyn Embedder + Send + Sync>,
+        store: Arc<dyn VectorStore + Send + Sync>,
+    ) -> Self {
+        Self {
+            embedder,
+            store,
+            namespace: "default".to_string(),
+        }
+    }
+
+    /// Sets up the embedder and store described by the configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the memory, in the configured namespace, or an error
+    /// if the store cannot be opened.
+    pub fn from_config(config: &MemoryConfig) -> Result<Self> {
+        let store = store::open(config.path.as_deref())?;
+        let memory = Self::new(config.embedder.build(), store);
+        Ok(match &config.namespace {
+            Some(namespace) => memory.with_namespace(namespace),
+            None => memory,
+        })
+    }
+
+    /// Returns a handle on the same memory in another namespace.
+    pub fn with_namespace(&self, namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Stores `text`, replacing the memory with the same id.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to remember.
+    /// * `id` - The memory's id. Defaults to a hash of the text, so that
+    ///   remembering the same text twice stores it once.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the memory's id.
+    pub async fn remember(&self, text: &str, id: Option<&str>) -> Result<String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(anyhow!("Cannot remember an empty text"));
+        }
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => format!("{:x}", Sha256::digest(text.as_bytes()))[..12].to_string(),
+        };
+        let embedding = self.embed(text).await?;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.store
+            .upsert(Record {
+                namespace: self.namespace.clone(),
+                id: id.clone(),
+                text: text.to_string(),
+                embedding,
+                created_at,
+                source: None,
+            })
+            .await?;
+        Ok(id)
+    }
+
+    /// Finds the memories most related to `query`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with at most `limit` matches, most similar first.
+    pub async fn recall(&self, query: &str, limit: usize) -> Result<Vec<Match>> {
+        let embedding = self.embed(query).await?;
+        self.store.search(&self.namespace, &embedding, limit).await
+    }
+
+    /// Deletes the memory with `id`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with whether the memory existed.
+    pub async fn forget(&self, id: &str) -> Result<bool> {
+        self.store.delete(&self.namespace, id).await
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedder
+            .embed(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("The embedder returned no embedding"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_persists_by_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MemoryConfig {
+            path: Some(dir.path().join("memory.jsonl")),
+            namespace: Some("research".to_string()),
+            embedder: EmbedderConfig::Hash { dimensions: 64 },
+        };
+        let memory = Memory::from_config(&config).unwrap();
+        memory
+            .remember("The staging database runs Postgres 16", None)
+            .await
+            .unwrap();
+        memory
+            .remember("Deploys happen on Tuesdays", Some("deploys"))
+            .await
+            .unwrap();
+        memory
+            .with_namespace("ops")
+            .remember("The staging database is being migrated", None)
+            .await
+            .unwrap();
+        assert!(memory.forget("deploys").await.unwrap());
+
+        // A new handle replays the file.
+        let memory = Memory::from_config(&config).unwrap();
+        let matches = memory
+            .recall("which database does staging use", 5)
+            .await
+            .unwrap();
+        let texts: Vec<_> = matches.iter().map(|m| m.record.text.as_str()).collect();
+        assert_eq!(texts, ["The staging database runs Postgres 16"]);
+        assert!(matches[0].score > 0.0);
+    }
+}
@@ -0,0 +1,276 @@
+use super::embedding::cosine_similarity;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// A stored memory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The namespace the memory belongs to, such as a team's name.
+    pub namespace: String,
+    /// Identifies the memory within its namespace. Storing a record with
+    /// the same id replaces the old one.
+    pub id: String,
+    pub text: String,
+    pub embedding: Vec<f32>,
+    /// When the memory was stored, in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Where the text was taken from, for records indexed from files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+}
+
+/// The part of a file a record was taken from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Source {
+    /// The file's path, relative to the workspace root.
+    pub path: String,
+    /// The first and last lines, counting from 1.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// The SHA-256 hash of the whole file when it was indexed.
+    pub hash: String,
+}
+
+/// A record found by a search, with its cosine similarity to the query.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub record: Record,
+    pub score: f32,
+}
+
+/// A trait for stores that hold memories and find the ones closest to an
+/// embedding.
+#[async_trait]
+pub trait VectorStore {
+    /// Stores `record`, replacing any record with the same namespace and id.
+    async fn upsert(&self, record: Record) -> Result<()>;
+
+    /// Finds the records in `namespace` most similar to `embedding`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with at most `limit` matches, most similar first, or an
+    /// error if the stored embeddings have a different number of dimensions,
+    /// as happens when the embedding model changes.
+    async fn search(&self, namespace: &str, embedding: &[f32], limit: usize) -> Result<Vec<Match>>;
+
+    /// Deletes the record with `id` from `namespace`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with whether the record existed.
+    async fn delete(&self, namespace: &str, id: &str) -> Result<bool>;
+
+    /// Returns every record in `namespace`.
+    async fn list(&self, namespace: &str) -> Result<Vec<Record>>;
+}
+
+/// Opens a `FileStore` at `path`, or an `InMemoryStore` without one.
+pub fn open(path: Option<&Path>) -> Result<Arc<dyn VectorStore + Send + Sync>> {
+    Ok(match path {
+        Some(path) => Arc::new(FileStore::open(path)?),
+        None => Arc::new(InMemoryStore::new()),
+    })
+}
+
+/// A `VectorStore` that keeps its records in memory, for the length of one
+/// process, and searches them exhaustively.
+#[derive(Default)]
+pub struct InMemoryStore {
+    /// The records, keyed by namespace and id.
+    records: Mutex<BTreeMap<(String, String), Record>>,
+}
+
+impl InMemoryStore {
+    /// Creates a new, empty `InMemoryStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn upsert_sync(&self, record: Record) {
+        let key = (record.namespace.clone(), record.id.clone());
+        self.records.lock().unwrap().insert(key, record);
+    }
+
+    fn delete_sync(&self, namespace: &str, id: &str) -> bool {
+        let key = (namespace.to_string(), id.to_string());
+        self.records.lock().unwrap().remove(&key).is_some()
+    }
+
+    fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryStore {
+    async fn upsert(&self, record: Record) -> Result<()> {
+        self.upsert_sync(record);
+        Ok(())
+    }
+
+    async fn search(&self, namespace: &str, embedding: &[f32], limit: usize) -> Result<Vec<Match>> {
+        let records = self.records.lock().unwrap();
+        let mut matches = Vec::new();
+        for record in records.values().filter(|r| r.namespace == namespace) {
+            if record.embedding.len() != embedding.len() {
+                return Err(anyhow!(
+                    "The stored memories have {} dimensions but the query has {}; \
+                     they were made with a different embedding model",
+                    record.embedding.len(),
+                    embedding.len()
+                ));
+            }
+            matches.push(Match {
+                score: cosine_similarity(&record.embedding, embedding),
+                record: record.clone(),
+            });
+        }
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    async fn delete(&self, namespace: &str, id: &str) -> Result<bool> {
+        Ok(self.delete_sync(namespace, id))
+    }
+
+    async fn list(&self, namespace: &str) -> Result<Vec<Record>> {
+        let records = self.records.lock().unwrap();
+        Ok(records
+            .values()
+            .filter(|r| r.namespace == namespace)
+            .cloned()
+            .collect())
+    }
+}
+
+/// A line of a `FileStore`'s log.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Entry {
+    Upsert(Record),
+    Delete { namespace: String, id: String },
+}
+
+/// A `VectorStore` that persists its records to a JSON Lines file, so that
+/// memories outlive the process.
+///
+/// Every change is appended to the file as it happens, and the file is
+/// replayed into memory when the store is opened. Searches run in memory.
+/// When most of the file's lines have been superseded, opening the store
+/// rewrites it with only the live records.
+pub struct FileStore {
+    records: InMemoryStore,
+    file: Mutex<File>,
+}
+
+impl FileStore {
+    /// Opens the store at `path`, creating the file if it does not exist.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the store, or an error if the file cannot be opened
+    /// or a line of it is invalid.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = Self::open_log(path)?;
+
+        let records = InMemoryStore::new();
+        let mut entries = 0;
+        for (number, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| {
+                anyhow!(
+                    "Invalid entry on line {} of {}: {}",
+                    number + 1,
+                    path.display(),
+                    e
+                )
+            })?;
+            entries += 1;
+            match entry {
+                Entry::Upsert(record) => records.upsert_sync(record),
+                Entry::Delete { namespace, id } => {
+                    records.delete_sync(&namespace, &id);
+                }
+            }
+        }
+
+        if entries > 2 * records.len() + 100 {
+            let compacted = path.with_extension("compacting");
+            let mut output = String::new();
+            for record in records.records.lock().unwrap().values() {
+                output.push_str(&serde_json::to_string(&Entry::Upsert(record.clone()))?);
+                output.push('\n');
+            }
+            std::fs::write(&compacted, output)?;
+            std::fs::rename(&compacted, path)?;
+            file = Self::open_log(path)?;
+        }
+        Ok(Self {
+            records,
+            file: Mutex::new(file),
+        })
+    }
+
+    fn open_log(path: &Path) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow!("Failed to open the vector store {}: {}", path.display(), e))
+    }
+
+    fn append(&self, entry: &Entry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl VectorStore for FileStore {
+    async fn upsert(&self, record: Record) -> Result<()> {
+        self.append(&Entry::Upsert(record.clone()))?;
+        self.records.upsert_sync(record);
+        Ok(())
+    }
+
+    async fn search(&self, namespace: &str, embedding: &[f32], limit: usize) -> Result<Vec<Match>> {
+        self.records.search(namespace, embedding, limit).await
+    }
+
+    async fn delete(&self, namespace: &str, id: &str) -> Result<bool> {
+        if !self.records.delete_sync(namespace, id) {
+            return Ok(false);
+        }
+        self.append(&Entry::Delete {
+            namespace: namespace.to_string(),
+            id: id.to_string(),
+        })?;
+        Ok(true)
+    }
+
+    async fn list(&self, namespace: &str) -> Result<Vec<Record>> {
+        self.records.list(namespace).await
+    }
+}
@@ -1,17 +1,35 @@
-use crate::agent::Agent;
+use crate::{
+    agent::Agent,
+    run_log::{self, RunLog},
+    trajectory,
+};
 use anyhow::Result;
-use tracing::info;
+use std::sync::Arc;
+use tracing::{info, warn};
 
 /// The orchestrator is responsible for managing the agents and the overall
 /// workflow of the multi-agent system.
 pub struct Orchestrator {
     supervisor: Box<dyn Agent + Send + Sync>,
+    run_log: Option<Arc<RunLog>>,
 }
 
 impl Orchestrator {
     /// Creates a new `Orchestrator`.
     pub fn new(supervisor: Box<dyn Agent + Send + Sync>) -> Self {
-        Self { supervisor }
+        Self {
+            supervisor,
+            run_log: None,
+        }
+    }
+
+    /// Records every step of the run in `run_log`, and writes the run's
+    /// trajectory next to it when the run ends. If the log belongs to a
+    /// resumed run, the recorded steps are replayed before the run carries
+    /// on.
+    pub fn with_run_log(mut self, run_log: Arc<RunLog>) -> Self {
+        self.run_log = Some(run_log);
+        self
     }
 
     /// Runs the multi-agent system to complete a given task.
@@ -23,11 +41,32 @@ impl Orchestrator {
     /// # Returns
     ///
     /// A `Result` with the final result of the task, or an error if the
-    /// system fails.
+    /// system fails. The agents are shut down before returning either way.
     #[tracing::instrument(skip(self))]
     pub async fn run(&self, task: &str) -> Result<String> {
         info!("Starting orchestrator with task: {}", task);
-        let result = self.supervisor.run(task).await?;
+        let result = match &self.run_log {
+            Some(run_log) => match run_log.result() {
+                Some(result) => {
+                    info!("Run {} already finished", run_log.id());
+                    Ok(result)
+                }
+                None => {
+                    let name = self.supervisor.name();
+                    let run = run_log::agent(&name, task, self.supervisor.run(task));
+                    let result = run_log.clone().scope(run).await;
+                    run_log.finish(&result)?;
+                    match trajectory::export(run_log.log_path()) {
+                        Ok(path) => info!("Wrote the trajectory to {}", path.display()),
+                        Err(e) => warn!("Failed to write the trajectory: {}", e),
+                    }
+                    result
+                }
+            },
+            None => self.supervisor.run(task).await,
+        };
+        self.supervisor.shutdown().await;
+        let result = result?;
         info!("Task completed with result: {}", result);
         Ok(result)
     }
@@ -0,0 +1,574 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+use crate::llm::{LlmResponse, Usage};
+
+/// The `[runs]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunsConfig {
+    /// The directory run logs are written to.
+    pub directory: PathBuf,
+}
+
+impl Default for RunsConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from(".runs"),
+        }
+    }
+}
+
+/// Something that happened during a run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The run started. This is always the first event.
+    Started { run_id: String, task: String },
+    /// The run was resumed after it stopped.
+    Resumed,
+    /// An agent started working on a task.
+    AgentStarted { agent: String, task: String },
+    /// An agent finished its task.
+    AgentFinished { agent: String, result: String },
+    /// An agent called its LLM.
+    Llm {
+        agent: String,
+        prompt: String,
+        response: String,
+        #[serde(default)]
+        duration_ms: u64,
+        /// The tokens used, if the LLM reports them.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+    },
+    /// A supervisor routed a task to a worker.
+    Route {
+        agent: String,
+        worker: String,
+        task: String,
+    },
+    /// An executor decided on its next action.
+    Thought {
+        agent: String,
+        thought: String,
+        tool: String,
+        args: String,
+    },
+    /// An executor ran a tool.
+    Tool {
+        agent: String,
+        tool: String,
+        args: String,
+        observation: String,
+        #[serde(default)]
+        duration_ms: u64,
+    },
+    /// The run finished with a result.
+    Finished { result: String },
+    /// The run stopped with an error. It can be resumed from where it
+    /// stopped.
+    Failed { error: String },
+}
+
+impl Event {
+    /// Whether replaying a run returns this event's recorded outcome rather
+    /// than calling the LLM or running the tool again.
+    fn is_replayed(&self) -> bool {
+        matches!(self, Self::Llm { .. } | Self::Tool { .. })
+    }
+
+    /// Whether this event marks a change in the run's lifecycle rather
+    /// than a step taken by an agent.
+    fn is_lifecycle(&self) -> bool {
+        matches!(
+            self,
+            Self::Started { .. } | Self::Resumed | Self::Finished { .. } | Self::Failed { .. }
+        )
+    }
+}
+
+/// A line of a run log: an event and when it happened.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Milliseconds since the Unix epoch.
+    pub time_ms: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// The events of a resumed run that have not been replayed yet.
+struct Replay {
+    events: Vec<Event>,
+    cursor: usize,
+}
+
+/// A persistent, append-only log of a run, stored as JSON Lines in
+/// `<directory>/<run id>.jsonl`.
+///
+/// Every agent run, LLM call, routing decision, thought and tool call is
+/// appended as it happens. A run that crashed or failed can be resumed:
+/// agents then replay the recorded LLM responses and tool observations in
+/// order, without calling the LLM or running tools again, and carry on live
+/// from the first step that was not recorded.
+pub struct RunLog {
+    id: String,
+    task: String,
+    path: PathBuf,
+    file: Mutex<File>,
+    replay: Mutex<Replay>,
+}
+
+tokio::task_local! {
+    /// The log of the run the current task belongs to.
+    static CURRENT: Arc<RunLog>;
+}
+
+impl RunLog {
+    /// Starts a new run of `task`, with a new run ID.
+    pub fn create(directory: &Path, task: &str) -> Result<Self> {
+        fs::create_dir_all(directory)?;
+        let id = new_run_id(task);
+        let path = directory.join(format!("{}.jsonl", id));
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| anyhow!("Failed to create the run log {}: {}", path.display(), e))?;
+        let log = Self {
+            id: id.clone(),
+            task: task.to_string(),
+            path,
+            file: Mutex::new(file),
+            replay: Mutex::new(Replay {
+                events: Vec::new(),
+                cursor: 0,
+            }),
+        };
+        log.append(Event::Started {
+            run_id: id,
+            task: task.to_string(),
+        })?;
+        Ok(log)
+    }
+
+    /// Reopens the run `id` to resume it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the log, positioned to replay the run from its
+    /// start, or an error if there is no such run or its log is invalid.
+    pub fn resume(directory: &Path, id: &str) -> Result<Self> {
+        let path = Self::path(directory, id)?;
+        let events: Vec<Event> = read(&path)?.into_iter().map(|e| e.event).collect();
+        let task = match events.first() {
+            Some(Event::Started { task, .. }) => task.clone(),
+            _ => {
+                return Err(anyhow!(
+                    "{} does not start with a started event",
+                    path.display()
+                ))
+            }
+        };
+        let file = OpenOptions::new().append(true).open(&path)?;
+        let log = Self {
+            id: id.to_string(),
+            task,
+            path,
+            file: Mutex::new(file),
+            replay: Mutex::new(Replay { events, cursor: 1 }),
+        };
+        log.append(Event::Resumed)?;
+        Ok(log)
+    }
+
+    /// Returns the path of the log of run `id`, which must exist.
+    pub fn path(directory: &Path, id: &str) -> Result<PathBuf> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(anyhow!("Invalid run ID: {:?}", id));
+        }
+        let path = directory.join(format!("{}.jsonl", id));
+        if !path.exists() {
+            return Err(anyhow!(
+                "No run with the ID {} in {}",
+                id,
+                directory.display()
+            ));
+        }
+        Ok(path)
+    }
+
+    /// Returns the run's ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the path of the run's log.
+    pub fn log_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the run's task.
+    pub fn task(&self) -> &str {
+        &self.task
+    }
+
+    /// Returns the result of the run if it already finished.
+    pub fn result(&self) -> Option<String> {
+        let replay = self.replay.lock().unwrap();
+        replay.events.iter().rev().find_map(|event| match event {
+            Event::Finished { result } => Some(result.clone()),
+            _ => None,
+        })
+    }
+
+    /// Runs `future` as part of this run, so that the agents it runs record
+    /// their steps here.
+    pub async fn scope<F: Future>(self: Arc<Self>, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Records how the run ended.
+    pub fn finish(&self, result: &Result<String>) -> Result<()> {
+        self.append(match result {
+            Ok(result) => Event::Finished {
+                result: result.clone(),
+            },
+            Err(error) => Event::Failed {
+                error: error.to_string(),
+            },
+        })
+    }
+
+    fn append(&self, event: Event) -> Result<()> {
+        let entry = Entry {
+            time_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            event,
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Returns the next replayed event, which must satisfy `expected`, or
+    /// `None` once every recorded step has been replayed.
+    fn next_replayed(&self, expected: impl Fn(&Event) -> bool) -> Result<Option<Event>> {
+        let mut replay = self.replay.lock().unwrap();
+        while replay.cursor < replay.events.len() {
+            let event = replay.events[replay.cursor].clone();
+            replay.cursor += 1;
+            if !event.is_replayed() {
+                continue;
+            }
+            if !expected(&event) {
+                return Err(anyhow!(
+                    "Run {} took a different path than the one recorded; expected {:?}",
+                    self.id,
+                    event
+                ));
+            }
+            return Ok(Some(event));
+        }
+        Ok(None)
+    }
+
+    /// Appends an event that is not replayed, unless it was already recorded
+    /// and the run is still being replayed.
+    fn record(&self, event: Event) -> Result<()> {
+        {
+            let mut replay = self.replay.lock().unwrap();
+            while replay.cursor < replay.events.len() && replay.events[replay.cursor].is_lifecycle()
+            {
+                replay.cursor += 1;
+            }
+            if replay.cursor < replay.events.len() {
+                if replay.events[replay.cursor] == event {
+                    replay.cursor += 1;
+                }
+                return Ok(());
+            }
+        }
+        self.append(event)
+    }
+}
+
+/// Makes a run ID from the current time and a hash of the task, such as
+/// `1760790000-3fa2c1`.
+fn new_run_id(task: &str) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seed = format!("{}:{}:{}", now.as_nanos(), std::process::id(), task);
+    let hash = format!("{:x}", Sha256::digest(seed.as_bytes()));
+    format!("{}-{}", now.as_secs(), &hash[..6])
+}
+
+/// Reads every entry of the log at `path`.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let file = File::open(path)?;
+    let mut entries = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            // A crash can leave the last line half written.
+            Err(e) => warn!(
+                "Ignoring invalid line {} of {}: {}",
+                number + 1,
+                path.display(),
+                e
+            ),
+        }
+    }
+    Ok(entries)
+}
+
+/// Calls an agent's LLM as a step of the current run.
+///
+/// When a run is being resumed, this returns the recorded response instead
+/// of calling the LLM. Otherwise it awaits `call` and records the prompt,
+/// the response, how long it took and the tokens it used. Outside of a run,
+/// it simply awaits `call`.
+pub async fn llm(
+    agent: &str,
+    prompt: &str,
+    call: impl Future<Output = Result<LlmResponse>>,
+) -> Result<String> {
+    let Ok(log) = CURRENT.try_with(Arc::clone) else {
+        return Ok(call.await?.content);
+    };
+    let replayed = log.next_replayed(
+        |event| matches!(event, Event::Llm { agent: recorded, .. } if recorded == agent),
+    )?;
+    if let Some(Event::Llm {
+        prompt: recorded,
+        response,
+        ..
+    }) = replayed
+    {
+        if recorded != prompt {
+            warn!(
+                "{} is replaying a response to a prompt that has since changed",
+                agent
+            );
+        }
+        return Ok(response);
+    }
+    let started = Instant::now();
+    let response = call.await?;
+    log.append(Event::Llm {
+        agent: agent.to_string(),
+        prompt: prompt.to_string(),
+        response: response.content.clone(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        usage: response.usage,
+    })?;
+    Ok(response.content)
+}
+
+/// Runs a tool as a step of the current run.
+///
+/// When a run is being resumed, this returns the recorded observation
+/// instead of running the tool again. Otherwise it awaits `call` and
+/// records the observation and how long it took. Outside of a run, it simply awaits `call`.
+pub async fn tool(
+    agent: &str,
+    tool: &str,
+    args: &str,
+    call: impl Future<Output = Result<String>>,
+) -> Result<String> {
+    let Ok(log) = CURRENT.try_with(Arc::clone) else {
+        return call.await;
+    };
+    let replayed = log.next_replayed(|event| {
+        matches!(
+            event,
+            Event::Tool { agent: a, tool: t, args: r, .. } if a == agent && t == tool && r == args
+        )
+    })?;
+    if let Some(Event::Tool { observation, .. }) = replayed {
+        return Ok(observation);
+    }
+    let started = Instant::now();
+    let observation = call.await?;
+    log.append(Event::Tool {
+        agent: agent.to_string(),
+        tool: tool.to_string(),
+        args: args.to_string(),
+        observation: observation.clone(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    })?;
+    Ok(observation)
+}
+
+/// Runs an agent on a task as part of the current run, recording when it
+/// starts and what it returns, so that the run's trajectory shows which
+/// agent took each step.
+///
+/// Failures are not recorded here: the run records its error when it
+/// stops, and a resumed run carries on inside the agents that were running.
+pub async fn agent(
+    agent: &str,
+    task: &str,
+    run: impl Future<Output = Result<String>>,
+) -> Result<String> {
+    record(Event::AgentStarted {
+        agent: agent.to_string(),
+        task: task.to_string(),
+    })?;
+    let result = run.await?;
+    record(Event::AgentFinished {
+        agent: agent.to_string(),
+        result: result.clone(),
+    })?;
+    Ok(result)
+}
+
+/// Records an event of the current run that is not replayed, such as a
+/// routing decision. Outside of a run, this does nothing.
+pub fn record(event: Event) -> Result<()> {
+    match CURRENT.try_with(Arc::clone) {
+        Ok(log) => log.record(event),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agent::Agent, executor::ExecutorAgent, llm::Llm, tools::Tool};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An LLM that returns the given responses in order.
+    struct ScriptedLlm {
+        responses: Mutex<Vec<String>>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Llm for ScriptedLlm {
+        async fn call(&self, _prompt: &str) -> Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+    }
+
+    /// A tool that fails the first time it is given `fail`.
+    struct FlakyTool {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for FlakyTool {
+        fn name(&self) -> &str {
+            "FlakyTool"
+        }
+
+        async fn execute(&self, args: &str) -> Result<String> {
+            let runs = self.runs.fetch_add(1, Ordering::SeqCst);
+            if args == "fail" && runs < 2 {
+                return Err(anyhow!("The laptop went to sleep"));
+            }
+            Ok(format!("ran {}", args))
+        }
+    }
+
+    fn step(tool: &str, args: &str) -> String {
+        json!({ "thought": "Next.", "action": { "tool": tool, "args": args } }).to_string()
+    }
+
+    fn agent(
+        responses: &[String],
+        calls: &Arc<AtomicUsize>,
+        runs: &Arc<AtomicUsize>,
+    ) -> ExecutorAgent {
+        let llm = ScriptedLlm {
+            responses: Mutex::new(responses.to_vec()),
+            calls: calls.clone(),
+        };
+        let tools: Vec<Box<dyn Tool + Send + Sync>> =
+            vec![Box::new(FlakyTool { runs: runs.clone() })];
+        ExecutorAgent::new(Box::new(llm), tools, "Worker", "A worker")
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_recorded_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let log = Arc::new(RunLog::create(dir.path(), "Do the thing").unwrap());
+        let worker = agent(
+            &[step("FlakyTool", "ok"), step("FlakyTool", "fail")],
+            &calls,
+            &runs,
+        );
+        let result = log.clone().scope(worker.run("Do the thing")).await;
+        log.finish(&result).unwrap();
+        assert!(result.is_err());
+        assert_eq!(
+            (calls.load(Ordering::SeqCst), runs.load(Ordering::SeqCst)),
+            (2, 2)
+        );
+
+        // Resuming replays both LLM calls and the first tool call, then runs
+        // the failed tool call and asks the LLM for the next step.
+        let log = Arc::new(RunLog::resume(dir.path(), log.id()).unwrap());
+        assert_eq!(log.task(), "Do the thing");
+        let worker = agent(&[step("Finish", "done")], &calls, &runs);
+        let result = log.clone().scope(worker.run(log.task())).await;
+        log.finish(&result).unwrap();
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(
+            (calls.load(Ordering::SeqCst), runs.load(Ordering::SeqCst)),
+            (3, 3)
+        );
+
+        let id = log.id().to_string();
+        let events: Vec<Event> = read(&RunLog::path(dir.path(), &id).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        let kinds: Vec<String> = events
+            .iter()
+            .map(|event| {
+                serde_json::to_value(event).unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "started", "llm", "thought", "tool", "llm", "thought", "failed", "resumed", "tool",
+                "llm", "thought", "finished"
+            ]
+        );
+        assert_eq!(
+            RunLog::resume(dir.path(), &id).unwrap().result().as_deref(),
+            Some("done")
+        );
+    }
+}
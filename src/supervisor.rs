@@ -1,4 +1,8 @@
-use crate::{agent::Agent, llm::Llm};
+use crate::{
+    agent::Agent,
+    llm::Llm,
+    run_log::{self, Event},
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -16,6 +20,9 @@ struct RoutingDecision {
 pub struct SupervisorAgent {
     llm: Box<dyn Llm + Send + Sync>,
     workers: HashMap<String, Box<dyn Agent + Send + Sync>>,
+    name: String,
+    description: String,
+    system_prompt: Option<String>,
 }
 
 impl SupervisorAgent {
@@ -24,20 +31,45 @@ impl SupervisorAgent {
         llm: Box<dyn Llm + Send + Sync>,
         workers: HashMap<String, Box<dyn Agent + Send + Sync>>,
     ) -> Self {
-        Self { llm, workers }
+        Self {
+            llm,
+            workers,
+            name: "SupervisorAgent".to_string(),
+            description: "A supervisor agent that routes tasks to the correct worker agent."
+                .to_string(),
+            system_prompt: None,
+        }
+    }
+
+    /// Names the supervisor, so that it can be the worker of another
+    /// supervisor.
+    pub fn with_name(mut self, name: &str, description: &str) -> Self {
+        self.name = name.to_string();
+        self.description = description.to_string();
+        self
+    }
+
+    /// Opens the supervisor's prompt with `system_prompt` instead of the
+    /// default introduction.
+    pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
+        self.system_prompt = Some(system_prompt.to_string());
+        self
     }
 
     /// Constructs the prompt for the supervisor agent.
     fn construct_prompt(&self, task: &str) -> String {
-        let worker_descriptions = self
-            .workers
-            .values()
+        // Sorted so that the prompt is the same every time, which a resumed
+        // run relies on to replay the recorded routing decision.
+        let mut workers = self.workers.values().collect::<Vec<_>>();
+        workers.sort_by_key(|w| w.name());
+        let worker_descriptions = workers
+            .iter()
             .map(|w| format!("- {}: {}", w.name(), w.description()))
             .collect::<Vec<String>>()
             .join("\n");
 
         format!(
-            "You are a supervisor agent. Your job is to route a task to the correct worker agent.
+            "{}
 
 The available workers are:
 {}
@@ -53,7 +85,11 @@ Example:
     \"task\": \"Read the content of the file src/main.rs\"
 }}
 ```",
-            worker_descriptions, task
+            self.system_prompt.as_deref().unwrap_or(
+                "You are a supervisor agent. Your job is to route a task to the correct worker agent."
+            ),
+            worker_descriptions,
+            task
         )
     }
 }
@@ -61,11 +97,11 @@ Example:
 #[async_trait]
 impl Agent for SupervisorAgent {
     fn name(&self) -> String {
-        "SupervisorAgent".to_string()
+        self.name.clone()
     }
 
     fn description(&self) -> String {
-        "A supervisor agent that routes tasks to the correct worker agent.".to_string()
+        self.description.clone()
     }
 
     /// Runs the supervisor agent loop to route the given task.
@@ -85,8 +121,13 @@ impl Agent for SupervisorAgent {
 
         info!("---SUPERVISOR PROMPT---\n{}---END---\n", prompt);
 
-        let llm_response = self.llm.call(&prompt).await?;
+        let llm_response = run_log::llm(&self.name(), &prompt, self.llm.complete(&prompt)).await?;
         let decision: RoutingDecision = serde_json::from_str(&llm_response)?;
+        run_log::record(Event::Route {
+            agent: self.name(),
+            worker: decision.worker.clone(),
+            task: decision.task.clone(),
+        })?;
 
         info!(
             "---SUPERVISOR DECISION---\nWorker: {}, Task: {}---END---\n",
@@ -98,6 +139,13 @@ impl Agent for SupervisorAgent {
             .get(&decision.worker)
             .ok_or_else(|| anyhow::anyhow!("Worker not found: {}", decision.worker))?;
 
-        worker.run(&decision.task).await
+        run_log::agent(&decision.worker, &decision.task, worker.run(&decision.task)).await
+    }
+
+    /// Shuts down every worker.
+    async fn shutdown(&self) {
+        for worker in self.workers.values() {
+            worker.shutdown().await;
+        }
     }
 }
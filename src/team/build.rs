@@ -0,0 +1,287 @@
+use super::{AgentConfig, TeamConfig, ToolConfig};
+use crate::{
+    agent::Agent,
+    executor::ExecutorAgent,
+    llm::{Llm, MockLlm, OpenAiLlm},
+    supervisor::SupervisorAgent,
+    tools::registry::{ToolContext, ToolRegistry},
+};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+/// What agents are built from: the tools they can be given, and the
+/// workspace and configuration to build the tools from.
+pub struct BuildContext<'a> {
+    pub registry: &'a ToolRegistry,
+    pub tools: ToolContext<'a>,
+    /// The model of agents that do not set one.
+    pub model: &'a str,
+    /// When set, every agent uses a `MockLlm` that finishes this task
+    /// straight away.
+    pub mock_task: Option<&'a str>,
+}
+
+/// Returns the team used when the configuration file has no `[agents]`:
+/// a `SupervisorAgent` routing to a `FileSystemAgent` and a
+/// `WebScraperAgent`, plus a `DataAgent` when databases are configured and
+/// a `MemoryAgent` when memory is.
+pub fn default_team(context: &BuildContext) -> Result<TeamConfig> {
+    let mut agents = BTreeMap::new();
+    let tools = |names: &[&str]| names.iter().map(|name| ToolConfig::named(name)).collect();
+
+    let mut file_system_tools: Vec<ToolConfig> = tools(&[
+        "CodeWriterTool",
+        "FileReaderTool",
+        "DirectoryListerTool",
+        "CodeSearchTool",
+        "OutlineTool",
+        "GitTool",
+        "CargoTool",
+        "SystemTool",
+        "ShellSessionTool",
+        "ProcessManagerTool",
+    ]);
+    if context.tools.index.is_some() {
+        file_system_tools.push(ToolConfig::named("SemanticSearchTool"));
+    }
+    agents.insert(
+        "FileSystemAgent".to_string(),
+        AgentConfig {
+            description: "An agent that can interact with the file system.".to_string(),
+            tools: file_system_tools,
+            ..AgentConfig::default()
+        },
+    );
+
+    let mut web_tools: Vec<ToolConfig> = tools(&["WebScraperTool", "RestApiTool"]);
+    let mut apis: Vec<&String> = context.tools.apis.keys().collect();
+    apis.sort();
+    for api in apis {
+        let mut tool = ToolConfig::named("OpenApi");
+        tool.options
+            .insert("api".to_string(), Value::String(api.clone()));
+        web_tools.push(tool);
+    }
+    agents.insert(
+        "WebScraperAgent".to_string(),
+        AgentConfig {
+            description: "An agent that can scrape web pages and call web APIs.".to_string(),
+            tools: web_tools,
+            ..AgentConfig::default()
+        },
+    );
+
+    if !context.tools.databases.is_empty() {
+        agents.insert(
+            "DataAgent".to_string(),
+            AgentConfig {
+                description: "An agent that can explore and query SQL databases.".to_string(),
+                tools: tools(&["DatabaseTool"]),
+                ..AgentConfig::default()
+            },
+        );
+    }
+    if !context.tools.mcp_servers.is_empty() {
+        let mut servers: Vec<&String> = context.tools.mcp_servers.keys().collect();
+        servers.sort();
+        let description = format!(
+            "An agent that uses the tools of the MCP servers {}.",
+            servers
+                .iter()
+                .map(|server| server.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let tools = servers
+            .into_iter()
+            .map(|server| {
+                let mut tool = ToolConfig::named("Mcp");
+                tool.options
+                    .insert("server".to_string(), Value::String(server.clone()));
+                tool
+            })
+            .collect();
+        agents.insert(
+            "McpAgent".to_string(),
+            AgentConfig {
+                description,
+                tools,
+                ..AgentConfig::default()
+            },
+        );
+    }
+    if context.tools.memory.is_some() {
+        agents.insert(
+            "MemoryAgent".to_string(),
+            AgentConfig {
+                description:
+                    "An agent that stores facts in long-term memory and recalls them in later tasks."
+                        .to_string(),
+                tools: tools(&["RememberTool", "RecallTool", "ForgetTool"]),
+                ..AgentConfig::default()
+            },
+        );
+    }
+
+    // The supervisor lists its workers in the order above, so that mock
+    // runs route to the FileSystemAgent.
+    let workers = [
+        "FileSystemAgent",
+        "WebScraperAgent",
+        "DataAgent",
+        "McpAgent",
+        "MemoryAgent",
+    ]
+    .iter()
+    .filter(|name| agents.contains_key(**name))
+    .map(|name| name.to_string())
+    .collect();
+    agents.insert(
+        "SupervisorAgent".to_string(),
+        AgentConfig {
+            description: "A supervisor agent that routes tasks to the correct worker agent."
+                .to_string(),
+            workers,
+            ..AgentConfig::default()
+        },
+    );
+    TeamConfig::new(agents, Some("SupervisorAgent"))
+}
+
+/// Builds the team's root agent, and the agents it routes to.
+///
+/// # Returns
+///
+/// A `Result` with the root agent, or an error that starts with the key of
+/// the tool that cannot be built, such as a tool whose options are invalid
+/// or that needs a section the configuration file does not have.
+pub async fn build(
+    team: &TeamConfig,
+    context: &BuildContext<'_>,
+) -> Result<Box<dyn Agent + Send + Sync>> {
+    build_agent(team, &team.root, context).await
+}
+
+async fn build_agent(
+    team: &TeamConfig,
+    name: &str,
+    context: &BuildContext<'_>,
+) -> Result<Box<dyn Agent + Send + Sync>> {
+    let config = &team.agents[name];
+    let llm = llm(config, context);
+    if !config.workers.is_empty() {
+        let mut workers: HashMap<String, Box<dyn Agent + Send + Sync>> = HashMap::new();
+        for worker in &config.workers {
+            // Boxed, as the future of a recursive call has no fixed size.
+            let agent = Box::pin(build_agent(team, worker, context)).await?;
+            workers.insert(worker.clone(), agent);
+        }
+        let mut supervisor =
+            SupervisorAgent::new(llm, workers).with_name(name, &config.description);
+        if let Some(system_prompt) = &config.system_prompt {
+            supervisor = supervisor.with_system_prompt(system_prompt);
+        }
+        return Ok(Box::new(supervisor));
+    }
+
+    let mut tools = Vec::new();
+    for (i, tool) in config.tools.iter().enumerate() {
+        let built = context
+            .registry
+            .build(&tool.name, tool.options.clone(), name, &context.tools)
+            .await
+            .map_err(|e| anyhow!("agents.{}.tools[{}]: {}", name, i, e))?;
+        tools.extend(built);
+    }
+    let mut executor = ExecutorAgent::new(llm, tools, name, &config.description);
+    if let Some(system_prompt) = &config.system_prompt {
+        executor = executor.with_system_prompt(system_prompt);
+    }
+    if let Some(max_steps) = config.max_steps {
+        executor = executor.with_max_steps(max_steps);
+    }
+    if let Some(timeout_secs) = config.timeout_secs {
+        executor = executor.with_timeout(Duration::from_secs(timeout_secs));
+    }
+    Ok(Box::new(executor))
+}
+
+fn llm(config: &AgentConfig, context: &BuildContext) -> Box<dyn Llm + Send + Sync> {
+    match (context.mock_task, config.workers.first()) {
+        (Some(task), Some(worker)) => Box::new(MockLlm::new(
+            &serde_json::json!({ "worker": worker, "task": task }).to_string(),
+        )),
+        (Some(_), None) => Box::new(MockLlm::new(
+            r#"{"thought": "Mock run.", "action": {"tool": "Finish", "args": "done"}}"#,
+        )),
+        (None, _) => Box::new(OpenAiLlm::new(
+            config.model.as_deref().unwrap_or(context.model),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{
+        http::{HttpClient, HttpConfig},
+        secrets::Secrets,
+        workspace::Workspace,
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_build_team() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sandbox, command_policies, apis, databases, git, mcp_servers) = Default::default();
+        let registry = ToolRegistry::builtin();
+        let context = BuildContext {
+            registry: &registry,
+            tools: ToolContext {
+                workspace: Workspace::new(dir.path()).unwrap(),
+                sandbox: &sandbox,
+                command_policies: &command_policies,
+                http: HttpClient::new(&HttpConfig::default()).unwrap(),
+                secrets: Secrets::new(&HashMap::new()).unwrap(),
+                apis: &apis,
+                databases: &databases,
+                git: &git,
+                mcp_servers: &mcp_servers,
+                memory: None,
+                index: None,
+            },
+            model: "gpt-4o",
+            mock_task: Some("Say hi"),
+        };
+
+        let team = default_team(&context).unwrap();
+        assert_eq!(
+            team.agents["SupervisorAgent"].workers,
+            ["FileSystemAgent", "WebScraperAgent"]
+        );
+        let supervisor = build(&team, &context).await.unwrap();
+        assert_eq!(supervisor.run("Say hi").await.unwrap(), "done");
+
+        let team = TeamConfig::parse(
+            &serde_json::from_value(json!({
+                "Reader": { "tools": [{ "name": "FileReaderTool", "path": "src" }] }
+            }))
+            .unwrap(),
+            None,
+            &registry,
+        )
+        .unwrap();
+        let error = build(&team, &context).await.err().unwrap().to_string();
+        assert!(
+            error.starts_with(
+                "agents.Reader.tools[0]: invalid options for FileReaderTool: unknown field `path`"
+            ),
+            "{}",
+            error
+        );
+    }
+}
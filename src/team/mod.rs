@@ -0,0 +1,319 @@
+pub mod build;
+
+use crate::tools::registry::ToolRegistry;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An agent described in the `[agents.<name>]` section of the configuration
+/// file. An agent with `workers` is a supervisor that routes each task to
+/// one of them; any other agent is an executor that works on the task with
+/// its `tools`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AgentConfig {
+    /// What the agent is for, which supervisors choose workers by.
+    pub description: String,
+    /// Replaces the opening sentence of the agent's prompt.
+    pub system_prompt: Option<String>,
+    /// The model the agent uses, instead of the top-level `model`.
+    pub model: Option<String>,
+    pub tools: Vec<ToolConfig>,
+    /// The names of the agents a supervisor routes tasks to.
+    pub workers: Vec<String>,
+    /// The most LLM calls an executor makes before failing the task.
+    pub max_steps: Option<usize>,
+    /// The most time, in seconds, an executor spends before failing the
+    /// task.
+    pub timeout_secs: Option<u64>,
+}
+
+/// A tool of an agent: its name and the options that configure it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolConfig {
+    pub name: String,
+    pub options: Map<String, Value>,
+}
+
+impl ToolConfig {
+    /// A tool without options.
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            options: Map::new(),
+        }
+    }
+}
+
+/// An agent as written in the configuration file, before its tools are
+/// checked.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAgentConfig {
+    #[serde(default)]
+    description: String,
+    system_prompt: Option<String>,
+    model: Option<String>,
+    #[serde(default)]
+    tools: Vec<Value>,
+    #[serde(default)]
+    workers: Vec<String>,
+    max_steps: Option<usize>,
+    timeout_secs: Option<u64>,
+}
+
+/// The agents to build and the one that receives each task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamConfig {
+    pub agents: BTreeMap<String, AgentConfig>,
+    pub root: String,
+}
+
+impl TeamConfig {
+    /// Checks the `[agents]` section of the configuration file.
+    ///
+    /// # Arguments
+    ///
+    /// * `agents` - The agents, keyed by name, as written in the file.
+    /// * `root` - The `root_agent` setting, which names the agent that
+    ///   receives each task. It can be left out when only one agent is not
+    ///   a worker of another.
+    /// * `registry` - The tools agents can be given.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the team, or an error that starts with the key it
+    /// is about, such as `agents.Lead.workers[1]`.
+    pub fn parse(
+        agents: &BTreeMap<String, Value>,
+        root: Option<&str>,
+        registry: &ToolRegistry,
+    ) -> Result<Self> {
+        let mut parsed = BTreeMap::new();
+        for (name, value) in agents {
+            parsed.insert(name.clone(), parse_agent(name, value, registry)?);
+        }
+        Self::new(parsed, root)
+    }
+
+    /// Checks a team whose agents were parsed already, or built in code.
+    pub fn new(agents: BTreeMap<String, AgentConfig>, root: Option<&str>) -> Result<Self> {
+        if agents.is_empty() {
+            return Err(anyhow!("agents: define at least one agent"));
+        }
+        for (name, agent) in &agents {
+            let key = format!("agents.{}", name);
+            if !agent.workers.is_empty() {
+                if !agent.tools.is_empty() {
+                    return Err(anyhow!(
+                        "{}: an agent has either tools or workers, not both",
+                        key
+                    ));
+                }
+                if agent.max_steps.is_some() || agent.timeout_secs.is_some() {
+                    return Err(anyhow!(
+                        "{}: max_steps and timeout_secs only apply to agents with tools",
+                        key
+                    ));
+                }
+            }
+            for (i, worker) in agent.workers.iter().enumerate() {
+                if !agents.contains_key(worker) {
+                    return Err(anyhow!(
+                        "{}.workers[{}]: there is no agent named {:?}",
+                        key,
+                        i,
+                        worker
+                    ));
+                }
+                if leads_to(&agents, worker, name) {
+                    return Err(anyhow!(
+                        "{}.workers[{}]: {} routes tasks back to {}",
+                        key,
+                        i,
+                        worker,
+                        name
+                    ));
+                }
+            }
+        }
+
+        let root = match root {
+            Some(root) if agents.contains_key(root) => root.to_string(),
+            Some(root) => return Err(anyhow!("root_agent: there is no agent named {:?}", root)),
+            None => {
+                let workers: BTreeSet<&String> =
+                    agents.values().flat_map(|agent| &agent.workers).collect();
+                let candidates: Vec<&String> = agents
+                    .keys()
+                    .filter(|name| !workers.contains(name))
+                    .collect();
+                match candidates[..] {
+                    [root] => root.clone(),
+                    _ => {
+                        return Err(anyhow!(
+                            "root_agent: set it to the agent that receives each task, one of {}",
+                            candidates
+                                .iter()
+                                .map(|name| name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(Self { agents, root })
+    }
+}
+
+fn parse_agent(name: &str, value: &Value, registry: &ToolRegistry) -> Result<AgentConfig> {
+    let key = format!("agents.{}", name);
+    let raw: RawAgentConfig =
+        serde_json::from_value(value.clone()).map_err(|e| anyhow!("{}: {}", key, e))?;
+    let mut tools = Vec::new();
+    for (i, tool) in raw.tools.into_iter().enumerate() {
+        let tool = match tool {
+            Value::String(name) => ToolConfig::named(&name),
+            Value::Object(mut options) => match options.remove("name") {
+                Some(Value::String(name)) => ToolConfig { name, options },
+                _ => {
+                    return Err(anyhow!(
+                        "{}.tools[{}]: a tool with options needs a `name`",
+                        key,
+                        i
+                    ))
+                }
+            },
+            _ => {
+                return Err(anyhow!(
+                    "{}.tools[{}]: expected a tool name, or a table with a `name` and options",
+                    key,
+                    i
+                ))
+            }
+        };
+        if !registry.contains(&tool.name) {
+            return Err(anyhow!(
+                "{}.tools[{}]: unknown tool {:?}; the tools are {}",
+                key,
+                i,
+                tool.name,
+                registry.names().join(", ")
+            ));
+        }
+        tools.push(tool);
+    }
+    Ok(AgentConfig {
+        description: raw.description,
+        system_prompt: raw.system_prompt,
+        model: raw.model,
+        tools,
+        workers: raw.workers,
+        max_steps: raw.max_steps,
+        timeout_secs: raw.timeout_secs,
+    })
+}
+
+/// Whether tasks routed to `from` can reach `to`.
+fn leads_to(agents: &BTreeMap<String, AgentConfig>, from: &str, to: &str) -> bool {
+    let mut stack = vec![from];
+    let mut seen = BTreeSet::new();
+    while let Some(name) = stack.pop() {
+        if name == to {
+            return true;
+        }
+        if seen.insert(name) {
+            if let Some(agent) = agents.get(name) {
+                stack.extend(agent.workers.iter().map(String::as_str));
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(agents: Value, root: Option<&str>) -> Result<TeamConfig> {
+        TeamConfig::parse(
+            &serde_json::from_value(agents).unwrap(),
+            root,
+            &ToolRegistry::builtin(),
+        )
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        let team = parse(
+            json!({
+                "Lead": { "description": "Leads.", "workers": ["Coder"] },
+                "Coder": {
+                    "description": "Codes.",
+                    "model": "gpt-4o-mini",
+                    "max_steps": 20,
+                    "tools": ["FileReaderTool", { "name": "SystemTool", "command_policy": { "default": "deny" } }]
+                }
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(team.root, "Lead");
+        let coder = &team.agents["Coder"];
+        assert_eq!(
+            (coder.model.as_deref(), coder.max_steps),
+            (Some("gpt-4o-mini"), Some(20))
+        );
+        assert_eq!(coder.tools[1].name, "SystemTool");
+        assert_eq!(
+            coder.tools[1].options["command_policy"],
+            json!({ "default": "deny" })
+        );
+
+        let error =
+            |agents: Value, root: Option<&str>| parse(agents, root).unwrap_err().to_string();
+        assert_eq!(
+            error(
+                json!({ "Coder": { "tools": ["FileReaderTool", "FileReadTool"] } }),
+                None
+            )
+            .split(';')
+            .next()
+            .unwrap(),
+            "agents.Coder.tools[1]: unknown tool \"FileReadTool\""
+        );
+        assert!(error(json!({ "Coder": { "modle": "gpt-4o" } }), None)
+            .starts_with("agents.Coder: unknown field `modle`"));
+        assert_eq!(
+            error(
+                json!({ "Coder": { "tools": [{ "command_policy": {} }] } }),
+                None
+            ),
+            "agents.Coder.tools[0]: a tool with options needs a `name`"
+        );
+        assert_eq!(
+            error(
+                json!({ "Lead": { "workers": ["Coder", "Writer"] }, "Coder": {} }),
+                None
+            ),
+            "agents.Lead.workers[1]: there is no agent named \"Writer\""
+        );
+        assert_eq!(
+            error(
+                json!({ "A": { "workers": ["B"] }, "B": { "workers": ["A"] }, "C": {} }),
+                Some("C")
+            ),
+            "agents.A.workers[0]: B routes tasks back to A"
+        );
+        assert_eq!(
+            error(json!({ "Coder": {}, "Writer": {} }), None),
+            "root_agent: set it to the agent that receives each task, one of Coder, Writer"
+        );
+        assert_eq!(
+            error(json!({ "Coder": {} }), Some("Lead")),
+            "root_agent: there is no agent named \"Lead\""
+        );
+    }
+}
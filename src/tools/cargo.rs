@@ -0,0 +1,550 @@
+use crate::tools::{
+    process::{kill_process_group, OutputCapture},
+    sandbox::{Sandbox, SandboxConfig},
+    workspace::Workspace,
+    Tool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashSet, fmt::Write, process::Stdio};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// The number of bytes of cargo's own stderr kept for when it fails before
+/// compiling anything, e.g. because of a broken manifest.
+const MAX_STDERR_BYTES: usize = 4 * 1024;
+
+/// The maximum number of characters of output shown per failed test.
+const MAX_TEST_OUTPUT_CHARS: usize = 2_000;
+
+/// The cargo subcommands the tool can run.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Subcommand {
+    #[default]
+    Check,
+    Clippy,
+    Test,
+}
+
+impl Subcommand {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::Clippy => "clippy",
+            Self::Test => "test",
+        }
+    }
+}
+
+/// The arguments accepted by `CargoTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `command` with all other options defaulted.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct CargoArgs {
+    /// `check`, `clippy` or `test`.
+    #[serde(default)]
+    command: Subcommand,
+    /// The package to build, for workspaces with several.
+    #[serde(default)]
+    package: Option<String>,
+    /// The directory of the crate, relative to the workspace root.
+    #[serde(default)]
+    path: Option<String>,
+    /// Also check tests, examples and benches.
+    #[serde(default)]
+    all_targets: bool,
+    /// Only run tests whose names contain this string.
+    #[serde(default)]
+    filter: Option<String>,
+    /// The maximum number of diagnostics to list.
+    #[serde(default = "default_max_diagnostics")]
+    max_diagnostics: usize,
+}
+
+fn default_max_diagnostics() -> usize {
+    30
+}
+
+impl CargoArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        let parsed = if args.starts_with('{') {
+            serde_json::from_str(args)
+        } else {
+            serde_json::from_value(serde_json::json!({ "command": args }))
+        };
+        parsed.map_err(|e| anyhow!("Invalid arguments for CargoTool: {}", e))
+    }
+}
+
+/// Quotes a string for `sh`.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Formats a compiler message as `file:line:column: level[code]: message`,
+/// followed by its notes and help, indented.
+fn format_diagnostic(message: &Value) -> Option<String> {
+    let text = message.get("message")?.as_str()?;
+    let level = message.get("level")?.as_str()?;
+    let spans = message.get("spans").and_then(Value::as_array);
+    let primary = spans
+        .into_iter()
+        .flatten()
+        .find(|span| span.get("is_primary").and_then(Value::as_bool) == Some(true));
+    // Summaries like "aborting due to 2 previous errors" repeat the counts.
+    if primary.is_none()
+        && (text.starts_with("aborting due to")
+            || text.ends_with("warning emitted")
+            || text.ends_with("warnings emitted"))
+    {
+        return None;
+    }
+
+    let mut output = String::new();
+    if let Some(span) = primary {
+        let _ = write!(
+            output,
+            "{}:{}:{}: ",
+            span.get("file_name")?.as_str()?,
+            span.get("line_start")?,
+            span.get("column_start")?
+        );
+    }
+    output.push_str(level);
+    if let Some(code) = message.pointer("/code/code").and_then(Value::as_str) {
+        let _ = write!(output, "[{}]", code);
+    }
+    let _ = write!(output, ": {}", text);
+    if let Some(label) = primary
+        .and_then(|span| span.get("label"))
+        .and_then(Value::as_str)
+    {
+        let _ = write!(output, " ({})", label);
+    }
+    for child in message
+        .get("children")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let (Some(level), Some(text)) = (
+            child.get("level").and_then(Value::as_str),
+            child.get("message").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let suggestion = child
+            .get("spans")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find_map(|span| span.get("suggested_replacement")?.as_str());
+        let _ = write!(output, "\n  {}: {}", level, text);
+        if let Some(suggestion) = suggestion.filter(|s| !s.is_empty() && !s.contains('\n')) {
+            let _ = write!(output, ": `{}`", suggestion);
+        }
+    }
+    Some(output)
+}
+
+/// A failed test and what it printed.
+#[derive(Debug, Default)]
+struct FailedTest {
+    name: String,
+    output: String,
+}
+
+/// What was learned from cargo's output, which mixes JSON messages from
+/// the build with the plain text of the test harness.
+#[derive(Debug, Default)]
+struct Report {
+    diagnostics: Vec<String>,
+    seen: HashSet<String>,
+    errors: usize,
+    warnings: usize,
+    build_succeeded: Option<bool>,
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    failures: Vec<FailedTest>,
+    /// The test whose output is being read, in a `failures:` section.
+    reading: Option<usize>,
+}
+
+impl Report {
+    /// Takes in one line of cargo's standard output.
+    fn push_line(&mut self, line: &str) {
+        if line.starts_with('{') {
+            if let Ok(message) = serde_json::from_str::<Value>(line) {
+                self.push_message(&message);
+                return;
+            }
+        }
+        self.push_test_line(line);
+    }
+
+    fn push_message(&mut self, message: &Value) {
+        match message.get("reason").and_then(Value::as_str) {
+            Some("compiler-message") => {
+                let Some(message) = message.get("message") else {
+                    return;
+                };
+                let Some(diagnostic) = format_diagnostic(message) else {
+                    return;
+                };
+                // The same diagnostic is reported once per target built.
+                if !self.seen.insert(diagnostic.clone()) {
+                    return;
+                }
+                match message.get("level").and_then(Value::as_str) {
+                    Some("error" | "error: internal compiler error") => self.errors += 1,
+                    Some("warning") => self.warnings += 1,
+                    _ => {}
+                }
+                self.diagnostics.push(diagnostic);
+            }
+            Some("build-finished") => {
+                self.build_succeeded = message.get("success").and_then(Value::as_bool);
+            }
+            _ => {}
+        }
+    }
+
+    /// Follows the test harness: `test name ... FAILED` lines, the
+    /// `---- name stdout ----` blocks of the failures section, and the
+    /// `test result:` summary of each test binary.
+    fn push_test_line(&mut self, line: &str) {
+        if let Some(rest) = line.strip_prefix("---- ") {
+            let name = rest
+                .trim_end_matches(" ----")
+                .trim_end_matches(" stdout")
+                .trim_end_matches(" stderr");
+            self.reading = Some(self.failure(name));
+            return;
+        }
+        if line == "failures:" || line.starts_with("test result:") {
+            self.reading = None;
+        }
+        if let Some(index) = self.reading {
+            let output = &mut self.failures[index].output;
+            output.push_str(line);
+            output.push('\n');
+            return;
+        }
+        if let Some(result) = line.strip_prefix("test result:") {
+            for part in result.split([';', '.']) {
+                let mut words = part.split_whitespace();
+                let (Some(count), Some(kind)) = (words.next(), words.next()) else {
+                    continue;
+                };
+                let Ok(count) = count.parse::<usize>() else {
+                    continue;
+                };
+                match kind {
+                    "passed" => self.passed += count,
+                    "failed" => self.failed += count,
+                    "ignored" => self.ignored += count,
+                    _ => {}
+                }
+            }
+        } else if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" ... FAILED"))
+        {
+            self.failure(name);
+        }
+    }
+
+    /// Returns the index of the failed test `name`, adding it if needed.
+    fn failure(&mut self, name: &str) -> usize {
+        match self.failures.iter().position(|test| test.name == name) {
+            Some(index) => index,
+            None => {
+                self.failures.push(FailedTest {
+                    name: name.to_string(),
+                    output: String::new(),
+                });
+                self.failures.len() - 1
+            }
+        }
+    }
+
+    /// Summarizes the run for the model.
+    fn render(&self, subcommand: Subcommand, status: &str, max_diagnostics: usize) -> String {
+        let mut output = format!(
+            "cargo {}: {} ({} errors, {} warnings)",
+            subcommand.as_str(),
+            status,
+            self.errors,
+            self.warnings
+        );
+        if subcommand == Subcommand::Test && self.build_succeeded != Some(false) {
+            let _ = write!(
+                output,
+                "\nTests: {} passed, {} failed, {} ignored",
+                self.passed, self.failed, self.ignored
+            );
+        }
+        if !self.diagnostics.is_empty() {
+            output.push_str("\n\nDiagnostics:\n");
+            for diagnostic in self.diagnostics.iter().take(max_diagnostics) {
+                let _ = writeln!(output, "{}", diagnostic);
+            }
+            if self.diagnostics.len() > max_diagnostics {
+                let _ = writeln!(
+                    output,
+                    "... {} more",
+                    self.diagnostics.len() - max_diagnostics
+                );
+            }
+        }
+        if !self.failures.is_empty() {
+            output.truncate(output.trim_end().len());
+            output.push_str("\n\nFailed tests:\n");
+            for test in &self.failures {
+                let _ = writeln!(output, "- {}", test.name);
+                let text = test.output.trim();
+                let text = match text.char_indices().nth(MAX_TEST_OUTPUT_CHARS) {
+                    Some((cut, _)) => format!("{}\n...", &text[..cut]),
+                    None => text.to_string(),
+                };
+                for line in text.lines() {
+                    let _ = writeln!(output, "    {}", line);
+                }
+            }
+        }
+        output.trim_end().to_string()
+    }
+}
+
+/// A tool that runs `cargo check`, `cargo clippy` or `cargo test` and
+/// summarizes the result.
+///
+/// Compiler messages are read from `--message-format=json` and reduced to
+/// one `file:line:column: level: message` entry each, with their notes and
+/// suggestions, and duplicates across targets removed. For tests, the names
+/// of failing tests are listed with what they printed.
+///
+/// Commands run in the sandbox, like those of `SystemTool`, since building a
+/// crate runs its build scripts and procedural macros.
+pub struct CargoTool {
+    sandbox: Sandbox,
+}
+
+impl CargoTool {
+    /// Creates a new `CargoTool` for the crate or workspace at the root of
+    /// `workspace`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if the configured sandbox
+    /// backend is not available.
+    pub fn new(config: &SandboxConfig, workspace: Workspace) -> Result<Self> {
+        Ok(Self {
+            sandbox: Sandbox::new(config, workspace)?,
+        })
+    }
+
+    /// Builds the shell command for the arguments.
+    fn script(&self, args: &CargoArgs) -> String {
+        let mut script = format!("cargo {} --message-format=json", args.command.as_str());
+        if let Some(path) = &args.path {
+            let manifest = format!("{}/Cargo.toml", path.trim_end_matches('/'));
+            let _ = write!(script, " --manifest-path {}", shell_quote(&manifest));
+        }
+        if let Some(package) = &args.package {
+            let _ = write!(script, " --package {}", shell_quote(package));
+        }
+        if args.all_targets && args.command != Subcommand::Test {
+            script.push_str(" --all-targets");
+        }
+        if args.command == Subcommand::Test {
+            script.push_str(" --no-fail-fast");
+            if let Some(filter) = &args.filter {
+                let _ = write!(script, " -- {}", shell_quote(filter));
+            }
+        }
+        script
+    }
+}
+
+#[async_trait]
+impl Tool for CargoTool {
+    /// Returns the name of the tool, "CargoTool".
+    fn name(&self) -> &str {
+        "CargoTool"
+    }
+
+    fn description(&self) -> String {
+        "Runs `cargo check`, `cargo clippy` or `cargo test` and lists compiler diagnostics as \
+         `file:line:column: level: message`, and failing tests with their output. Args: the \
+         `command`, or a JSON object with the `command` and optionally the `package`, the \
+         crate's `path`, `all_targets`, a test name `filter` and `max_diagnostics`."
+            .to_string()
+    }
+
+    /// Runs cargo and summarizes its output.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - `check`, `clippy` or `test`, or a JSON object with the
+    ///   `command` and its options.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the summary, or an error if cargo could not be
+    /// started or the path is outside the workspace. Build and test
+    /// failures are part of the summary rather than errors.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = CargoArgs::parse(args)?;
+        if args
+            .path
+            .as_deref()
+            .is_some_and(|path| path.starts_with('/') || path.split('/').any(|part| part == ".."))
+        {
+            return Err(anyhow!("The path must be relative to the workspace"));
+        }
+
+        let mut command = self.sandbox.command(&self.script(&args)).await?;
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+        let pid = child.id();
+        let stderr = child.stderr.take();
+        let stderr = tokio::spawn(async move {
+            let capture = OutputCapture::new(MAX_STDERR_BYTES);
+            match stderr {
+                Some(stderr) => capture.read_from(stderr).await,
+                None => Ok(capture),
+            }
+        });
+
+        let mut report = Report::default();
+        let stdout = child.stdout.take();
+        let read = async {
+            let Some(stdout) = stdout else {
+                return Ok::<_, std::io::Error>(());
+            };
+            let mut reader = BufReader::new(stdout);
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).await? > 0 {
+                report.push_line(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']));
+                line.clear();
+            }
+            Ok(())
+        };
+        let finished = tokio::time::timeout(self.sandbox.wall_time(), async {
+            read.await?;
+            child.wait().await
+        })
+        .await;
+        if let Some(pid) = pid {
+            kill_process_group(pid);
+        }
+
+        let status = match finished {
+            Ok(status) => match status?.code() {
+                Some(0) => "succeeded".to_string(),
+                Some(code) => format!("failed with exit code {}", code),
+                None => "was killed".to_string(),
+            },
+            Err(_) => {
+                child.wait().await?;
+                format!(
+                    "timed out after {} seconds",
+                    self.sandbox.wall_time().as_secs()
+                )
+            }
+        };
+        let mut output = report.render(args.command, &status, args.max_diagnostics);
+        if report.diagnostics.is_empty() && report.failures.is_empty() && status != "succeeded" {
+            let stderr = stderr.await??.to_string_lossy();
+            let _ = write!(output, "\n\nstderr:\n{}", stderr.trim_end());
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_summarizes_diagnostics_and_failures() {
+        let error = serde_json::json!({
+            "reason": "compiler-message",
+            "message": {
+                "message": "mismatched types",
+                "level": "error",
+                "code": {"code": "E0308"},
+                "spans": [{
+                    "file_name": "src/lib.rs",
+                    "line_start": 3,
+                    "column_start": 18,
+                    "is_primary": true,
+                    "label": "expected `u32`, found `&str`"
+                }],
+                "children": [{
+                    "message": "try using a conversion method",
+                    "level": "help",
+                    "spans": [{"suggested_replacement": "x.parse()?"}]
+                }]
+            }
+        })
+        .to_string();
+        let aborting = serde_json::json!({
+            "reason": "compiler-message",
+            "message": {"message": "aborting due to 1 previous error", "level": "error", "spans": []}
+        })
+        .to_string();
+        let output = [
+            error.as_str(),
+            error.as_str(),
+            aborting.as_str(),
+            r#"{"reason":"build-finished","success":true}"#,
+            "running 3 tests",
+            "test tests::adds ... ok",
+            "test tests::parses ... FAILED",
+            "",
+            "failures:",
+            "",
+            "---- tests::parses stdout ----",
+            "thread 'tests::parses' panicked at src/lib.rs:9:5:",
+            "assertion failed: parsed",
+            "",
+            "failures:",
+            "    tests::parses",
+            "",
+            "test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out",
+        ];
+
+        let mut report = Report::default();
+        for line in output {
+            report.push_line(line);
+        }
+        assert_eq!(
+            report.render(Subcommand::Test, "failed with exit code 101", 30),
+            "cargo test: failed with exit code 101 (1 errors, 0 warnings)\n\
+             Tests: 1 passed, 1 failed, 1 ignored\n\
+             \n\
+             Diagnostics:\n\
+             src/lib.rs:3:18: error[E0308]: mismatched types (expected `u32`, found `&str`)\n  \
+             help: try using a conversion method: `x.parse()?`\n\
+             \n\
+             Failed tests:\n\
+             - tests::parses\n    \
+             thread 'tests::parses' panicked at src/lib.rs:9:5:\n    \
+             assertion failed: parsed"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use crate::tools::{workspace::Workspace, Tool};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::{fmt::Write, fs, path::Path};
+
+/// Files larger than this are skipped, as they are almost never source code.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Lines longer than this are cut short in the output.
+const MAX_LINE_LENGTH: usize = 300;
+
+/// The arguments accepted by `CodeSearchTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `pattern` with all other options defaulted.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SearchArgs {
+    /// The regular expression (or literal string) to search for.
+    pattern: String,
+    /// Treat `pattern` as a literal string rather than a regex.
+    #[serde(default)]
+    literal: bool,
+    /// Match case-insensitively.
+    #[serde(default)]
+    case_insensitive: bool,
+    /// The directory or file to search, relative to the workspace root.
+    #[serde(default)]
+    path: String,
+    /// File globs to include, e.g. `*.rs`. Prefix with `!` to exclude.
+    #[serde(default)]
+    glob: Vec<String>,
+    /// The number of lines of context to show around each match.
+    #[serde(default)]
+    context: usize,
+    /// The maximum number of matching lines to return.
+    #[serde(default = "default_max_results")]
+    max_results: usize,
+    /// Search hidden files and directories.
+    #[serde(default)]
+    hidden: bool,
+    /// Do not respect `.gitignore` and `.ignore` files.
+    #[serde(default)]
+    no_ignore: bool,
+}
+
+fn default_max_results() -> usize {
+    100
+}
+
+impl SearchArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for CodeSearchTool: {}", e));
+        }
+        if args.is_empty() {
+            return Err(anyhow!("CodeSearchTool requires a search pattern"));
+        }
+        Ok(Self {
+            pattern: args.to_string(),
+            literal: false,
+            case_insensitive: false,
+            path: String::new(),
+            glob: Vec::new(),
+            context: 0,
+            max_results: default_max_results(),
+            hidden: false,
+            no_ignore: false,
+        })
+    }
+
+    fn regex(&self) -> Result<Regex> {
+        let pattern = if self.literal {
+            regex::escape(&self.pattern)
+        } else {
+            self.pattern.clone()
+        };
+        Ok(RegexBuilder::new(&pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()?)
+    }
+}
+
+/// A tool for searching file contents across the workspace.
+///
+/// This tool runs a regex or literal search over every file below a
+/// directory in the workspace, honoring `.gitignore` files, and returns the
+/// matching lines as `path:line: text`. Context lines are shown as
+/// `path-line- text`, in the style of `grep`.
+pub struct CodeSearchTool {
+    workspace: Workspace,
+}
+
+impl CodeSearchTool {
+    /// Creates a new `CodeSearchTool` confined to the given workspace.
+    pub fn new(workspace: Workspace) -> Self {
+        Self { workspace }
+    }
+}
+
+/// Walks the search root and collects the formatted results.
+fn search(workspace: &Workspace, args: &SearchArgs) -> Result<String> {
+    let regex = args.regex()?;
+    let root = workspace.resolve(&args.path)?;
+
+    let mut overrides = OverrideBuilder::new(&root);
+    for glob in &args.glob {
+        overrides.add(glob)?;
+    }
+
+    let walker = WalkBuilder::new(&root)
+        .hidden(!args.hidden)
+        .git_ignore(!args.no_ignore)
+        .git_global(!args.no_ignore)
+        .git_exclude(!args.no_ignore)
+        .ignore(!args.no_ignore)
+        .parents(!args.no_ignore)
+        .require_git(false)
+        .overrides(overrides.build()?)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+
+    let mut output = String::new();
+    let mut matches = 0;
+    let mut truncated = false;
+
+    'files: for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+            continue;
+        }
+        let Some(content) = read_text(entry.path()) else {
+            continue;
+        };
+
+        let display = workspace.relative(entry.path()).display().to_string();
+        let lines: Vec<&str> = content.lines().collect();
+        let mut last_printed: Option<usize> = None;
+
+        for (i, line) in lines.iter().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            if matches == args.max_results {
+                truncated = true;
+                break 'files;
+            }
+            matches += 1;
+
+            let start = i.saturating_sub(args.context);
+            let start = last_printed.map_or(start, |last| start.max(last + 1));
+            if let Some(last) = last_printed {
+                if args.context > 0 && start > last + 1 {
+                    output.push_str("--\n");
+                }
+            }
+            for (j, context_line) in lines.iter().enumerate().take(i).skip(start) {
+                let _ = writeln!(output, "{}-{}- {}", display, j + 1, clip(context_line));
+            }
+            let _ = writeln!(output, "{}:{}: {}", display, i + 1, clip(line));
+            last_printed = Some(i);
+
+            let end = (i + args.context).min(lines.len().saturating_sub(1));
+            for (j, context_line) in lines.iter().enumerate().take(end + 1).skip(i + 1) {
+                if regex.is_match(context_line) {
+                    break;
+                }
+                let _ = writeln!(output, "{}-{}- {}", display, j + 1, clip(context_line));
+                last_printed = Some(j);
+            }
+        }
+    }
+
+    if matches == 0 {
+        return Ok(format!("No matches found for `{}`.", args.pattern));
+    }
+    if truncated {
+        let _ = writeln!(
+            output,
+            "(results truncated after {} matches; narrow the search or raise max_results)",
+            args.max_results
+        );
+    }
+    Ok(output)
+}
+
+/// Reads a file as UTF-8 text, returning `None` for binary or unreadable
+/// files.
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Shortens overly long lines, such as minified code, to keep the output
+/// readable.
+fn clip(line: &str) -> &str {
+    match line.char_indices().nth(MAX_LINE_LENGTH) {
+        Some((idx, _)) => &line[..idx],
+        None => line,
+    }
+}
+
+#[async_trait]
+impl Tool for CodeSearchTool {
+    /// Returns the name of the tool, "CodeSearchTool".
+    fn name(&self) -> &str {
+        "CodeSearchTool"
+    }
+
+    /// Executes the search.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either a bare search pattern, or a JSON object with the
+    ///   fields `pattern`, `literal`, `case_insensitive`, `path`, `glob`,
+    ///   `context`, `max_results`, `hidden` and `no_ignore`.
+    ///   Example: `{"pattern": "fn main", "glob": ["*.rs"], "context": 2}`
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the matching lines in `path:line: text` form, or an
+    /// error if the arguments are invalid or the path lies outside the
+    /// workspace.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = SearchArgs::parse(args)?;
+        let workspace = self.workspace.clone();
+        tokio::task::spawn_blocking(move || search(&workspace, &args)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, CodeSearchTool) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(
+            dir.path().join("src/main.rs"),
+            "use std::io;\n\nfn main() {\n    helper();\n}\n\nfn helper() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/notes.md"),
+            "fn main is the entry point\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("target/generated.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        let tool = CodeSearchTool::new(Workspace::new(dir.path()).unwrap());
+        (dir, tool)
+    }
+
+    #[tokio::test]
+    async fn test_search_respects_globs_and_gitignore() {
+        let (_dir, tool) = setup();
+        let result = tool
+            .execute(r#"{"pattern": "fn \\w+\\(", "glob": ["*.rs"]}"#)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "src/main.rs:3: fn main() {\nsrc/main.rs:7: fn helper() {}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_with_context_and_limit() {
+        let (_dir, tool) = setup();
+        let result = tool
+            .execute(r#"{"pattern": "helper()", "literal": true, "context": 1, "max_results": 1}"#)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            "src/main.rs-3- fn main() {\nsrc/main.rs:4:     helper();\nsrc/main.rs-5- }\n\
+             (results truncated after 1 matches; narrow the search or raise max_results)\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_paths_outside_workspace() {
+        let (_dir, tool) = setup();
+        let result = tool.execute(r#"{"pattern": "root", "path": "../"}"#).await;
+
+        assert!(result.is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::tools::Tool;
+use crate::tools::{workspace::Workspace, Tool};
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::fs;
@@ -7,8 +7,17 @@ use tokio::fs;
 ///
 /// This tool takes a filepath and a JSON string containing the content to be
 /// written to the file. It is used by the agent to create or overwrite files
-/// in the filesystem.
-pub struct CodeWriterTool;
+/// inside the workspace.
+pub struct CodeWriterTool {
+    workspace: Workspace,
+}
+
+impl CodeWriterTool {
+    /// Creates a new `CodeWriterTool` confined to the given workspace.
+    pub fn new(workspace: Workspace) -> Self {
+        Self { workspace }
+    }
+}
 
 #[async_trait]
 impl Tool for CodeWriterTool {
@@ -28,7 +37,8 @@ impl Tool for CodeWriterTool {
     /// # Returns
     ///
     /// A `Result` with a success message if the file was written correctly, or
-    /// an error if the arguments are invalid or the file cannot be written.
+    /// an error if the arguments are invalid, the path lies outside the
+    /// workspace, or the file cannot be written.
     #[tracing::instrument(skip(self))]
     async fn execute(&self, args: &str) -> Result<String> {
         let parts: Vec<&str> = args.splitn(2, ' ').collect();
@@ -43,7 +53,7 @@ impl Tool for CodeWriterTool {
         // The content is a JSON string, so we need to parse it to get the raw string.
         let content: String = serde_json::from_str(content_json)?;
 
-        fs::write(filepath, content).await?;
+        fs::write(self.workspace.resolve(filepath)?, content).await?;
         Ok(format!("Successfully wrote to {}", filepath))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,531 @@
+use anyhow::{anyhow, Result};
+
+/// A redirection attached to a command, e.g. `> out.txt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirection {
+    /// The operator without any file descriptor prefix, e.g. `>`, `>>` or
+    /// `<`.
+    pub op: String,
+    /// The file (or descriptor, for `>&`) being redirected to or from.
+    pub target: String,
+}
+
+impl Redirection {
+    /// Returns whether the redirection writes to its target.
+    pub fn is_write(&self) -> bool {
+        self.op.contains('>')
+    }
+}
+
+/// A single command with its arguments and redirections.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    /// The program followed by its arguments, with quotes removed and
+    /// leading variable assignments stripped.
+    pub argv: Vec<String>,
+    /// The redirections, in the order they appear.
+    pub redirections: Vec<Redirection>,
+}
+
+impl SimpleCommand {
+    /// Returns the command as a single line, for error messages.
+    pub fn display(&self) -> String {
+        let mut parts = self.argv.clone();
+        for redirection in &self.redirections {
+            parts.push(format!("{} {}", redirection.op, redirection.target));
+        }
+        parts.join(" ")
+    }
+}
+
+/// Commands connected with `|`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    pub commands: Vec<SimpleCommand>,
+}
+
+/// The result of parsing a shell script.
+///
+/// Commands found inside `$(...)`, backticks, process substitutions,
+/// `sh -c '...'` and `eval` are parsed recursively and appended as extra
+/// pipelines, and wrappers such as `sudo` or `env` are followed by the
+/// command they run, so that a policy sees every command the script would
+/// execute.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub pipelines: Vec<Pipeline>,
+}
+
+impl Script {
+    /// Iterates over every command in the script.
+    pub fn commands(&self) -> impl Iterator<Item = &SimpleCommand> {
+        self.pipelines.iter().flat_map(|p| p.commands.iter())
+    }
+}
+
+/// Words that start a compound command and can be skipped to find the
+/// command that follows them.
+const KEYWORDS: &[&str] = &[
+    "!", "{", "if", "then", "else", "elif", "while", "until", "do", "time",
+];
+
+/// Words that only close a compound command.
+const TERMINATORS: &[&str] = &["}", "fi", "done", "esac"];
+
+/// Commands that run another command given as their remaining arguments.
+const WRAPPERS: &[&str] = &[
+    "sudo", "doas", "env", "nice", "nohup", "timeout", "xargs", "exec", "command", "builtin",
+    "stdbuf", "time", "watch", "strace", "chroot", "setsid",
+];
+
+/// Shells that can run a script passed with `-c`.
+const SHELLS: &[&str] = &["sh", "bash", "dash", "zsh", "ksh", "ash", "fish"];
+
+/// The maximum nesting depth of substitutions and `sh -c` scripts.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    /// `|`, `|&`, `&&`, `||`, `;`, `&`, a newline, `(` or `)`.
+    Operator(&'static str),
+    Redirect(String),
+}
+
+/// Parses a shell command line into its pipelines and commands.
+///
+/// This understands the common subset of POSIX shell syntax that agents
+/// produce: quoting and escapes, pipes, command lists, redirections
+/// (including here-documents), subshells, command and process substitution.
+/// It does not expand variables or globs.
+///
+/// # Returns
+///
+/// A `Result` with the parsed script, or an error if the quoting is
+/// unbalanced or the script is nested too deeply.
+pub fn parse(script: &str) -> Result<Script> {
+    let mut parsed = Script::default();
+    parse_into(script, 0, &mut parsed)?;
+    Ok(parsed)
+}
+
+fn parse_into(script: &str, depth: usize, out: &mut Script) -> Result<()> {
+    if depth > MAX_DEPTH {
+        return Err(anyhow!("Command is nested too deeply"));
+    }
+    let mut nested = Vec::new();
+    let tokens = Lexer::new(script, &mut nested).tokenize()?;
+
+    let mut pipeline = Pipeline::default();
+    let mut command = SimpleCommand::default();
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(word) => {
+                let at_start = command.argv.is_empty();
+                if at_start && KEYWORDS.contains(&word.as_str()) {
+                    continue;
+                }
+                if at_start && is_assignment(&word) {
+                    continue;
+                }
+                command.argv.push(word);
+            }
+            Token::Redirect(op) => {
+                let target = match tokens.next() {
+                    Some(Token::Word(target)) => target,
+                    _ => return Err(anyhow!("Redirection `{}` is missing a target", op)),
+                };
+                command.redirections.push(Redirection { op, target });
+            }
+            Token::Operator(op) => {
+                finish_command(&mut command, &mut pipeline, &mut nested);
+                if op != "|" && op != "|&" {
+                    finish_pipeline(&mut pipeline, out);
+                }
+            }
+        }
+    }
+    finish_command(&mut command, &mut pipeline, &mut nested);
+    finish_pipeline(&mut pipeline, out);
+
+    for script in nested {
+        parse_into(&script, depth + 1, out)?;
+    }
+    Ok(())
+}
+
+fn finish_command(command: &mut SimpleCommand, pipeline: &mut Pipeline, nested: &mut Vec<String>) {
+    let command = std::mem::take(command);
+    if command.argv.is_empty() && command.redirections.is_empty() {
+        return;
+    }
+    if command.argv.len() == 1 && TERMINATORS.contains(&command.argv[0].as_str()) {
+        return;
+    }
+
+    // Follow wrappers like `sudo env FOO=1 nice rm -rf /` down to the command
+    // they actually run, keeping each level so that rules can match either.
+    let mut current = command.argv.clone();
+    pipeline.commands.push(command);
+    while let Some(inner) = unwrap(&current, nested) {
+        if inner.is_empty() {
+            break;
+        }
+        pipeline.commands.push(SimpleCommand {
+            argv: inner.clone(),
+            redirections: Vec::new(),
+        });
+        current = inner;
+    }
+}
+
+fn finish_pipeline(pipeline: &mut Pipeline, out: &mut Script) {
+    let pipeline = std::mem::take(pipeline);
+    if !pipeline.commands.is_empty() {
+        out.pipelines.push(pipeline);
+    }
+}
+
+/// Returns the command run by a wrapper command, if `argv` is one. Scripts
+/// passed to a shell with `-c` or to `eval` are pushed onto `nested` instead.
+fn unwrap(argv: &[String], nested: &mut Vec<String>) -> Option<Vec<String>> {
+    let program = basename(argv.first()?);
+
+    if SHELLS.contains(&program) {
+        // `-c` may be combined with other options, as in `bash -ec`.
+        let is_c_option =
+            |arg: &String| arg.starts_with('-') && !arg.starts_with("--") && arg.ends_with('c');
+        if let Some(script) = argv
+            .iter()
+            .position(is_c_option)
+            .and_then(|i| argv.get(i + 1))
+        {
+            nested.push(script.clone());
+        }
+        return None;
+    }
+    if program == "eval" {
+        nested.push(argv[1..].join(" "));
+        return None;
+    }
+    if !WRAPPERS.contains(&program) {
+        return None;
+    }
+
+    let mut rest = argv[1..].iter().peekable();
+    while let Some(arg) = rest.peek() {
+        let is_option = arg.starts_with('-') && arg.len() > 1;
+        let is_assignment = program == "env" && is_assignment(arg);
+        // `timeout` takes a duration before the command.
+        let is_duration =
+            program == "timeout" && arg.chars().next().is_some_and(|c| c.is_ascii_digit());
+        if arg.as_str() == "--" {
+            rest.next();
+            break;
+        }
+        if is_option || is_assignment || is_duration {
+            rest.next();
+        } else {
+            break;
+        }
+    }
+    Some(rest.cloned().collect())
+}
+
+/// Returns the file name part of a program path, e.g. `rm` for `/bin/rm`.
+pub fn basename(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+struct Lexer<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    tokens: Vec<Token>,
+    nested: &'a mut Vec<String>,
+    /// Here-document delimiters whose bodies start after the next newline.
+    heredocs: Vec<String>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(script: &str, nested: &'a mut Vec<String>) -> Self {
+        Self {
+            chars: script.chars().collect(),
+            pos: 0,
+            tokens: Vec::new(),
+            nested,
+            heredocs: Vec::new(),
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>> {
+        while let Some(c) = self.peek(0) {
+            match c {
+                ' ' | '\t' => self.pos += 1,
+                '\\' if self.peek(1) == Some('\n') => self.pos += 2,
+                '\n' => {
+                    self.pos += 1;
+                    self.tokens.push(Token::Operator("\n"));
+                    self.skip_heredocs();
+                }
+                '#' => {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                ';' => {
+                    // `;;` only appears in `case`, treat it like `;`.
+                    while self.peek(0) == Some(';') {
+                        self.pos += 1;
+                    }
+                    self.tokens.push(Token::Operator(";"));
+                }
+                '|' => self.operator(&[("||", "||"), ("|&", "|&"), ("|", "|")]),
+                '&' if self.peek(1) == Some('>') => {
+                    let op = if self.peek(2) == Some('>') {
+                        "&>>"
+                    } else {
+                        "&>"
+                    };
+                    self.pos += op.len();
+                    self.tokens.push(Token::Redirect(op.to_string()));
+                }
+                '&' => self.operator(&[("&&", "&&"), ("&", "&")]),
+                '(' => self.operator(&[("(", "(")]),
+                ')' => self.operator(&[(")", ")")]),
+                '<' | '>' if self.peek(1) == Some('(') => {
+                    self.pos += 2;
+                    let inner = self.capture_parens()?;
+                    self.nested.push(inner);
+                    self.tokens.push(Token::Word(String::new()));
+                }
+                '<' | '>' => self.redirect(),
+                _ => self.word()?,
+            }
+        }
+        Ok(self.tokens)
+    }
+
+    fn operator(&mut self, candidates: &[(&str, &'static str)]) {
+        for (text, op) in candidates {
+            let matches = text
+                .chars()
+                .enumerate()
+                .all(|(i, c)| self.peek(i) == Some(c));
+            if matches {
+                self.pos += text.len();
+                self.tokens.push(Token::Operator(op));
+                return;
+            }
+        }
+    }
+
+    fn redirect(&mut self) {
+        const OPERATORS: &[&str] = &["<<<", "<<-", "<<", "<&", "<>", "<", ">>", ">&", ">|", ">"];
+        for op in OPERATORS {
+            let matches = op.chars().enumerate().all(|(i, c)| self.peek(i) == Some(c));
+            if matches {
+                self.pos += op.len();
+                let op = match *op {
+                    ">|" => ">",
+                    "<<-" => "<<",
+                    other => other,
+                };
+                self.tokens.push(Token::Redirect(op.to_string()));
+                return;
+            }
+        }
+    }
+
+    /// Skips the bodies of pending here-documents, which are data rather
+    /// than commands.
+    fn skip_heredocs(&mut self) {
+        for delimiter in std::mem::take(&mut self.heredocs) {
+            loop {
+                let start = self.pos;
+                while self.peek(0).is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+                let line: String = self.chars[start..self.pos].iter().collect();
+                if self.peek(0).is_none() {
+                    return;
+                }
+                self.pos += 1;
+                if line.trim_start_matches('\t') == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn word(&mut self) -> Result<()> {
+        let mut word = String::new();
+        while let Some(c) = self.peek(0) {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '|' | '&' | '(' | ')' => break,
+                '<' | '>' => {
+                    // A leading file descriptor, as in `2>` or `2>&1`.
+                    if !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()) {
+                        self.redirect();
+                        return Ok(());
+                    }
+                    break;
+                }
+                '\\' => {
+                    if let Some(next) = self.peek(1) {
+                        word.push(next);
+                    }
+                    self.pos += 2;
+                }
+                '\'' => {
+                    self.pos += 1;
+                    loop {
+                        match self.peek(0) {
+                            Some('\'') => break,
+                            Some(c) => word.push(c),
+                            None => return Err(anyhow!("Unterminated single quote")),
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                }
+                '"' => {
+                    self.pos += 1;
+                    loop {
+                        match self.peek(0) {
+                            Some('"') => break,
+                            Some('\\') if matches!(self.peek(1), Some('"' | '\\' | '$' | '`')) => {
+                                word.push(self.peek(1).unwrap_or_default());
+                                self.pos += 2;
+                                continue;
+                            }
+                            Some('$') if self.peek(1) == Some('(') => {
+                                self.substitution(&mut word)?;
+                                continue;
+                            }
+                            Some('`') => {
+                                self.backticks(&mut word)?;
+                                continue;
+                            }
+                            Some(c) => word.push(c),
+                            None => return Err(anyhow!("Unterminated double quote")),
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                }
+                '$' if self.peek(1) == Some('(') => self.substitution(&mut word)?,
+                '`' => self.backticks(&mut word)?,
+                _ => {
+                    word.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        if let Some(Token::Redirect(op)) = self.tokens.last() {
+            if op == "<<" {
+                self.heredocs.push(word.clone());
+            }
+        }
+        self.tokens.push(Token::Word(word));
+        Ok(())
+    }
+
+    /// Handles `$(...)` and `$((...))` at the current position.
+    fn substitution(&mut self, word: &mut String) -> Result<()> {
+        self.pos += 2;
+        if self.peek(0) == Some('(') {
+            // Arithmetic expansion contains no commands.
+            self.pos += 1;
+            let inner = self.capture_parens()?;
+            if self.peek(0) == Some(')') {
+                self.pos += 1;
+            }
+            word.push_str(&format!("$(({}))", inner));
+            return Ok(());
+        }
+        let inner = self.capture_parens()?;
+        word.push_str("$(...)");
+        self.nested.push(inner);
+        Ok(())
+    }
+
+    fn backticks(&mut self, word: &mut String) -> Result<()> {
+        self.pos += 1;
+        let mut inner = String::new();
+        loop {
+            match self.peek(0) {
+                Some('`') => break,
+                Some('\\') if self.peek(1) == Some('`') => {
+                    inner.push('`');
+                    self.pos += 1;
+                }
+                Some(c) => inner.push(c),
+                None => return Err(anyhow!("Unterminated backtick")),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        word.push_str("$(...)");
+        self.nested.push(inner);
+        Ok(())
+    }
+
+    /// Captures everything up to the `)` matching an already consumed `(`.
+    fn capture_parens(&mut self) -> Result<String> {
+        let mut depth = 1;
+        let mut inner = String::new();
+        let mut quote: Option<char> = None;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some('"'), '\\') => {
+                    inner.push(c);
+                    if let Some(next) = self.peek(0) {
+                        inner.push(next);
+                        self.pos += 1;
+                    }
+                    continue;
+                }
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '\\') => {
+                    inner.push(c);
+                    if let Some(next) = self.peek(0) {
+                        inner.push(next);
+                        self.pos += 1;
+                    }
+                    continue;
+                }
+                (None, '(') => depth += 1,
+                (None, ')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(inner);
+                    }
+                }
+                _ => {}
+            }
+            inner.push(c);
+        }
+        Err(anyhow!("Unterminated parenthesis"))
+    }
+}
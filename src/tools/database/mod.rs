@@ -0,0 +1,414 @@
+use crate::tools::Tool;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[cfg(feature = "postgres")]
+mod postgres;
+mod sqlite;
+
+/// The upper bound on the number of rows a query may return.
+const MAX_ROWS: usize = 500;
+
+/// The maximum number of characters shown per cell.
+const MAX_CELL_CHARS: usize = 200;
+
+/// A `[databases.<name>]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// The database to connect to: `sqlite://<path>`, or with the
+    /// `postgres` feature a `postgres://` connection string.
+    pub url: String,
+    /// Open the database so that it cannot be modified.
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+    /// The maximum time a statement may run.
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_read_only() -> bool {
+    true
+}
+
+fn default_timeout_seconds() -> u64 {
+    30
+}
+
+/// The result of a statement, with every value as text.
+#[derive(Debug, Default)]
+struct Rows {
+    columns: Vec<String>,
+    /// The values of each row. `None` is SQL `NULL`.
+    rows: Vec<Vec<Option<String>>>,
+    /// Whether rows were left out because of the row limit.
+    truncated: bool,
+    /// The number of rows changed, for statements that return no rows.
+    affected: Option<u64>,
+}
+
+/// A connection to a database.
+#[async_trait]
+trait Database: Send + Sync {
+    /// Lists the tables and views.
+    async fn tables(&self) -> Result<Rows>;
+
+    /// Describes the columns of a table.
+    async fn describe(&self, table: &str) -> Result<Rows>;
+
+    /// Runs a statement, keeping at most `max_rows` rows of its result.
+    async fn query(&self, sql: &str, max_rows: usize) -> Result<Rows>;
+}
+
+/// Connects to the database described by `config`.
+async fn connect(config: &DatabaseConfig) -> Result<Box<dyn Database>> {
+    let timeout = Duration::from_secs(config.timeout_seconds);
+    if let Some(path) = config.url.strip_prefix("sqlite://") {
+        return Ok(Box::new(sqlite::Sqlite::open(
+            path,
+            config.read_only,
+            timeout,
+        )?));
+    }
+    if config.url.starts_with("postgres://") || config.url.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        return Ok(Box::new(
+            postgres::Postgres::connect(&config.url, config.read_only, timeout).await?,
+        ));
+        #[cfg(not(feature = "postgres"))]
+        return Err(anyhow!(
+            "Postgres support requires building with the `postgres` feature"
+        ));
+    }
+    Err(anyhow!("Unsupported database URL: {}", config.url))
+}
+
+/// What `DatabaseTool` should do.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    /// List the tables and views.
+    Tables,
+    /// Describe the columns of `table`.
+    Describe,
+    /// Run `sql`.
+    #[default]
+    Query,
+}
+
+/// How results are formatted.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    #[default]
+    Markdown,
+    Csv,
+}
+
+/// The arguments accepted by `DatabaseTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is run as the `sql` against the only database.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct DatabaseArgs {
+    /// The database to use. May be left out if only one is configured.
+    #[serde(default)]
+    database: Option<String>,
+    #[serde(default)]
+    action: Action,
+    /// The table to describe.
+    #[serde(default)]
+    table: Option<String>,
+    /// The statement to run.
+    #[serde(default)]
+    sql: Option<String>,
+    #[serde(default)]
+    format: Format,
+    /// The maximum number of rows to return.
+    #[serde(default = "default_max_rows")]
+    max_rows: usize,
+}
+
+fn default_max_rows() -> usize {
+    50
+}
+
+impl DatabaseArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for DatabaseTool: {}", e));
+        }
+        Ok(Self {
+            database: None,
+            action: Action::Query,
+            table: None,
+            sql: Some(args.to_string()),
+            format: Format::Markdown,
+            max_rows: default_max_rows(),
+        })
+    }
+}
+
+/// Shortens a value to `MAX_CELL_CHARS` characters.
+fn clip(value: &str) -> String {
+    match value.char_indices().nth(MAX_CELL_CHARS) {
+        Some((cut, _)) => format!("{}...", &value[..cut]),
+        None => value.to_string(),
+    }
+}
+
+/// Formats rows as a Markdown table. `NULL`s are shown as `NULL`.
+fn to_markdown(rows: &Rows) -> String {
+    let cell = |value: &str| clip(value).replace('|', "\\|").replace(['\r', '\n'], " ");
+    let mut output = format!(
+        "| {} |\n|{}\n",
+        rows.columns
+            .iter()
+            .map(|column| cell(column))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        " --- |".repeat(rows.columns.len())
+    );
+    for row in &rows.rows {
+        let values: Vec<String> = row
+            .iter()
+            .map(|value| value.as_deref().map_or_else(|| "NULL".to_string(), cell))
+            .collect();
+        output.push_str(&format!("| {} |\n", values.join(" | ")));
+    }
+    output
+}
+
+/// Formats rows as CSV, quoting values where needed. `NULL`s are empty.
+fn to_csv(rows: &Rows) -> String {
+    let field = |value: &str| {
+        let value = clip(value);
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value
+        }
+    };
+    let mut output = rows
+        .columns
+        .iter()
+        .map(|column| field(column))
+        .collect::<Vec<_>>()
+        .join(",");
+    output.push('\n');
+    for row in &rows.rows {
+        let values: Vec<String> = row
+            .iter()
+            .map(|value| value.as_deref().map(field).unwrap_or_default())
+            .collect();
+        output.push_str(&values.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+/// Formats the result of a statement, noting if rows were left out.
+fn format_rows(rows: &Rows, format: Format) -> String {
+    if let Some(affected) = rows.affected {
+        return format!("{} rows affected.", affected);
+    }
+    if rows.columns.is_empty() {
+        return "The statement returned nothing.".to_string();
+    }
+    let mut output = match format {
+        Format::Markdown => to_markdown(rows),
+        Format::Csv => to_csv(rows),
+    };
+    if rows.rows.is_empty() {
+        output.push_str("\n(no rows)");
+    } else if rows.truncated {
+        output.push_str(&format!(
+            "\n(showing the first {} rows; refine the query or raise max_rows to see more)",
+            rows.rows.len()
+        ));
+    }
+    output.trim_end().to_string()
+}
+
+/// A tool for exploring and querying SQL databases.
+///
+/// Databases are configured by name. Read-only databases are opened so that
+/// the connection itself refuses writes, every statement is subject to a
+/// timeout, and results are cut off after a number of rows.
+pub struct DatabaseTool {
+    databases: BTreeMap<String, Box<dyn Database>>,
+}
+
+impl DatabaseTool {
+    /// Connects to the configured databases.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The databases, keyed by name.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if a database cannot be opened.
+    pub async fn new<'a>(
+        config: impl IntoIterator<Item = (&'a String, &'a DatabaseConfig)>,
+    ) -> Result<Self> {
+        let mut databases = BTreeMap::new();
+        for (name, database) in config {
+            let connection = connect(database)
+                .await
+                .map_err(|e| anyhow!("Failed to open database {}: {}", name, e))?;
+            databases.insert(name.clone(), connection);
+        }
+        Ok(Self { databases })
+    }
+
+    fn database(&self, name: Option<&str>) -> Result<&dyn Database> {
+        let names = || {
+            self.databases
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match name {
+            Some(name) => self
+                .databases
+                .get(name)
+                .map(|database| database.as_ref())
+                .ok_or_else(|| anyhow!("Unknown database {}; expected one of: {}", name, names())),
+            None if self.databases.len() == 1 => {
+                Ok(self.databases.values().next().unwrap().as_ref())
+            }
+            None => Err(anyhow!("Specify the database, one of: {}", names())),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for DatabaseTool {
+    /// Returns the name of the tool, "DatabaseTool".
+    fn name(&self) -> &str {
+        "DatabaseTool"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Explores and queries SQL databases ({}). Args: a JSON object with the `action`: \
+             `tables`, `describe` with a `table`, or `query` (default) with the `sql` to run; \
+             optionally the `database`, the `format` (`markdown` or `csv`) and `max_rows`.",
+            self.databases
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+
+    /// Lists tables, describes a table, or runs a statement.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either a SQL statement, or a JSON object with the
+    ///   `action`, the `database`, the `table` to describe or the `sql` to
+    ///   run, the `format` and `max_rows`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the rows as a Markdown or CSV table, or an error if
+    /// the statement fails or times out.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = DatabaseArgs::parse(args)?;
+        let database = self.database(args.database.as_deref())?;
+        let rows = match args.action {
+            Action::Tables => database.tables().await?,
+            Action::Describe => {
+                let table = args
+                    .table
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("The describe action needs a `table`"))?;
+                database.describe(table).await?
+            }
+            Action::Query => {
+                let sql = args
+                    .sql
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("The query action needs the `sql` to run"))?;
+                database
+                    .query(sql, args.max_rows.clamp(1, MAX_ROWS))
+                    .await?
+            }
+        };
+        Ok(format_rows(&rows, args.format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_read_only_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shop.db");
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL, note TEXT);
+                 INSERT INTO items (name, note) VALUES ('pen', 'blue, fine'), ('cup', NULL), ('a|b', 'x');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let config = HashMap::from([(
+            "shop".to_string(),
+            DatabaseConfig {
+                url: format!("sqlite://{}", path.display()),
+                read_only: true,
+                timeout_seconds: 1,
+            },
+        )]);
+        let tool = DatabaseTool::new(&config).await.unwrap();
+
+        let tables = tool.execute(r#"{"action": "tables"}"#).await.unwrap();
+        assert!(tables.contains("| items | table |"));
+        let schema = tool
+            .execute(r#"{"action": "describe", "table": "items"}"#)
+            .await
+            .unwrap();
+        assert!(schema.contains("| name | TEXT | NO | NULL | NO |"));
+
+        let rows = tool
+            .execute(r#"{"sql": "SELECT name, note FROM items ORDER BY id", "max_rows": 2}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            "| name | note |\n| --- | --- |\n| pen | blue, fine |\n| cup | NULL |\n\n\
+             (showing the first 2 rows; refine the query or raise max_rows to see more)"
+        );
+        let csv = tool
+            .execute(r#"{"sql": "SELECT name, note FROM items WHERE id = 1", "format": "csv"}"#)
+            .await
+            .unwrap();
+        assert_eq!(csv, "name,note\npen,\"blue, fine\"");
+
+        assert!(tool.execute("DELETE FROM items").await.is_err());
+        let vacuum = format!("VACUUM INTO '{}'", dir.path().join("copy.db").display());
+        assert!(tool.execute(&vacuum).await.is_err());
+        assert!(!dir.path().join("copy.db").exists());
+
+        let endless = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) \
+                       SELECT count(*) FROM n";
+        let error = tool.execute(endless).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+        assert!(tool.execute("SELECT count(*) FROM items").await.is_ok());
+    }
+}
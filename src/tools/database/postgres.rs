@@ -0,0 +1,157 @@
+use super::{Database, Rows};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::{pin_mut, StreamExt};
+use std::time::Duration;
+use tokio_postgres::{Client, Config, NoTls, SimpleQueryMessage};
+
+/// A Postgres database.
+///
+/// The connection sets `statement_timeout`, and for a read-only database
+/// `default_transaction_read_only`. Since a statement could change those
+/// settings, read-only queries must also be a single statement, which runs
+/// in a read-only transaction that is always rolled back.
+pub struct Postgres {
+    client: Client,
+    read_only: bool,
+    timeout: Duration,
+}
+
+impl Postgres {
+    /// Connects to the database at `url`, without TLS.
+    pub async fn connect(url: &str, read_only: bool, timeout: Duration) -> Result<Self> {
+        let mut config: Config = url.parse()?;
+        let mut options = format!("-c statement_timeout={}", timeout.as_millis());
+        if read_only {
+            options.push_str(" -c default_transaction_read_only=on");
+        }
+        config.options(options).connect_timeout(timeout);
+        let (client, connection) = config.connect(NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(error) = connection.await {
+                tracing::error!("Postgres connection error: {}", error);
+            }
+        });
+        Ok(Self {
+            client,
+            read_only,
+            timeout,
+        })
+    }
+
+    /// Runs `sql` with the simple query protocol, which returns every value
+    /// as text, keeping at most `max_rows` rows of the last result.
+    async fn simple_query(&self, sql: &str, max_rows: usize) -> Result<Rows> {
+        let stream = self.client.simple_query_raw(sql).await?;
+        pin_mut!(stream);
+        let mut result = Rows::default();
+        while let Some(message) = stream.next().await {
+            match message? {
+                SimpleQueryMessage::RowDescription(columns) => {
+                    result = Rows {
+                        columns: columns.iter().map(|c| c.name().to_string()).collect(),
+                        ..Rows::default()
+                    };
+                }
+                SimpleQueryMessage::Row(row) => {
+                    if result.rows.len() == max_rows {
+                        // Dropping the stream discards the remaining rows.
+                        result.truncated = true;
+                        break;
+                    }
+                    let values = (0..row.len())
+                        .map(|index| row.get(index).map(String::from))
+                        .collect();
+                    result.rows.push(values);
+                }
+                SimpleQueryMessage::CommandComplete(affected) if result.columns.is_empty() => {
+                    result.affected = Some(affected);
+                }
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl Database for Postgres {
+    async fn tables(&self) -> Result<Rows> {
+        self.simple_query(
+            "SELECT table_schema AS schema, table_name AS name, table_type AS type
+             FROM information_schema.tables
+             WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
+             ORDER BY 1, 2",
+            usize::MAX,
+        )
+        .await
+    }
+
+    async fn describe(&self, table: &str) -> Result<Rows> {
+        let (schema, name) = match table.split_once('.') {
+            Some((schema, name)) => (Some(schema), name),
+            None => (None, table),
+        };
+        let rows = self
+            .client
+            .query(
+                "SELECT c.column_name::text, c.data_type::text, c.is_nullable::text,
+                     c.column_default::text,
+                     CASE WHEN EXISTS (
+                         SELECT 1 FROM information_schema.table_constraints t
+                         JOIN information_schema.key_column_usage k
+                             USING (constraint_schema, constraint_name)
+                         WHERE t.constraint_type = 'PRIMARY KEY'
+                             AND k.table_schema = c.table_schema
+                             AND k.table_name = c.table_name
+                             AND k.column_name = c.column_name
+                     ) THEN 'YES' ELSE 'NO' END
+                 FROM information_schema.columns c
+                 WHERE c.table_name = $1 AND ($2::text IS NULL OR c.table_schema = $2)
+                 ORDER BY c.table_schema, c.ordinal_position",
+                &[&name, &schema],
+            )
+            .await?;
+        if rows.is_empty() {
+            return Err(anyhow!("No such table: {}", table));
+        }
+        Ok(Rows {
+            columns: ["column", "type", "nullable", "default", "primary_key"]
+                .map(String::from)
+                .to_vec(),
+            rows: rows
+                .iter()
+                .map(|row| (0..5).map(|index| row.get(index)).collect())
+                .collect(),
+            ..Rows::default()
+        })
+    }
+
+    async fn query(&self, sql: &str, max_rows: usize) -> Result<Rows> {
+        let run = async {
+            if !self.read_only {
+                return self.simple_query(sql, max_rows).await;
+            }
+            // Preparing rejects strings with more than one statement.
+            self.client.prepare(sql).await.map_err(|e| {
+                anyhow!(
+                    "Invalid read-only query, which must be a single statement: {}",
+                    e
+                )
+            })?;
+            self.client.batch_execute("BEGIN READ ONLY").await?;
+            let result = self.simple_query(sql, max_rows).await;
+            self.client.batch_execute("ROLLBACK").await?;
+            result
+        };
+        // The server enforces the timeout, but a lost connection would not.
+        tokio::time::timeout(self.timeout + Duration::from_secs(5), run)
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "The statement timed out after {} seconds",
+                    self.timeout.as_secs()
+                )
+            })?
+    }
+}
@@ -0,0 +1,162 @@
+use super::{Database, Rows};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rusqlite::{limits::Limit, types::ValueRef, Connection, InterruptHandle, OpenFlags, Params};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// A SQLite database.
+///
+/// Statements run on a blocking thread and are interrupted when they exceed
+/// the timeout.
+pub struct Sqlite {
+    connection: Arc<Mutex<Connection>>,
+    interrupt: Arc<InterruptHandle>,
+    timeout: Duration,
+}
+
+impl Sqlite {
+    /// Opens the database file at `path`.
+    ///
+    /// A read-only database is opened with `SQLITE_OPEN_READ_ONLY` and
+    /// `query_only`, and may not attach other databases, which also rules
+    /// out `VACUUM INTO`.
+    pub fn open(path: &str, read_only: bool, timeout: Duration) -> Result<Self> {
+        let flags = if read_only {
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        };
+        let connection = Connection::open_with_flags(path, flags)?;
+        connection.busy_timeout(timeout)?;
+        if read_only {
+            connection.pragma_update(None, "query_only", true)?;
+            connection.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 0);
+        }
+        Ok(Self {
+            interrupt: Arc::new(connection.get_interrupt_handle()),
+            connection: Arc::new(Mutex::new(connection)),
+            timeout,
+        })
+    }
+
+    /// Runs `f` with the connection on a blocking thread, interrupting it
+    /// if it runs longer than the timeout.
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let connection = connection
+                .lock()
+                .map_err(|_| anyhow!("The database connection is poisoned"))?;
+            f(&connection)
+        });
+        match tokio::time::timeout(self.timeout, task).await {
+            Ok(result) => result?,
+            Err(_) => {
+                self.interrupt.interrupt();
+                Err(anyhow!(
+                    "The statement timed out after {} seconds",
+                    self.timeout.as_secs_f64()
+                ))
+            }
+        }
+    }
+}
+
+/// Converts a value to text. Blobs are shown by their size.
+fn to_text(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(value) => Some(value.to_string()),
+        ValueRef::Real(value) => Some(value.to_string()),
+        ValueRef::Text(value) => Some(String::from_utf8_lossy(value).into_owned()),
+        ValueRef::Blob(value) => Some(format!("<{} bytes>", value.len())),
+    }
+}
+
+/// Runs a statement, keeping at most `max_rows` rows.
+fn query(connection: &Connection, sql: &str, params: impl Params, max_rows: usize) -> Result<Rows> {
+    let mut statement = connection.prepare(sql)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    if columns.is_empty() {
+        let affected = statement.execute(params)?;
+        return Ok(Rows {
+            affected: Some(affected as u64),
+            ..Rows::default()
+        });
+    }
+
+    let mut result = Rows {
+        columns,
+        ..Rows::default()
+    };
+    let mut rows = statement.query(params)?;
+    while let Some(row) = rows.next()? {
+        if result.rows.len() == max_rows {
+            result.truncated = true;
+            break;
+        }
+        let values = (0..result.columns.len())
+            .map(|index| Ok(to_text(row.get_ref(index)?)))
+            .collect::<Result<_>>()?;
+        result.rows.push(values);
+    }
+    Ok(result)
+}
+
+#[async_trait]
+impl Database for Sqlite {
+    async fn tables(&self) -> Result<Rows> {
+        self.run(|connection| {
+            query(
+                connection,
+                "SELECT name, type FROM sqlite_schema
+                 WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+                 ORDER BY name",
+                [],
+                usize::MAX,
+            )
+        })
+        .await
+    }
+
+    async fn describe(&self, table: &str) -> Result<Rows> {
+        let name = table.to_string();
+        let rows = self
+            .run(move |connection| {
+                query(
+                    connection,
+                    "SELECT name AS \"column\", type,
+                         CASE WHEN \"notnull\" THEN 'NO' ELSE 'YES' END AS nullable,
+                         dflt_value AS \"default\",
+                         CASE WHEN pk > 0 THEN 'YES' ELSE 'NO' END AS primary_key
+                     FROM pragma_table_info(?1)",
+                    [name],
+                    usize::MAX,
+                )
+            })
+            .await?;
+        if rows.rows.is_empty() {
+            return Err(anyhow!("No such table: {}", table));
+        }
+        Ok(rows)
+    }
+
+    async fn query(&self, sql: &str, max_rows: usize) -> Result<Rows> {
+        let sql = sql.to_string();
+        self.run(move |connection| query(connection, &sql, [], max_rows))
+            .await
+    }
+}
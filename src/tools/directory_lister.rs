@@ -1,4 +1,4 @@
-use crate::tools::Tool;
+use crate::tools::{workspace::Workspace, Tool};
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::fs;
@@ -6,8 +6,17 @@ use tokio::fs;
 /// A tool for listing the contents of a directory.
 ///
 /// This tool allows the agent to see what files and subdirectories exist in a
-/// given directory.
-pub struct DirectoryListerTool;
+/// given directory inside the workspace.
+pub struct DirectoryListerTool {
+    workspace: Workspace,
+}
+
+impl DirectoryListerTool {
+    /// Creates a new `DirectoryListerTool` confined to the given workspace.
+    pub fn new(workspace: Workspace) -> Self {
+        Self { workspace }
+    }
+}
 
 #[async_trait]
 impl Tool for DirectoryListerTool {
@@ -26,10 +35,10 @@ impl Tool for DirectoryListerTool {
     ///
     /// A `Result` with a string containing the names of the files and
     /// directories, separated by newlines, or an error if the directory
-    /// cannot be read.
+    /// cannot be read or lies outside the workspace.
     #[tracing::instrument(skip(self))]
     async fn execute(&self, args: &str) -> Result<String> {
-        let dir_path = args.trim();
+        let dir_path = self.workspace.resolve(args)?;
         let mut entries = fs::read_dir(dir_path).await?;
         let mut entry_names = Vec::new();
 
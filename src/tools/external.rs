@@ -0,0 +1,251 @@
+use crate::tools::{
+    jsonrpc::{RpcError, StdioClient},
+    registry::{RegisteredTool, ToolContext},
+    Tool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+/// How long a process gets to exit after its input is closed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// The options of `ExternalTool`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalToolConfig {
+    /// The program, looked up on the `PATH`.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Environment variables set for the program, in addition to those of
+    /// this process.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Replaces the name the process describes itself with.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// How long `describe` and each `execute` may take, in seconds.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+impl ExternalToolConfig {
+    fn spawn(&self, directory: &Path) -> Result<StdioClient> {
+        StdioClient::spawn(&self.command, &self.args, &self.env, directory)
+    }
+}
+
+/// What a process answers to `describe`.
+#[derive(Deserialize)]
+struct Description {
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// A tool implemented by another program, in any language, that speaks
+/// JSON-RPC 2.0 on its standard input and output, one message per line.
+///
+/// The program answers two methods:
+///
+/// - `describe`, without params, with `{"name": ..., "description": ...}`.
+/// - `execute`, with `{"args": ...}`, with the tool's output: a string, or
+///   any other JSON value, which the agent sees serialized. An error
+///   response fails the tool call with its message.
+///
+/// The program is started in the workspace when the tool is built and
+/// stays running between calls. A call that takes longer than the timeout
+/// kills it, as does the program exiting, and the next call starts it
+/// again. When the agent's run ends, the program's input is closed, and it
+/// is killed if it does not exit soon after.
+pub struct ExternalTool {
+    config: ExternalToolConfig,
+    directory: PathBuf,
+    name: String,
+    description: String,
+    client: Mutex<Option<StdioClient>>,
+}
+
+impl ExternalTool {
+    /// Starts the program and asks it to describe itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The program to run, and how long it may take.
+    /// * `directory` - The directory to run it in.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if the program does not start
+    /// or does not answer `describe`.
+    pub async fn start(config: ExternalToolConfig, directory: PathBuf) -> Result<Self> {
+        let mut client = config.spawn(&directory)?;
+        let described = call(&mut client, "describe", Value::Null, config.timeout_secs)
+            .await
+            .map_err(|e| anyhow!("{} did not describe itself: {}", config.command, e))?;
+        let described: Description = serde_json::from_value(described)
+            .map_err(|e| anyhow!("{} gave an invalid description: {}", config.command, e))?;
+        Ok(Self {
+            name: config.name.clone().unwrap_or(described.name),
+            description: described.description,
+            config,
+            directory,
+            client: Mutex::new(Some(client)),
+        })
+    }
+}
+
+/// Sends a request, giving up after `timeout_secs`.
+async fn call(
+    client: &mut StdioClient,
+    method: &str,
+    params: Value,
+    timeout_secs: u64,
+) -> Result<Value> {
+    tokio::time::timeout(
+        Duration::from_secs(timeout_secs),
+        client.request(method, params),
+    )
+    .await
+    .map_err(|_| anyhow!("No answer within {} seconds", timeout_secs))?
+}
+
+#[async_trait]
+impl Tool for ExternalTool {
+    /// Returns the name the program described itself with, unless the
+    /// configuration replaces it.
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    /// Sends the arguments to the program's `execute` method.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, passed on as a string.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the program's output, or an error if the program
+    /// fails the call, exits or runs past the timeout.
+    async fn execute(&self, args: &str) -> Result<String> {
+        let mut slot = self.client.lock().await;
+        if slot.is_none() {
+            *slot = Some(self.config.spawn(&self.directory)?);
+        }
+        let client = slot.as_mut().expect("the process was just started");
+        match call(
+            client,
+            "execute",
+            json!({ "args": args }),
+            self.config.timeout_secs,
+        )
+        .await
+        {
+            Ok(Value::String(output)) => Ok(output),
+            Ok(output) => Ok(output.to_string()),
+            Err(e) => {
+                // An error response leaves the program able to take the
+                // next call; anything else means it is gone or stuck.
+                if !e.is::<RpcError>() {
+                    *slot = None;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn shutdown(&self) {
+        if let Some(client) = self.client.lock().await.take() {
+            client.close(SHUTDOWN_GRACE).await;
+        }
+    }
+}
+
+#[async_trait]
+impl RegisteredTool for ExternalTool {
+    const NAME: &'static str = "ExternalTool";
+    type Options = ExternalToolConfig;
+
+    async fn build(
+        options: ExternalToolConfig,
+        _agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        let tool = ExternalTool::start(options, context.workspace.root().to_path_buf()).await?;
+        Ok(vec![Box::new(tool)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tool in shell that echoes its arguments, fails on "fail" and
+    /// hangs on "hang".
+    const ECHO_TOOL: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+  case "$line" in
+    *'"method":"describe"'*)
+      printf '{"jsonrpc":"2.0","method":"log","params":"starting"}\n'
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"name":"Echo","description":"Echoes."}}\n' "$id" ;;
+    *'"args":"fail"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"error":{"code":1,"message":"It failed."}}\n' "$id" ;;
+    *'"args":"hang"'*)
+      sleep 10 ;;
+    *)
+      args=$(printf '%s' "$line" | sed 's/.*"args":"\([^"]*\)".*/\1/')
+      printf '{"jsonrpc":"2.0","id":%s,"result":"echo: %s"}\n' "$id" "$args" ;;
+  esac
+done
+"#;
+
+    #[tokio::test]
+    async fn test_external_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let config: ExternalToolConfig = serde_json::from_value(json!({
+            "command": "sh",
+            "args": ["-c", ECHO_TOOL],
+            "timeout_secs": 1
+        }))
+        .unwrap();
+        let tool = ExternalTool::start(config, dir.path().to_path_buf())
+            .await
+            .unwrap();
+        assert_eq!(
+            (tool.name(), tool.description()),
+            ("Echo", "Echoes.".into())
+        );
+
+        assert_eq!(tool.execute("hi").await.unwrap(), "echo: hi");
+        assert_eq!(
+            tool.execute("fail").await.unwrap_err().to_string(),
+            "It failed."
+        );
+        assert_eq!(tool.execute("again").await.unwrap(), "echo: again");
+        assert_eq!(
+            tool.execute("hang").await.unwrap_err().to_string(),
+            "No answer within 1 seconds"
+        );
+        // The stuck process was killed, and a new one takes the next call.
+        assert_eq!(tool.execute("back").await.unwrap(), "echo: back");
+        tool.shutdown().await;
+    }
+}
@@ -1,12 +1,22 @@
-use crate::tools::Tool;
+use crate::tools::{workspace::Workspace, Tool};
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::fs;
 
 /// A tool for reading files.
 ///
-/// This tool allows the agent to read the content of a file at a given path.
-pub struct FileReaderTool;
+/// This tool allows the agent to read the content of a file at a given path
+/// inside the workspace.
+pub struct FileReaderTool {
+    workspace: Workspace,
+}
+
+impl FileReaderTool {
+    /// Creates a new `FileReaderTool` confined to the given workspace.
+    pub fn new(workspace: Workspace) -> Self {
+        Self { workspace }
+    }
+}
 
 #[async_trait]
 impl Tool for FileReaderTool {
@@ -24,10 +34,10 @@ impl Tool for FileReaderTool {
     /// # Returns
     ///
     /// A `Result` with the content of the file, or an error if the file
-    /// cannot be read.
+    /// cannot be read or lies outside the workspace.
     #[tracing::instrument(skip(self))]
     async fn execute(&self, args: &str) -> Result<String> {
-        let filepath = args.trim();
+        let filepath = self.workspace.resolve(args)?;
         let content = fs::read_to_string(filepath).await?;
         Ok(content)
     }
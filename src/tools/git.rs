@@ -0,0 +1,431 @@
+use crate::tools::{workspace::Workspace, Tool};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{process::Stdio, time::Duration};
+use tokio::process::Command;
+
+/// The maximum time a git command may run.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Output beyond this many characters is cut off.
+const MAX_OUTPUT_CHARS: usize = 30_000;
+
+/// Configuration that overrides the repository's own, so that files the
+/// agent can write, such as hooks and `.git/config`, cannot make git run
+/// arbitrary programs outside the sandbox.
+const SAFE_CONFIG: &[&str] = &[
+    "core.hooksPath=/dev/null",
+    "core.fsmonitor=false",
+    "core.pager=cat",
+    "diff.external=",
+];
+
+/// The `[git]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitConfig {
+    /// The author and committer name for commits. Defaults to git's own
+    /// configuration.
+    pub author_name: Option<String>,
+    /// The author and committer email for commits.
+    pub author_email: Option<String>,
+    /// Branches that may not be committed to directly.
+    pub protected_branches: Vec<String>,
+}
+
+/// The arguments accepted by `GitTool`, as a JSON object tagged by
+/// `action`. A bare string is taken as an action with no arguments, such
+/// as `status`.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase", deny_unknown_fields)]
+enum GitArgs {
+    /// Show the branch and the changed files.
+    Status,
+    /// Show changes in the working tree, or the index with `staged`, or
+    /// against `base`.
+    Diff {
+        #[serde(default)]
+        staged: bool,
+        #[serde(default)]
+        base: Option<String>,
+        #[serde(default)]
+        paths: Vec<String>,
+        /// Show only a summary of changed files.
+        #[serde(default)]
+        stat: bool,
+    },
+    /// List commits, newest first.
+    Log {
+        #[serde(default)]
+        rev: Option<String>,
+        #[serde(default)]
+        paths: Vec<String>,
+        #[serde(default = "default_max_count")]
+        max_count: usize,
+    },
+    /// Show a commit with its changes.
+    Show {
+        #[serde(default = "default_rev")]
+        rev: String,
+        #[serde(default)]
+        stat: bool,
+    },
+    /// Show who last changed each line of a file.
+    Blame {
+        path: String,
+        #[serde(default)]
+        start_line: Option<usize>,
+        #[serde(default)]
+        end_line: Option<usize>,
+    },
+    /// List branches, or create the branch `name`.
+    Branch {
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        start_point: Option<String>,
+        /// Switch to the new branch.
+        #[serde(default)]
+        switch: bool,
+    },
+    /// Switch to an existing branch.
+    Switch { name: String },
+    /// Stage files for the next commit.
+    Add { paths: Vec<String> },
+    /// Commit the staged changes, or with `all` every change to tracked
+    /// files.
+    Commit {
+        message: String,
+        #[serde(default)]
+        all: bool,
+    },
+}
+
+fn default_max_count() -> usize {
+    20
+}
+
+fn default_rev() -> String {
+    "HEAD".to_string()
+}
+
+impl GitArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        let parsed = if args.starts_with('{') {
+            serde_json::from_str(args)
+        } else {
+            serde_json::from_value(serde_json::json!({ "action": args }))
+        };
+        parsed.map_err(|e| anyhow!("Invalid arguments for GitTool: {}", e))
+    }
+}
+
+/// Rejects revisions that git would parse as options.
+fn check_rev(rev: &str) -> Result<&str> {
+    if rev.is_empty() || rev.starts_with('-') || rev.chars().any(char::is_whitespace) {
+        return Err(anyhow!("Invalid revision: {:?}", rev));
+    }
+    Ok(rev)
+}
+
+/// Appends arguments to a command.
+fn push(command: &mut Vec<String>, args: &[&str]) {
+    command.extend(args.iter().map(|arg| arg.to_string()));
+}
+
+/// A tool for inspecting and committing to the workspace's git repository.
+///
+/// Each action runs a fixed git command, so the model cannot push, rewrite
+/// history, reset or delete branches. Hooks, `core.fsmonitor`, external
+/// diff programs and text conversion filters are disabled, since they run
+/// programs on the host rather than in the sandbox.
+pub struct GitTool {
+    workspace: Workspace,
+    config: GitConfig,
+}
+
+impl GitTool {
+    /// Creates a new `GitTool`.
+    ///
+    /// # Arguments
+    ///
+    /// * `workspace` - The workspace, which must be inside a git repository.
+    /// * `config` - The author and guardrails for commits.
+    pub fn new(workspace: Workspace, config: GitConfig) -> Self {
+        Self { workspace, config }
+    }
+
+    /// Resolves paths inside the workspace, for use after `--`.
+    fn paths(&self, paths: &[String]) -> Result<Vec<String>> {
+        paths
+            .iter()
+            .map(|path| Ok(self.workspace.resolve(path)?.to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    /// Runs git in the workspace.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the output, or an error with git's message if it
+    /// fails or times out.
+    async fn git(&self, args: &[String]) -> Result<String> {
+        let mut command = Command::new("git");
+        command
+            .arg("--no-pager")
+            .arg("-C")
+            .arg(self.workspace.root());
+        for config in SAFE_CONFIG {
+            command.arg("-c").arg(config);
+        }
+        if let Some(name) = &self.config.author_name {
+            command.arg("-c").arg(format!("user.name={}", name));
+        }
+        if let Some(email) = &self.config.author_email {
+            command.arg("-c").arg(format!("user.email={}", email));
+        }
+        command
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .env("GIT_OPTIONAL_LOCKS", "0")
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let output = tokio::time::timeout(TIMEOUT, command.output())
+            .await
+            .map_err(|_| anyhow!("git {} timed out", args[0]))??;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("git {} failed: {}", args[0], stderr.trim()));
+        }
+        let mut stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if let Some((cut, _)) = stdout.char_indices().nth(MAX_OUTPUT_CHARS) {
+            let omitted = stdout[cut..].chars().count();
+            stdout.truncate(cut);
+            stdout.push_str(&format!("\n... [{} more characters]", omitted));
+        }
+        Ok(stdout)
+    }
+
+    /// Builds the git arguments for an action.
+    async fn command(&self, args: GitArgs) -> Result<Vec<String>> {
+        let mut command: Vec<String> = Vec::new();
+        match args {
+            GitArgs::Status => push(&mut command, &["status", "--short", "--branch"]),
+            GitArgs::Diff {
+                staged,
+                base,
+                paths,
+                stat,
+            } => {
+                push(&mut command, &["diff", "--no-ext-diff", "--no-textconv"]);
+                if staged {
+                    push(&mut command, &["--cached"]);
+                }
+                if stat {
+                    push(&mut command, &["--stat"]);
+                }
+                if let Some(base) = &base {
+                    push(&mut command, &[check_rev(base)?]);
+                }
+                push(&mut command, &["--"]);
+                command.extend(self.paths(&paths)?);
+            }
+            GitArgs::Log {
+                rev,
+                paths,
+                max_count,
+            } => {
+                push(
+                    &mut command,
+                    &[
+                        "log",
+                        &format!("--max-count={}", max_count.clamp(1, 200)),
+                        "--date=short",
+                        "--format=%h %ad %an  %s",
+                    ],
+                );
+                if let Some(rev) = &rev {
+                    push(&mut command, &[check_rev(rev)?]);
+                }
+                push(&mut command, &["--"]);
+                command.extend(self.paths(&paths)?);
+            }
+            GitArgs::Show { rev, stat } => {
+                push(
+                    &mut command,
+                    &["show", "--no-ext-diff", "--no-textconv", "--format=fuller"],
+                );
+                if stat {
+                    push(&mut command, &["--stat"]);
+                }
+                push(&mut command, &[check_rev(&rev)?, "--"]);
+            }
+            GitArgs::Blame {
+                path,
+                start_line,
+                end_line,
+            } => {
+                push(&mut command, &["blame", "--no-textconv", "--date=short"]);
+                if start_line.is_some() || end_line.is_some() {
+                    let start = start_line.unwrap_or(1).max(1);
+                    let end = end_line.map(|end| end.to_string()).unwrap_or_default();
+                    push(&mut command, &["-L", &format!("{},{}", start, end)]);
+                }
+                push(&mut command, &["--"]);
+                command.extend(self.paths(&[path])?);
+            }
+            GitArgs::Branch {
+                name: None,
+                start_point: _,
+                switch: _,
+            } => push(&mut command, &["branch", "--list", "-vv"]),
+            GitArgs::Branch {
+                name: Some(name),
+                start_point,
+                switch,
+            } => {
+                // `git check-ref-format` rejects names like `-f` or `a..b`.
+                let args = ["check-ref-format", "--branch", &name].map(String::from);
+                self.git(&args).await?;
+                if switch {
+                    push(&mut command, &["switch", "--create", &name]);
+                } else {
+                    push(&mut command, &["branch", &name]);
+                }
+                if let Some(start_point) = &start_point {
+                    push(&mut command, &[check_rev(start_point)?]);
+                }
+            }
+            GitArgs::Switch { name } => push(&mut command, &["switch", check_rev(&name)?]),
+            GitArgs::Add { paths } => {
+                if paths.is_empty() {
+                    return Err(anyhow!("Specify the paths to add"));
+                }
+                push(&mut command, &["add", "--"]);
+                command.extend(self.paths(&paths)?);
+            }
+            GitArgs::Commit { message, all } => {
+                let branch = self
+                    .git(&["branch", "--show-current"].map(String::from))
+                    .await?;
+                let branch = branch.trim();
+                if self.config.protected_branches.iter().any(|b| b == branch) {
+                    return Err(anyhow!(
+                        "{} is protected; create a branch for your changes first",
+                        branch
+                    ));
+                }
+                push(
+                    &mut command,
+                    &["commit", "--no-verify", "--message", &message],
+                );
+                if all {
+                    push(&mut command, &["--all"]);
+                }
+            }
+        }
+        Ok(command)
+    }
+}
+
+#[async_trait]
+impl Tool for GitTool {
+    /// Returns the name of the tool, "GitTool".
+    fn name(&self) -> &str {
+        "GitTool"
+    }
+
+    fn description(&self) -> String {
+        "Works with the workspace's git repository. Args: a JSON object with the `action`: \
+         `status`; `diff` with optional `staged`, `base`, `paths` and `stat`; `log` with \
+         optional `rev`, `paths` and `max_count`; `show` with optional `rev` and `stat`; \
+         `blame` with a `path` and optional `start_line` and `end_line`; `branch` to list \
+         branches, or with a `name`, optional `start_point` and `switch` to create one; \
+         `switch` with a `name`; `add` with `paths`; `commit` with a `message` and optional \
+         `all`."
+            .to_string()
+    }
+
+    /// Runs a git action in the workspace.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - A JSON object with the `action` and its arguments, or the
+    ///   name of an action that takes none, such as `status`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with git's output, or an error if the arguments are
+    /// invalid or git fails.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = GitArgs::parse(args)?;
+        let command = self.command(args).await?;
+        let output = self.git(&command).await?;
+        if output.trim().is_empty() {
+            return Ok(format!("git {} succeeded with no output.", command[0]));
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_git_workflow() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let tool = GitTool::new(
+            workspace.clone(),
+            GitConfig {
+                author_name: Some("Agent".to_string()),
+                author_email: Some("agent@example.com".to_string()),
+                protected_branches: vec!["main".to_string()],
+            },
+        );
+        let init = ["init", "--initial-branch=main"].map(String::from);
+        tool.git(&init).await.unwrap();
+        std::fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+
+        assert!(tool.execute("status").await.unwrap().contains("?? a.txt"));
+        tool.execute(r#"{"action": "add", "paths": ["a.txt"]}"#)
+            .await
+            .unwrap();
+        let error = tool
+            .execute(r#"{"action": "commit", "message": "Add a"}"#)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("main is protected"));
+
+        tool.execute(r#"{"action": "branch", "name": "feature", "switch": true}"#)
+            .await
+            .unwrap();
+        tool.execute(r#"{"action": "commit", "message": "Add a"}"#)
+            .await
+            .unwrap();
+        let log = tool.execute("log").await.unwrap();
+        assert!(log.contains("Agent  Add a"));
+
+        std::fs::write(dir.path().join("a.txt"), "two\n").unwrap();
+        let diff = tool
+            .execute(r#"{"action": "diff", "paths": ["a.txt"]}"#)
+            .await
+            .unwrap();
+        assert!(diff.contains("-one\n+two"));
+
+        let invalid = [
+            r#"{"action": "branch", "name": "--force"}"#,
+            r#"{"action": "diff", "base": "--output=/tmp/x"}"#,
+            r#"{"action": "add", "paths": ["../outside"]}"#,
+            r#"{"action": "push"}"#,
+        ];
+        for args in invalid {
+            assert!(tool.execute(args).await.is_err(), "{}", args);
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use anyhow::{anyhow, Result};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE},
+    redirect, Method, RequestBuilder, StatusCode, Url,
+};
+use serde::Deserialize;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+/// The user agent sent with every request.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The `[http]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// The maximum time for a whole request, including reading the body.
+    pub timeout_seconds: u64,
+    /// The maximum time to establish a connection.
+    pub connect_timeout_seconds: u64,
+    /// The maximum size of a response body.
+    pub max_body_bytes: usize,
+    /// The maximum number of redirects to follow.
+    pub max_redirects: usize,
+    /// Content types that may be read, matched as prefixes of the
+    /// `Content-Type` header, e.g. `text/` or `application/json`.
+    pub allowed_content_types: Vec<String>,
+    /// If not empty, only these hosts may be contacted. Entries are domain
+    /// names, which also match their subdomains, IP addresses, or CIDR
+    /// ranges like `10.1.0.0/16`. Addresses listed here may be contacted
+    /// even if they are private.
+    pub allowed_hosts: Vec<String>,
+    /// Hosts that may never be contacted, in the same format.
+    pub denied_hosts: Vec<String>,
+    /// Allow loopback, private, link-local and other non-public addresses,
+    /// such as `127.0.0.1` or the cloud metadata service at
+    /// `169.254.169.254`.
+    pub allow_private_networks: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 30,
+            connect_timeout_seconds: 10,
+            max_body_bytes: 5 * 1024 * 1024,
+            max_redirects: 5,
+            allowed_content_types: [
+                "text/",
+                "application/json",
+                "application/xml",
+                "application/xhtml+xml",
+                "application/rss+xml",
+                "application/atom+xml",
+                "application/ld+json",
+                "application/javascript",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allow_private_networks: false,
+        }
+    }
+}
+
+/// An entry in a host allow or deny list.
+#[derive(Debug, Clone)]
+enum HostPattern {
+    /// A domain and its subdomains.
+    Domain(String),
+    /// An address range.
+    Network(IpAddr, u8),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+        let (address, prefix) = match pattern.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (pattern.as_str(), None),
+        };
+        let address = address.trim_start_matches('[').trim_end_matches(']');
+        match (address.parse::<IpAddr>(), prefix) {
+            (Ok(ip), prefix) => {
+                let max = if ip.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .parse()
+                        .ok()
+                        .filter(|&p| p <= max)
+                        .ok_or_else(|| anyhow!("Invalid network prefix in {:?}", pattern))?,
+                    None => max,
+                };
+                Ok(Self::Network(ip, prefix))
+            }
+            (Err(_), None) if !pattern.is_empty() => {
+                Ok(Self::Domain(pattern.trim_start_matches("*.").to_string()))
+            }
+            _ => Err(anyhow!("Invalid host pattern {:?}", pattern)),
+        }
+    }
+
+    fn matches_domain(&self, host: &str) -> bool {
+        match self {
+            Self::Domain(domain) => {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            }
+            Self::Network(..) => false,
+        }
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self, ip) {
+            (Self::Network(IpAddr::V4(network), prefix), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(ip) & mask
+            }
+            (Self::Network(IpAddr::V6(network), prefix), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns whether an address is not on the public internet: loopback,
+/// private, link-local, shared, multicast, reserved and similar ranges,
+/// including IPv4 addresses embedded in IPv6 ones.
+pub fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_private_v4(v4);
+            }
+            let segments = ip.segments();
+            // NAT64 addresses embed an IPv4 address in their last 32 bits.
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_private_v4(Ipv4Addr::new(a, b, c, d));
+            }
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+        }
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        || a >= 240
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b))
+        || (a == 192 && b == 0 && ip.octets()[2] == 0)
+}
+
+/// Decides which hosts and addresses may be contacted.
+#[derive(Debug)]
+struct HostPolicy {
+    allowed: Vec<HostPattern>,
+    denied: Vec<HostPattern>,
+    allow_private: bool,
+}
+
+impl HostPolicy {
+    fn new(config: &HttpConfig) -> Result<Self> {
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| HostPattern::parse(pattern))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            allowed: parse(&config.allowed_hosts)?,
+            denied: parse(&config.denied_hosts)?,
+            allow_private: config.allow_private_networks,
+        })
+    }
+
+    /// Checks the scheme and host of a URL before it is requested.
+    fn check_url(&self, url: &Url) -> Result<()> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("Only http and https URLs are allowed: {}", url));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("URL has no host: {}", url))?;
+        match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(ip) => self.check_ip(ip, true),
+            Err(_) => {
+                let domain = host.trim_end_matches('.').to_ascii_lowercase();
+                if self.denied.iter().any(|p| p.matches_domain(&domain)) {
+                    return Err(anyhow!("Access to {} is denied", domain));
+                }
+                if !self.allowed.is_empty()
+                    && !self.allowed.iter().any(|p| p.matches_domain(&domain))
+                {
+                    return Err(anyhow!("{} is not in the list of allowed hosts", domain));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks an address, either written in a URL (`literal`) or resolved
+    /// from an allowed domain name.
+    fn check_ip(&self, ip: IpAddr, literal: bool) -> Result<()> {
+        if self.denied.iter().any(|p| p.contains(ip)) {
+            return Err(anyhow!("Access to {} is denied", ip));
+        }
+        let listed = self.allowed.iter().any(|p| p.contains(ip));
+        if literal && !self.allowed.is_empty() && !listed {
+            return Err(anyhow!("{} is not in the list of allowed hosts", ip));
+        }
+        if is_private(ip) && !self.allow_private && !listed {
+            return Err(anyhow!("Access to the private address {} is denied", ip));
+        }
+        Ok(())
+    }
+}
+
+/// Resolves host names and drops addresses the policy does not allow, so
+/// that a public name pointing at a private address cannot be used to reach
+/// internal services.
+struct CheckedResolver {
+    policy: Arc<HostPolicy>,
+}
+
+impl Resolve for CheckedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addresses: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let mut blocked = None;
+            let allowed: Vec<SocketAddr> = addresses
+                .into_iter()
+                .filter(|address| match policy.check_ip(address.ip(), false) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        blocked = Some(error);
+                        false
+                    }
+                })
+                .collect();
+            if allowed.is_empty() {
+                let error = blocked.unwrap_or_else(|| anyhow!("{} did not resolve", host));
+                return Err(format!("{}: {}", host, error).into());
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A response read in full by `HttpClient`.
+#[derive(Debug)]
+pub struct HttpResponse {
+    /// The final URL, after redirects.
+    pub url: Url,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub content_type: Option<String>,
+    /// The body, decoded as UTF-8 with invalid sequences replaced.
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Returns an error if the status is a client or server error.
+    pub fn error_for_status(self) -> Result<Self> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(anyhow!("{} returned {}", self.url, self.status));
+        }
+        Ok(self)
+    }
+}
+
+/// The HTTP client shared by the tools that access the web.
+///
+/// It applies the limits from the `[http]` configuration to every request:
+/// timeouts, a maximum body size and number of redirects, the content types
+/// that may be read, and which hosts may be contacted. Private addresses
+/// are refused by default, both when written in a URL and when a host name
+/// resolves to them, including after redirects.
+///
+/// Cloning the client is cheap and shares its connection pool.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    policy: Arc<HostPolicy>,
+    max_body_bytes: usize,
+    allowed_content_types: Arc<Vec<String>>,
+}
+
+impl HttpClient {
+    /// Creates a new `HttpClient` from the configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the client, or an error if a host pattern is invalid.
+    pub fn new(config: &HttpConfig) -> Result<Self> {
+        let policy = Arc::new(HostPolicy::new(config)?);
+        let max_redirects = config.max_redirects;
+        let redirect_policy = policy.clone();
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .connect_timeout(Duration::from_secs(config.connect_timeout_seconds))
+            // A proxy would resolve names itself, bypassing the checks.
+            .no_proxy()
+            .dns_resolver(Arc::new(CheckedResolver {
+                policy: policy.clone(),
+            }))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() > max_redirects {
+                    return attempt.error(format!("More than {} redirects", max_redirects));
+                }
+                match redirect_policy.check_url(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(error) => attempt.error(error.to_string()),
+                }
+            }))
+            .build()?;
+
+        Ok(Self {
+            client,
+            policy,
+            max_body_bytes: config.max_body_bytes,
+            allowed_content_types: Arc::new(config.allowed_content_types.clone()),
+        })
+    }
+
+    /// Starts building a request, after checking that its URL is allowed.
+    pub fn request(&self, method: Method, url: Url) -> Result<RequestBuilder> {
+        self.policy.check_url(&url)?;
+        Ok(self.client.request(method, url))
+    }
+
+    /// Fetches a URL with a `GET` request.
+    pub async fn get(&self, url: Url) -> Result<HttpResponse> {
+        self.send(self.request(Method::GET, url)?).await
+    }
+
+    /// Sends a request and reads the response body.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the response, whatever its status, or an error if
+    /// the request fails, the content type is not allowed, or the body is
+    /// larger than the limit.
+    pub async fn send(&self, request: RequestBuilder) -> Result<HttpResponse> {
+        let mut response = request.send().await?;
+        let url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_ascii_lowercase());
+
+        if let Some(content_type) = &content_type {
+            let allowed = self
+                .allowed_content_types
+                .iter()
+                .any(|allowed| content_type.starts_with(&allowed.to_ascii_lowercase()));
+            if !allowed {
+                return Err(anyhow!(
+                    "{} has unsupported content type {}",
+                    url,
+                    content_type
+                ));
+            }
+        }
+
+        let too_large = || {
+            anyhow!(
+                "The response from {} is larger than {} bytes",
+                url,
+                self.max_body_bytes
+            )
+        };
+        let length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+        if length.is_some_and(|length| length > self.max_body_bytes) {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > self.max_body_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(HttpResponse {
+            url: url.clone(),
+            status: response.status(),
+            headers: response.headers().clone(),
+            content_type,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::test_server::{ok, redirect, serve};
+
+    #[test]
+    fn test_host_policy() {
+        let config = HttpConfig {
+            denied_hosts: vec!["internal.example.com".to_string()],
+            ..HttpConfig::default()
+        };
+        let policy = HostPolicy::new(&config).unwrap();
+        let check = |url: &str| policy.check_url(&Url::parse(url).unwrap());
+
+        assert!(check("https://example.com/").is_ok());
+        assert!(check("https://api.internal.example.com/").is_err());
+        assert!(check("http://169.254.169.254/latest/meta-data/").is_err());
+        assert!(check("http://[::ffff:127.0.0.1]/").is_err());
+        assert!(check("http://[fd00::1]/").is_err());
+        assert!(check("file:///etc/passwd").is_err());
+        assert!(policy.check_ip("10.0.0.1".parse().unwrap(), false).is_err());
+        assert!(policy
+            .check_ip("93.184.216.34".parse().unwrap(), false)
+            .is_ok());
+
+        let config = HttpConfig {
+            allowed_hosts: vec!["docs.rs".to_string(), "10.1.0.0/16".to_string()],
+            ..HttpConfig::default()
+        };
+        let policy = HostPolicy::new(&config).unwrap();
+        let check = |url: &str| policy.check_url(&Url::parse(url).unwrap());
+
+        assert!(check("https://docs.rs/serde").is_ok());
+        assert!(check("https://example.com/").is_err());
+        assert!(check("http://10.1.2.3/").is_ok());
+        assert!(check("http://10.2.0.1/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_limits() {
+        let (base, requests) = serve(vec![
+            ok("/small", "text/plain", "hello"),
+            ok("/large", "text/plain", &"x".repeat(100)),
+            ok("/binary", "application/octet-stream", "\0"),
+            redirect("/metadata", "http://169.254.169.254/latest/meta-data/"),
+        ])
+        .await;
+        let config = HttpConfig {
+            max_body_bytes: 50,
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..HttpConfig::default()
+        };
+        let client = HttpClient::new(&config).unwrap();
+        let get = |path: &str| client.get(base.join(path).unwrap());
+
+        assert_eq!(get("/small").await.unwrap().body, "hello");
+        assert!(get("/large").await.is_err());
+        assert!(get("/binary").await.is_err());
+        assert!(get("/metadata").await.is_err());
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["/small", "/large", "/binary", "/metadata"]
+        );
+
+        let client = HttpClient::new(&HttpConfig::default()).unwrap();
+        assert!(client.get(base.join("/small").unwrap()).await.is_err());
+    }
+}
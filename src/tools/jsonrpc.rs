@@ -0,0 +1,225 @@
+//! JSON-RPC 2.0 over the standard input and output of a child process, one
+//! message per line.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fmt, path::Path, process::Stdio};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    time::{timeout, Duration},
+};
+use tracing::debug;
+
+/// A request, or a notification when it has no `id`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+/// The response to a request, with either a `result` or an `error`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+/// The error of a response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// The error code for a method the receiver does not have.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+impl Request {
+    pub fn new(id: Option<u64>, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: id.map(Value::from),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+impl Response {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, code: i64, message: &str) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(RpcError {
+                code,
+                message: message.to_string(),
+                data: None,
+            }),
+        }
+    }
+
+    /// Returns the result, or the error as an `RpcError`.
+    pub fn into_result(self) -> Result<Value> {
+        match (self.result, self.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
+        }
+    }
+}
+
+/// A child process that answers JSON-RPC requests on its standard output.
+///
+/// What the process writes to its standard error is logged at debug level.
+/// Notifications from the process are skipped, and its requests are
+/// answered with an error, except for `ping`.
+pub struct StdioClient {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl StdioClient {
+    /// Starts a process.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The executable, looked up on the `PATH`.
+    /// * `args` - The arguments to pass it.
+    /// * `env` - Environment variables to set, in addition to those of this
+    ///   process.
+    /// * `directory` - The directory to run it in.
+    pub fn spawn(
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        directory: &Path,
+    ) -> Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .current_dir(directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start {}: {}", command, e))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let command = command.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!("{}: {}", command, line);
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+        })
+    }
+
+    /// Sends a request and waits for its response.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the response's result, or an error if the process
+    /// exited or wrote something that is not JSON-RPC. An error response
+    /// becomes an `RpcError`.
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.write(&Request::new(Some(id), method, params)).await?;
+        loop {
+            let line = self
+                .stdout
+                .next_line()
+                .await?
+                .ok_or_else(|| anyhow!("The process exited"))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Value = serde_json::from_str(&line)
+                .map_err(|e| anyhow!("The process wrote invalid JSON: {}", e))?;
+            // Requests and notifications from the process have a `method`.
+            if let Ok(request) = serde_json::from_value::<Request>(message.clone()) {
+                if let Some(id) = request.id {
+                    let response = match request.method.as_str() {
+                        "ping" => Response::result(id, serde_json::json!({})),
+                        method => Response::error(
+                            id,
+                            METHOD_NOT_FOUND,
+                            &format!("Unknown method {}", method),
+                        ),
+                    };
+                    self.write(&response).await?;
+                }
+                continue;
+            }
+            if message.get("id") != Some(&Value::from(id)) {
+                debug!("Skipped a message from the process: {}", line);
+                continue;
+            }
+            let response: Response = serde_json::from_value(message)
+                .map_err(|e| anyhow!("The process wrote an invalid response: {}", e))?;
+            return response.into_result();
+        }
+    }
+
+    /// Sends a notification, which has no response.
+    pub async fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.write(&Request::new(None, method, params)).await
+    }
+
+    async fn write(&mut self, message: &impl Serialize) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Closes the process's standard input, which asks it to exit, and
+    /// kills it if it has not exited within `grace`.
+    pub async fn close(mut self, grace: Duration) {
+        drop(self.stdin);
+        if timeout(grace, self.child.wait()).await.is_err() {
+            let _ = self.child.kill().await;
+        }
+    }
+}
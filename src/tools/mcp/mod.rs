@@ -0,0 +1,659 @@
+//! A client for the Model Context Protocol, which turns the tools and
+//! resources of MCP servers into tools.
+
+mod transport;
+
+use crate::tools::{
+    http::HttpClient,
+    jsonrpc::{RpcError, StdioClient},
+    secrets::Secrets,
+    Tool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+use transport::{HttpTransport, Transport};
+
+/// The version of the protocol the client asks for.
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// How many resources the description of a server's resource tool lists.
+const MAX_LISTED_RESOURCES: usize = 50;
+
+/// An `[mcp_servers.<name>]` section of the configuration file: an MCP
+/// server started as a process, with `command`, or reached over HTTP, with
+/// `url`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct McpServerConfig {
+    /// The program of a server that speaks on its standard input and
+    /// output, looked up on the `PATH`.
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Environment variables set for the program, in addition to those of
+    /// this process.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The endpoint of a server that speaks the Streamable HTTP transport.
+    /// It goes through `[http]`, like the other tools that access the web.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The name of the secret every HTTP request authenticates with.
+    #[serde(default)]
+    pub auth: Option<String>,
+    /// How long each request may take, in seconds.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+/// A server's tool, as `tools/list` describes it.
+#[derive(Deserialize)]
+struct ToolInfo {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(rename = "inputSchema", default)]
+    input_schema: Value,
+}
+
+/// A server's resource, as `resources/list` describes it.
+#[derive(Deserialize)]
+struct ResourceInfo {
+    uri: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// A connection to an MCP server, shared by the tools made from it.
+///
+/// The connection is opened and initialized when the client is created. A
+/// request that times out, or fails for any reason other than an error
+/// response, drops the connection, and the next request opens a new one.
+pub struct McpClient {
+    name: String,
+    config: McpServerConfig,
+    directory: PathBuf,
+    http: HttpClient,
+    secrets: Secrets,
+    /// What the server said it can do when it was first initialized.
+    capabilities: Value,
+    connection: Mutex<Option<Transport>>,
+}
+
+impl McpClient {
+    /// Connects to a server and initializes the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the server in the configuration file.
+    /// * `config` - How to reach the server.
+    /// * `directory` - The directory to run a server process in.
+    /// * `http` - The client to reach a server over HTTP with.
+    /// * `secrets` - The secrets, which must include the server's `auth`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the client, or an error if the configuration is
+    /// invalid or the server does not start or initialize.
+    pub async fn connect(
+        name: &str,
+        config: McpServerConfig,
+        directory: PathBuf,
+        http: HttpClient,
+        secrets: Secrets,
+    ) -> Result<Self> {
+        if config.command.is_some() == config.url.is_some() {
+            return Err(anyhow!(
+                "MCP server {} needs either a command or a url",
+                name
+            ));
+        }
+        if let Some(auth) = &config.auth {
+            if !secrets.contains(auth) {
+                return Err(anyhow!("Unknown secret `{}`", auth));
+            }
+        }
+        let mut client = Self {
+            name: name.to_string(),
+            config,
+            directory,
+            http,
+            secrets,
+            capabilities: Value::Null,
+            connection: Mutex::new(None),
+        };
+        let (transport, capabilities) = client.open().await?;
+        client.capabilities = capabilities;
+        *client.connection.get_mut() = Some(transport);
+        Ok(client)
+    }
+
+    /// Opens a connection and initializes it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the connection and the server's capabilities.
+    async fn open(&self) -> Result<(Transport, Value)> {
+        let mut transport = match (&self.config.command, &self.config.url) {
+            (Some(command), _) => Transport::Stdio(StdioClient::spawn(
+                command,
+                &self.config.args,
+                &self.config.env,
+                &self.directory,
+            )?),
+            (None, Some(url)) => {
+                let url = Url::parse(url)
+                    .map_err(|e| anyhow!("Invalid url {} for {}: {}", url, self.name, e))?;
+                Transport::Http(HttpTransport::new(
+                    self.http.clone(),
+                    url,
+                    self.config.auth.clone(),
+                    self.secrets.clone(),
+                ))
+            }
+            (None, None) => unreachable!("checked by connect"),
+        };
+        let initialized = self
+            .timed(transport.request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            ))
+            .await
+            .map_err(|e| anyhow!("MCP server {} did not initialize: {}", self.name, e))?;
+        transport
+            .notify("notifications/initialized", Value::Null)
+            .await?;
+        let capabilities = initialized.get("capabilities").cloned();
+        Ok((transport, capabilities.unwrap_or(Value::Null)))
+    }
+
+    /// Waits for a request, giving up after the server's timeout.
+    async fn timed(
+        &self,
+        request: impl std::future::Future<Output = Result<Value>>,
+    ) -> Result<Value> {
+        let timeout_secs = self.config.timeout_secs;
+        tokio::time::timeout(Duration::from_secs(timeout_secs), request)
+            .await
+            .map_err(|_| anyhow!("No answer within {} seconds", timeout_secs))?
+    }
+
+    /// Sends a request, reconnecting first if the connection was dropped.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the response's result, or an error if the server
+    /// cannot be reached, does not answer in time or answers with an error.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let mut slot = self.connection.lock().await;
+        if slot.is_none() {
+            *slot = Some(self.open().await?.0);
+        }
+        let transport = slot.as_mut().expect("the connection was just opened");
+        let result = self.timed(transport.request(method, params)).await;
+        if let Err(e) = &result {
+            // An error response leaves the session usable; anything else
+            // means the server is gone or stuck.
+            if !e.is::<RpcError>() {
+                if let Some(transport) = slot.take() {
+                    transport.close().await;
+                }
+            }
+        }
+        result
+    }
+
+    /// Sends a `list` request for every page of its items.
+    async fn list(&self, method: &str, key: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => Value::Null,
+            };
+            let page = self.request(method, params).await?;
+            if let Some(page_items) = page.get(key).and_then(Value::as_array) {
+                items.extend(page_items.iter().cloned());
+            }
+            match page.get("nextCursor").and_then(Value::as_str) {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    /// Closes the connection, if it is open.
+    pub async fn close(&self) {
+        if let Some(transport) = self.connection.lock().await.take() {
+            transport.close().await;
+        }
+    }
+}
+
+/// Connects to an MCP server and creates a tool for each of its tools, plus
+/// one that reads its resources if it has any.
+///
+/// Tools are named after the server and the tool, e.g. `github_create_issue`,
+/// and the resource tool after the server, e.g. `github_read_resource`.
+///
+/// # Arguments
+///
+/// * `client` - The connection to the server.
+/// * `only` - If not empty, only the server's tools with these names become
+///   tools.
+///
+/// # Returns
+///
+/// A `Result` with the tools, or an error if the server cannot list them or
+/// does not have one of `only`.
+pub async fn load(client: McpClient, only: &[String]) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+    let client = Arc::new(client);
+    let mut tools: Vec<Box<dyn Tool + Send + Sync>> = Vec::new();
+    let listed = client.list("tools/list", "tools").await?;
+    let mut infos = Vec::new();
+    for info in listed {
+        let info: ToolInfo = serde_json::from_value(info)
+            .map_err(|e| anyhow!("MCP server {} listed an invalid tool: {}", client.name, e))?;
+        infos.push(info);
+    }
+    for name in only {
+        if !infos.iter().any(|info| &info.name == name) {
+            return Err(anyhow!(
+                "MCP server {} has no tool named {:?}",
+                client.name,
+                name
+            ));
+        }
+    }
+    for info in infos {
+        if only.is_empty() || only.contains(&info.name) {
+            tools.push(Box::new(McpTool::new(client.clone(), info)));
+        }
+    }
+
+    if client.capabilities.get("resources").is_some() {
+        let mut resources = Vec::new();
+        for resource in client.list("resources/list", "resources").await? {
+            if let Ok(resource) = serde_json::from_value::<ResourceInfo>(resource) {
+                resources.push(resource);
+            }
+        }
+        tools.push(Box::new(McpResourceTool::new(client.clone(), &resources)));
+    }
+    Ok(tools)
+}
+
+/// A tool of an MCP server.
+///
+/// The model passes the tool's arguments as a JSON object, which the
+/// description shows the server's JSON Schema for, and the tool returns the
+/// text of the server's result.
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    /// The tool's name on the server.
+    tool: String,
+    description: String,
+}
+
+impl McpTool {
+    fn new(client: Arc<McpClient>, info: ToolInfo) -> Self {
+        let schema = match info.input_schema {
+            Value::Null => json!({ "type": "object" }),
+            schema => schema,
+        };
+        let mut description = info.description.trim().to_string();
+        if !description.is_empty() {
+            description.push('\n');
+        }
+        description.push_str(&format!(
+            "Args: a JSON object matching this JSON Schema: {}",
+            schema
+        ));
+        Self {
+            name: format!("{}_{}", client.name, info.name),
+            tool: info.name,
+            description,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    /// Calls the tool on the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, as a JSON object. Empty means none.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the text of the result, or an error if the
+    /// arguments are not a JSON object, or the server fails the call.
+    async fn execute(&self, args: &str) -> Result<String> {
+        let arguments = match args.trim() {
+            "" => Map::new(),
+            args => serde_json::from_str::<Map<String, Value>>(args)
+                .map_err(|e| anyhow!("Args must be a JSON object: {}", e))?,
+        };
+        let result = self
+            .client
+            .request(
+                "tools/call",
+                json!({ "name": self.tool, "arguments": arguments }),
+            )
+            .await?;
+        let content = result
+            .get("content")
+            .and_then(Value::as_array)
+            .map(|content| content_text(content))
+            .unwrap_or_default();
+        let output = match result.get("structuredContent") {
+            Some(structured) if content.is_empty() => structured.to_string(),
+            _ => content,
+        };
+        if result.get("isError").and_then(Value::as_bool) == Some(true) {
+            return Err(anyhow!("{}", output));
+        }
+        Ok(output)
+    }
+
+    async fn shutdown(&self) {
+        self.client.close().await;
+    }
+}
+
+/// Joins the text of a tool result's content, with a placeholder for each
+/// item that is not text.
+fn content_text(content: &[Value]) -> String {
+    content
+        .iter()
+        .map(|item| {
+            let field = |name: &str| item.get(name).and_then(Value::as_str).unwrap_or_default();
+            match field("type") {
+                "text" => field("text").to_string(),
+                "resource" => {
+                    let resource = &item["resource"];
+                    match resource.get("text").and_then(Value::as_str) {
+                        Some(text) => text.to_string(),
+                        None => format!("[resource {}]", resource["uri"].as_str().unwrap_or("")),
+                    }
+                }
+                "resource_link" => format!("[resource {}]", field("uri")),
+                kind => format!("[{} {}]", kind, field("mimeType")),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A tool that reads the resources of an MCP server, such as files or
+/// database rows, by URI.
+pub struct McpResourceTool {
+    client: Arc<McpClient>,
+    name: String,
+    description: String,
+}
+
+impl McpResourceTool {
+    fn new(client: Arc<McpClient>, resources: &[ResourceInfo]) -> Self {
+        let mut description = format!(
+            "Reads a resource of the {} MCP server. Args: the resource's URI.",
+            client.name
+        );
+        if !resources.is_empty() {
+            description.push_str(" Resources:");
+        }
+        for resource in resources.iter().take(MAX_LISTED_RESOURCES) {
+            description.push_str(&format!("\n- {} ({})", resource.uri, resource.name));
+            if let Some(about) = &resource.description {
+                description.push_str(&format!(": {}", about));
+            }
+        }
+        if resources.len() > MAX_LISTED_RESOURCES {
+            description.push_str(&format!(
+                "\n- and {} more",
+                resources.len() - MAX_LISTED_RESOURCES
+            ));
+        }
+        Self {
+            name: format!("{}_read_resource", client.name),
+            description,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpResourceTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    /// Reads a resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The resource's URI.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the resource's text, with a placeholder for binary
+    /// contents, or an error if the server cannot read it.
+    async fn execute(&self, args: &str) -> Result<String> {
+        let uri = args.trim();
+        if uri.is_empty() {
+            return Err(anyhow!("Args must be a resource URI"));
+        }
+        let result = self
+            .client
+            .request("resources/read", json!({ "uri": uri }))
+            .await?;
+        let contents = result
+            .get("contents")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        Ok(contents
+            .iter()
+            .map(
+                |content| match content.get("text").and_then(Value::as_str) {
+                    Some(text) => text.to_string(),
+                    None => format!(
+                        "[binary {}, {} bytes of base64]",
+                        content["mimeType"].as_str().unwrap_or("content"),
+                        content["blob"].as_str().map_or(0, str::len)
+                    ),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    async fn shutdown(&self) {
+        self.client.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{
+        http::HttpConfig,
+        jsonrpc::{Request, Response},
+        test_server::{handle, serve},
+    };
+
+    /// A server in shell with an `echo` tool, which fails on "fail", and a
+    /// resource.
+    const SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{},"resources":{}},"serverInfo":{"name":"test","version":"1"}}}\n' "$id" ;;
+    *'"method":"notifications/'*) ;;
+    *'"method":"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echoes text.","inputSchema":{"type":"object","properties":{"text":{"type":"string"}}}}]}}\n' "$id" ;;
+    *'"text":"fail"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"It failed."}],"isError":true}}\n' "$id" ;;
+    *'"method":"tools/call"'*)
+      text=$(printf '%s' "$line" | sed 's/.*"text":"\([^"]*\)".*/\1/')
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echo: %s"},{"type":"image","data":"","mimeType":"image/png"}]}}\n' "$id" "$text" ;;
+    *'"method":"resources/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"resources":[{"uri":"file:///notes.txt","name":"notes"}]}}\n' "$id" ;;
+    *'"method":"resources/read"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"contents":[{"uri":"file:///notes.txt","text":"Some notes."}]}}\n' "$id" ;;
+    *)
+      printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"Unknown method"}}\n' "$id" ;;
+  esac
+done
+"#;
+
+    fn config(value: Value) -> McpServerConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stdio_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = McpClient::connect(
+            "test",
+            config(json!({ "command": "sh", "args": ["-c", SERVER] })),
+            dir.path().to_path_buf(),
+            HttpClient::new(&HttpConfig::default()).unwrap(),
+            Secrets::default(),
+        )
+        .await
+        .unwrap();
+        let tools = load(client, &[]).await.unwrap();
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name()).collect();
+        assert_eq!(names, ["test_echo", "test_read_resource"]);
+        assert_eq!(
+            tools[0].description(),
+            "Echoes text.\nArgs: a JSON object matching this JSON Schema: \
+             {\"properties\":{\"text\":{\"type\":\"string\"}},\"type\":\"object\"}"
+        );
+        assert!(tools[1]
+            .description()
+            .ends_with("Resources:\n- file:///notes.txt (notes)"));
+
+        assert_eq!(
+            tools[0].execute(r#"{"text":"hi"}"#).await.unwrap(),
+            "echo: hi\n[image image/png]"
+        );
+        assert_eq!(
+            tools[0]
+                .execute(r#"{"text":"fail"}"#)
+                .await
+                .unwrap_err()
+                .to_string(),
+            "It failed."
+        );
+        assert!(tools[0].execute("hi").await.is_err());
+        assert_eq!(
+            tools[1].execute("file:///notes.txt").await.unwrap(),
+            "Some notes."
+        );
+
+        // After the run ends, the next call starts the server again.
+        tools[0].shutdown().await;
+        assert_eq!(
+            tools[0].execute(r#"{"text":"back"}"#).await.unwrap(),
+            "echo: back\n[image image/png]"
+        );
+        tools[1].shutdown().await;
+    }
+
+    /// Answers a request to an HTTP server with a tool, in a stream of
+    /// events that starts with a notification.
+    fn http_server(request: &str) -> String {
+        let body = request.split("\r\n\r\n").nth(1).unwrap_or_default();
+        let request: Request = serde_json::from_str(body).unwrap();
+        let Some(id) = request.id else {
+            return String::new();
+        };
+        let response = match request.method.as_str() {
+            "initialize" => Response::result(
+                id,
+                json!({ "protocolVersion": PROTOCOL_VERSION, "capabilities": {} }),
+            ),
+            "tools/list" => Response::result(id, json!({ "tools": [{ "name": "add" }] })),
+            "tools/call" => {
+                let arguments = &request.params["arguments"];
+                let sum = arguments["a"].as_i64().unwrap() + arguments["b"].as_i64().unwrap();
+                Response::result(
+                    id,
+                    json!({ "content": [{ "type": "text", "text": sum.to_string() }] }),
+                )
+            }
+            method => Response::error(id, -32601, method),
+        };
+        let progress = json!({ "jsonrpc": "2.0", "method": "notifications/progress" });
+        format!(
+            "event: message\ndata: {}\n\ndata: {}\n\n",
+            progress,
+            serde_json::to_string(&response).unwrap()
+        )
+    }
+
+    #[tokio::test]
+    async fn test_http_server() {
+        let (base, requests) = serve(vec![handle("/mcp", "text/event-stream", http_server)]).await;
+        let http = HttpClient::new(&HttpConfig {
+            allow_private_networks: true,
+            ..HttpConfig::default()
+        })
+        .unwrap();
+        let client = McpClient::connect(
+            "calc",
+            config(json!({ "url": base.join("/mcp").unwrap().to_string() })),
+            PathBuf::new(),
+            http,
+            Secrets::default(),
+        )
+        .await
+        .unwrap();
+        let tools = load(client, &["add".to_string()]).await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(
+            tools[0].description(),
+            "Args: a JSON object matching this JSON Schema: {\"type\":\"object\"}"
+        );
+        assert_eq!(tools[0].execute(r#"{"a":2,"b":3}"#).await.unwrap(), "5");
+        assert_eq!(requests.lock().unwrap().len(), 4);
+    }
+}
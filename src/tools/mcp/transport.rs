@@ -0,0 +1,152 @@
+use crate::tools::{
+    http::HttpClient,
+    jsonrpc::{Request, Response, StdioClient},
+    secrets::{redact, Secrets},
+};
+use anyhow::{anyhow, Result};
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE},
+    Method, Url,
+};
+use serde_json::Value;
+use std::time::Duration;
+
+/// The header that carries the session the server assigned at
+/// initialization.
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// How long a server started as a process gets to exit after its input is
+/// closed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// A connection to an MCP server.
+pub enum Transport {
+    /// A server started as a child process, on its standard input and
+    /// output.
+    Stdio(StdioClient),
+    /// A server reached over the Streamable HTTP transport.
+    Http(HttpTransport),
+}
+
+impl Transport {
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        match self {
+            Self::Stdio(client) => client.request(method, params).await,
+            Self::Http(client) => client.request(method, params).await,
+        }
+    }
+
+    pub async fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        match self {
+            Self::Stdio(client) => client.notify(method, params).await,
+            Self::Http(client) => client.notify(method, params).await,
+        }
+    }
+
+    pub async fn close(self) {
+        match self {
+            Self::Stdio(client) => client.close(SHUTDOWN_GRACE).await,
+            Self::Http(_) => {}
+        }
+    }
+}
+
+/// The Streamable HTTP transport: each message is `POST`ed to the server's
+/// URL, which answers with a JSON response or a stream of server-sent
+/// events that includes the response.
+pub struct HttpTransport {
+    http: HttpClient,
+    url: Url,
+    /// The secret every request authenticates with.
+    auth: Option<String>,
+    secrets: Secrets,
+    session: Option<String>,
+    next_id: u64,
+}
+
+impl HttpTransport {
+    pub fn new(http: HttpClient, url: Url, auth: Option<String>, secrets: Secrets) -> Self {
+        Self {
+            http,
+            url,
+            auth,
+            secrets,
+            session: None,
+            next_id: 1,
+        }
+    }
+
+    async fn post(&mut self, request: &Request) -> Result<(Option<String>, String)> {
+        let mut builder = self
+            .http
+            .request(Method::POST, self.url.clone())?
+            .header(ACCEPT, "application/json, text/event-stream")
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(request)?);
+        if let Some(session) = &self.session {
+            builder = builder.header(SESSION_HEADER, session);
+        }
+        let mut redactions = Vec::new();
+        if let Some(auth) = &self.auth {
+            (builder, redactions) = self.secrets.apply(auth, &self.url, builder)?;
+        }
+        let response = self.http.send(builder).await?.error_for_status()?;
+        if let Some(session) = response
+            .headers
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            self.session = Some(session.to_string());
+        }
+        Ok((response.content_type, redact(&response.body, &redactions)))
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let (content_type, body) = self.post(&Request::new(Some(id), method, params)).await?;
+        let messages = match content_type.as_deref() {
+            Some(content_type) if content_type.starts_with("text/event-stream") => events(&body),
+            _ => vec![body],
+        };
+        for message in messages {
+            let message: Value = serde_json::from_str(&message)
+                .map_err(|e| anyhow!("{} sent invalid JSON: {}", self.url, e))?;
+            // A batch holds the response among other messages.
+            let candidates = match message {
+                Value::Array(messages) => messages,
+                message => vec![message],
+            };
+            for candidate in candidates {
+                if candidate.get("method").is_none() && candidate.get("id") == Some(&id.into()) {
+                    let response: Response = serde_json::from_value(candidate)
+                        .map_err(|e| anyhow!("{} sent an invalid response: {}", self.url, e))?;
+                    return response.into_result();
+                }
+            }
+        }
+        Err(anyhow!("{} did not respond to {}", self.url, method))
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.post(&Request::new(None, method, params)).await?;
+        Ok(())
+    }
+}
+
+/// Returns the data of each event in a stream of server-sent events.
+fn events(body: &str) -> Vec<String> {
+    let mut events = Vec::new();
+    let mut data: Vec<&str> = Vec::new();
+    for line in body.lines().chain([""]) {
+        if line.is_empty() {
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+                data.clear();
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    events
+}
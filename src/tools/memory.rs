@@ -0,0 +1,265 @@
+use crate::{memory::Memory, tools::Tool};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::fmt::Write;
+
+/// The arguments accepted by `RememberTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `text`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RememberArgs {
+    /// The fact to remember.
+    text: String,
+    /// An id to store the memory under, replacing the memory already
+    /// stored with it.
+    #[serde(default)]
+    id: Option<String>,
+}
+
+impl RememberArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for RememberTool: {}", e));
+        }
+        Ok(Self {
+            text: args.to_string(),
+            id: None,
+        })
+    }
+}
+
+/// A tool that stores a fact in long-term memory, where it is kept across
+/// runs.
+pub struct RememberTool {
+    memory: Memory,
+}
+
+impl RememberTool {
+    /// Creates a new `RememberTool` that stores memories in the namespace of
+    /// `memory`.
+    pub fn new(memory: Memory) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl Tool for RememberTool {
+    /// Returns the name of the tool, "RememberTool".
+    fn name(&self) -> &str {
+        "RememberTool"
+    }
+
+    fn description(&self) -> String {
+        "Stores a fact in long-term memory, which is kept across tasks. Args: the text to \
+         remember, or a JSON object with the `text` and an optional `id` to replace an \
+         earlier memory with."
+            .to_string()
+    }
+
+    /// Remembers a fact.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either the bare text, or a JSON object with the fields
+    ///   `text` and `id`.
+    ///   Example: `{"text": "The API rate limit is 100 requests a minute", "id": "rate-limit"}`
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the memory's id, or an error if it cannot be stored.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = RememberArgs::parse(args)?;
+        let id = self.memory.remember(&args.text, args.id.as_deref()).await?;
+        Ok(format!("Remembered as `{}`.", id))
+    }
+}
+
+/// The arguments accepted by `RecallTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `query`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RecallArgs {
+    /// What to look for, in natural language.
+    query: String,
+    /// The maximum number of memories to return.
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    5
+}
+
+impl RecallArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for RecallTool: {}", e));
+        }
+        if args.is_empty() {
+            return Err(anyhow!("RecallTool requires a query"));
+        }
+        Ok(Self {
+            query: args.to_string(),
+            limit: default_limit(),
+        })
+    }
+}
+
+/// A tool that finds the memories most related to a query, by meaning
+/// rather than exact words.
+pub struct RecallTool {
+    memory: Memory,
+}
+
+impl RecallTool {
+    /// Creates a new `RecallTool` that searches the namespace of `memory`.
+    pub fn new(memory: Memory) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl Tool for RecallTool {
+    /// Returns the name of the tool, "RecallTool".
+    fn name(&self) -> &str {
+        "RecallTool"
+    }
+
+    fn description(&self) -> String {
+        "Searches long-term memory for the facts most related to a query. Args: the query, \
+         or a JSON object with the `query` and an optional `limit` (default 5)."
+            .to_string()
+    }
+
+    /// Recalls memories.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either the bare query, or a JSON object with the fields
+    ///   `query` and `limit`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with one `[id] (score) text` line per memory, most related
+    /// first, or an error if the search fails.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = RecallArgs::parse(args)?;
+        let matches = self.memory.recall(&args.query, args.limit).await?;
+        if matches.is_empty() {
+            return Ok("No memories found.".to_string());
+        }
+        let mut output = String::new();
+        for found in matches {
+            let _ = writeln!(
+                output,
+                "[{}] ({:.2}) {}",
+                found.record.id, found.score, found.record.text
+            );
+        }
+        Ok(output)
+    }
+}
+
+/// A tool that deletes a memory by id, for facts that turned out to be
+/// wrong or have gone stale.
+pub struct ForgetTool {
+    memory: Memory,
+}
+
+impl ForgetTool {
+    /// Creates a new `ForgetTool` that deletes from the namespace of
+    /// `memory`.
+    pub fn new(memory: Memory) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl Tool for ForgetTool {
+    /// Returns the name of the tool, "ForgetTool".
+    fn name(&self) -> &str {
+        "ForgetTool"
+    }
+
+    fn description(&self) -> String {
+        "Deletes a memory from long-term memory. Args: the memory's id, as shown by RecallTool."
+            .to_string()
+    }
+
+    /// Forgets a memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The id of the memory.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` saying whether the memory existed, or an error if it
+    /// cannot be deleted.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let id = args.trim().trim_matches('"');
+        if self.memory.forget(id).await? {
+            Ok(format!("Forgot `{}`.", id))
+        } else {
+            Ok(format!("No memory with the id `{}`.", id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{embedding::HashEmbedder, store::InMemoryStore};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_remember_and_recall() {
+        let memory = Memory::new(
+            Arc::new(HashEmbedder::new(64)),
+            Arc::new(InMemoryStore::new()),
+        );
+        let remember = RememberTool::new(memory.clone());
+        let recall = RecallTool::new(memory.clone());
+        let forget = ForgetTool::new(memory);
+
+        assert_eq!(
+            remember
+                .execute(
+                    r#"{"text": "The API rate limit is 100 requests a minute", "id": "rate-limit"}"#
+                )
+                .await
+                .unwrap(),
+            "Remembered as `rate-limit`."
+        );
+        remember
+            .execute("Release notes live in CHANGELOG.md")
+            .await
+            .unwrap();
+
+        let result = recall
+            .execute(r#"{"query": "what is the rate limit", "limit": 1}"#)
+            .await
+            .unwrap();
+        assert!(result.starts_with("[rate-limit] ("), "{}", result);
+        assert!(result.ends_with(") The API rate limit is 100 requests a minute\n"));
+
+        assert_eq!(
+            forget.execute("rate-limit").await.unwrap(),
+            "Forgot `rate-limit`."
+        );
+        let result = recall.execute("what is the rate limit").await.unwrap();
+        assert!(!result.contains("rate-limit"), "{}", result);
+    }
+}
@@ -12,6 +12,15 @@ pub trait Tool {
     /// The name should be a unique identifier for the tool.
     fn name(&self) -> &str;
 
+    /// Returns a description of what the tool does and the arguments it
+    /// takes, which is shown to the model next to the tool's name.
+    ///
+    /// The default implementation returns an empty string, in which case
+    /// only the name is shown.
+    fn description(&self) -> String {
+        String::new()
+    }
+
     /// Executes the tool with the given arguments.
     ///
     /// # Arguments
@@ -24,10 +33,39 @@ pub trait Tool {
     /// A `Result` containing a string with the output of the tool's execution,
     /// or an error if the execution fails.
     async fn execute(&self, args: &str) -> Result<String>;
+
+    /// Releases any resources the tool holds between calls, such as a
+    /// running shell.
+    ///
+    /// This is called once the agent run that owns the tool has finished.
+    /// The default implementation does nothing.
+    async fn shutdown(&self) {}
 }
 
+pub mod cargo;
+pub mod code_search;
 pub mod code_writer;
+pub mod command_policy;
+pub mod database;
 pub mod directory_lister;
+pub mod external;
 pub mod file_reader;
+pub mod git;
+pub mod http;
+pub mod jsonrpc;
+pub mod mcp;
+pub mod memory;
+pub mod outline;
+pub mod process;
+pub mod process_manager;
+pub mod registry;
+pub mod rest_api;
+pub mod sandbox;
+pub mod secrets;
+pub mod semantic_search;
+pub mod shell_session;
 pub mod system;
-pub mod web_scraper;
\ No newline at end of file
+#[cfg(test)]
+mod test_server;
+pub mod web_scraper;
+pub mod workspace;
@@ -0,0 +1,290 @@
+pub mod symbols;
+
+use crate::tools::{workspace::Workspace, Tool};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ignore::WalkBuilder;
+use serde::Deserialize;
+use std::{fmt::Write, fs, path::Path};
+use symbols::{symbols, Language, Symbol};
+
+/// Files larger than this are skipped, as they are usually generated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// A directory outline covers at most this many files.
+const MAX_FILES: usize = 200;
+
+/// Output beyond this many characters is cut off.
+const MAX_OUTPUT_CHARS: usize = 30_000;
+
+/// The arguments accepted by `OutlineTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `path`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct OutlineArgs {
+    /// The file or directory to outline, relative to the workspace root.
+    #[serde(default)]
+    path: String,
+    /// The name of a symbol whose full source to return instead, either
+    /// plain (`parse`) or qualified (`Parser::parse`, `Parser.parse`).
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+impl OutlineArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for OutlineTool: {}", e));
+        }
+        Ok(Self {
+            path: args.to_string(),
+            symbol: None,
+        })
+    }
+}
+
+/// A tool that lists the definitions in source files, so the model can
+/// find its way around a codebase without reading whole files.
+///
+/// Files are parsed with tree-sitter. Rust, Python, TypeScript, JavaScript
+/// and Go are supported. Each definition is shown as `start-end: signature`
+/// and indented below the definition it belongs to, such as methods inside
+/// their class or `impl` block.
+pub struct OutlineTool {
+    workspace: Workspace,
+}
+
+impl OutlineTool {
+    /// Creates a new `OutlineTool` confined to the given workspace.
+    pub fn new(workspace: Workspace) -> Self {
+        Self { workspace }
+    }
+}
+
+/// Reads and parses one source file.
+fn parse_file(path: &Path, language: Language) -> Result<(String, Vec<Symbol>)> {
+    let source = fs::read_to_string(path)?;
+    let symbols = symbols(&source, language)?;
+    Ok((source, symbols))
+}
+
+/// Lists the source files at `root`, which may be a single file, in path
+/// order, with whether the listing was cut short.
+fn source_files(root: &Path) -> (Vec<(std::path::PathBuf, Language)>, bool) {
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+    let mut files = Vec::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+            continue;
+        }
+        let Some(language) = Language::from_path(entry.path()) else {
+            continue;
+        };
+        if files.len() == MAX_FILES {
+            return (files, true);
+        }
+        files.push((entry.into_path(), language));
+    }
+    (files, false)
+}
+
+/// Writes the symbols as indented `start-end: signature` lines.
+fn write_outline(output: &mut String, symbols: &[Symbol], depth: usize) {
+    for symbol in symbols {
+        let _ = writeln!(
+            output,
+            "{:indent$}{}-{}: {}",
+            "",
+            symbol.start_line,
+            symbol.end_line,
+            symbol.signature,
+            indent = 2 * (depth + 1)
+        );
+        write_outline(output, &symbol.children, depth + 1);
+    }
+}
+
+/// Finds the symbols matching `name`, by plain or qualified name.
+fn find<'a>(symbols: &'a [Symbol], name: &str, found: &mut Vec<&'a Symbol>) {
+    for symbol in symbols {
+        let qualified = symbol.qualified_name.replace("::", ".");
+        if symbol.name == name || symbol.qualified_name == name || qualified == name {
+            found.push(symbol);
+        }
+        find(&symbol.children, name, found);
+    }
+}
+
+/// Outlines the files at the requested path, or returns the source of the
+/// requested symbol.
+fn outline(workspace: &Workspace, args: &OutlineArgs) -> Result<String> {
+    let root = workspace.resolve(&args.path)?;
+    if !root.exists() {
+        return Err(anyhow!("No such file or directory: {}", args.path));
+    }
+    if root.is_file() && Language::from_path(&root).is_none() {
+        return Err(anyhow!(
+            "Unsupported file type: {}. Outlines support Rust, Python, TypeScript, \
+             JavaScript and Go.",
+            args.path
+        ));
+    }
+    let (files, truncated) = source_files(&root);
+
+    let mut output = String::new();
+    let mut matches = 0;
+    for (path, language) in files {
+        let display = workspace.relative(&path).display().to_string();
+        let (source, symbols) = match parse_file(&path, language) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let _ = writeln!(output, "{}: could not be outlined: {}", display, e);
+                continue;
+            }
+        };
+        match &args.symbol {
+            None if symbols.is_empty() => {}
+            None => {
+                let _ = writeln!(output, "{}", display);
+                write_outline(&mut output, &symbols, 0);
+            }
+            Some(name) => {
+                let mut found = Vec::new();
+                find(&symbols, name.trim(), &mut found);
+                for symbol in found {
+                    matches += 1;
+                    let _ = writeln!(
+                        output,
+                        "{}:{}-{}",
+                        display, symbol.start_line, symbol.end_line
+                    );
+                    // Start at the beginning of the line so that the first
+                    // line keeps its indentation.
+                    let start = source[..symbol.bytes.start]
+                        .rfind('\n')
+                        .map_or(0, |i| i + 1);
+                    for (i, line) in source[start..symbol.bytes.end].lines().enumerate() {
+                        let _ = writeln!(output, "{:>5}  {}", symbol.start_line + i, line);
+                    }
+                }
+            }
+        }
+        if let Some((cut, _)) = output.char_indices().nth(MAX_OUTPUT_CHARS) {
+            output.truncate(cut);
+            output.push_str("\n(output truncated; outline a narrower path)\n");
+            return Ok(output);
+        }
+    }
+
+    if let Some(name) = &args.symbol {
+        if matches == 0 {
+            return Ok(format!(
+                "No symbol named `{}` found in {}.",
+                name,
+                display_path(args)
+            ));
+        }
+    } else if output.is_empty() {
+        return Ok(format!("No symbols found in {}.", display_path(args)));
+    }
+    if truncated {
+        let _ = writeln!(
+            output,
+            "(only the first {} files were outlined; outline a narrower path)",
+            MAX_FILES
+        );
+    }
+    Ok(output)
+}
+
+fn display_path(args: &OutlineArgs) -> &str {
+    match args.path.trim() {
+        "" => "the workspace",
+        path => path,
+    }
+}
+
+#[async_trait]
+impl Tool for OutlineTool {
+    /// Returns the name of the tool, "OutlineTool".
+    fn name(&self) -> &str {
+        "OutlineTool"
+    }
+
+    fn description(&self) -> String {
+        "Lists the functions, types, classes and methods in a source file or directory, \
+         with their line ranges, or shows the source of one symbol. Args: a path, or a JSON \
+         object with an optional `path` and `symbol`, a name such as `parse` or \
+         `Parser::parse`."
+            .to_string()
+    }
+
+    /// Outlines a file or directory, or shows a symbol's source.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either a bare path, or a JSON object with the fields `path`
+    ///   and `symbol`.
+    ///   Example: `{"path": "src", "symbol": "Workspace::resolve"}`
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the outline, or the matching symbols' source with
+    /// line numbers, or an error if the path is invalid.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = OutlineArgs::parse(args)?;
+        let workspace = self.workspace.clone();
+        tokio::task::spawn_blocking(move || outline(&workspace, &args)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_outline_and_symbol() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "pub struct Parser;\n\nimpl Parser {\n    pub fn parse(&self) -> u32 {\n        1\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/tool.py"),
+            "def parse(text):\n    return text\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/notes.md"), "# Notes\n").unwrap();
+        let tool = OutlineTool::new(Workspace::new(dir.path()).unwrap());
+
+        assert_eq!(
+            tool.execute("src").await.unwrap(),
+            "src/lib.rs\n  1-1: pub struct Parser\n  3-7: impl Parser\n    4-6: pub fn parse(&self) -> u32\n\
+             src/tool.py\n  1-2: def parse(text)\n"
+        );
+        assert_eq!(
+            tool.execute(r#"{"symbol": "Parser.parse"}"#).await.unwrap(),
+            "src/lib.rs:4-6\n    4      pub fn parse(&self) -> u32 {\n    5          1\n    6      }\n"
+        );
+        assert_eq!(
+            tool.execute(r#"{"path": "src/tool.py", "symbol": "missing"}"#)
+                .await
+                .unwrap(),
+            "No symbol named `missing` found in src/tool.py."
+        );
+        assert!(tool.execute("src/notes.md").await.is_err());
+    }
+}
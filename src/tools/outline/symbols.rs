@@ -0,0 +1,308 @@
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use tree_sitter::{Language as Grammar, Node, Parser};
+
+/// The languages outlines can be made for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Rust,
+    Python,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl Language {
+    /// Picks the language from a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "ts" | "mts" | "cts" | "js" | "mjs" | "cjs" => Some(Self::TypeScript),
+            "tsx" | "jsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn grammar(self) -> Grammar {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    /// The separator between a symbol and its members in qualified names.
+    fn separator(self) -> &'static str {
+        match self {
+            Self::Rust => "::",
+            _ => ".",
+        }
+    }
+}
+
+/// A definition in a source file.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// The name qualified by the enclosing symbols, e.g. `Parser::parse`.
+    pub qualified_name: String,
+    /// The definition up to its body, on one line, e.g. `fn parse(&self)`.
+    pub signature: String,
+    /// The first and last lines, counting from 1.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// The byte range of the whole definition, including its body.
+    pub bytes: std::ops::Range<usize>,
+    pub children: Vec<Symbol>,
+}
+
+/// The longest signature shown before it is cut off.
+const MAX_SIGNATURE_CHARS: usize = 160;
+
+/// Parses `source` and returns its top-level symbols, with their members
+/// nested inside them.
+pub fn symbols(source: &str, language: Language) -> Result<Vec<Symbol>> {
+    let mut parser = Parser::new();
+    parser.set_language(&language.grammar())?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| anyhow!("Failed to parse the file"))?;
+    let mut symbols = Vec::new();
+    collect(tree.root_node(), source, language, "", &mut symbols);
+    Ok(symbols)
+}
+
+/// Collects the symbols under `node`, qualifying their names with `scope`.
+fn collect(node: Node, source: &str, language: Language, scope: &str, out: &mut Vec<Symbol>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match definition(child, source, language) {
+            Some((name, body)) => {
+                let qualified_name = if scope.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}{}{}", scope, language.separator(), name)
+                };
+                let mut symbol = Symbol {
+                    name,
+                    signature: signature(child, body, source, language),
+                    start_line: child.start_position().row + 1,
+                    end_line: child.end_position().row + 1,
+                    bytes: child.byte_range(),
+                    children: Vec::new(),
+                    qualified_name,
+                };
+                if let Some(body) = body {
+                    collect(
+                        body,
+                        source,
+                        language,
+                        &symbol.qualified_name,
+                        &mut symbol.children,
+                    );
+                }
+                out.push(symbol);
+            }
+            // Look inside wrappers such as `export` statements, decorators
+            // and Go `type` blocks.
+            None => collect(child, source, language, scope, out),
+        }
+    }
+}
+
+/// Returns the name and body of `node` if it is a definition worth
+/// listing. Definitions inside function bodies are not.
+fn definition<'a>(
+    node: Node<'a>,
+    source: &str,
+    language: Language,
+) -> Option<(String, Option<Node<'a>>)> {
+    let text = |node: Node| source[node.byte_range()].to_string();
+    let field = |name: &str| node.child_by_field_name(name);
+    let named = |kinds: &[&str]| kinds.contains(&node.kind());
+
+    let name = match language {
+        Language::Rust => {
+            if named(&["impl_item"]) {
+                // Methods of `impl Trait for Type` belong to `Type`.
+                return Some((text(field("type")?), field("body")));
+            }
+            if !named(&[
+                "function_item",
+                "function_signature_item",
+                "struct_item",
+                "enum_item",
+                "union_item",
+                "trait_item",
+                "mod_item",
+                "type_item",
+                "const_item",
+                "static_item",
+                "macro_definition",
+            ]) {
+                return None;
+            }
+            text(field("name")?)
+        }
+        Language::Python => {
+            if !named(&["function_definition", "class_definition"]) {
+                return None;
+            }
+            text(field("name")?)
+        }
+        Language::TypeScript | Language::Tsx => {
+            if named(&["variable_declarator"]) {
+                // `const handler = () => ...` is a function in all but name.
+                let value = field("value")?;
+                if !matches!(value.kind(), "arrow_function" | "function_expression") {
+                    return None;
+                }
+                return Some((text(field("name")?), None));
+            }
+            if !named(&[
+                "function_declaration",
+                "generator_function_declaration",
+                "class_declaration",
+                "abstract_class_declaration",
+                "interface_declaration",
+                "type_alias_declaration",
+                "enum_declaration",
+                "method_definition",
+                "abstract_method_signature",
+                "method_signature",
+                "internal_module",
+            ]) {
+                return None;
+            }
+            text(field("name")?)
+        }
+        Language::Go => match node.kind() {
+            "function_declaration" | "type_spec" => text(field("name")?),
+            "method_declaration" => {
+                // Qualify methods with their receiver's type, e.g.
+                // `(*Server).Start` as `Server.Start`.
+                let receiver = text(field("receiver")?);
+                let receiver = receiver
+                    .trim_matches(|c| c == '(' || c == ')')
+                    .split_whitespace()
+                    .last()?
+                    .trim_start_matches('*')
+                    .to_string();
+                format!("{}.{}", receiver, text(field("name")?))
+            }
+            _ => return None,
+        },
+    };
+
+    let body = match (language, node.kind()) {
+        // Function bodies hold no symbols worth listing.
+        (Language::Rust, "function_item") => None,
+        (Language::Python, "function_definition") => None,
+        (Language::Go, _) => None,
+        (Language::TypeScript | Language::Tsx, kind)
+            if kind.contains("function") || kind.contains("method") =>
+        {
+            None
+        }
+        _ => field("body"),
+    };
+    Some((name, body))
+}
+
+/// Returns the definition's text up to its body, collapsed onto one line.
+fn signature(node: Node, body: Option<Node>, source: &str, language: Language) -> String {
+    let end = node
+        .child_by_field_name("body")
+        .or(body)
+        .map_or(node.end_byte(), |body| body.start_byte());
+    let mut text = source[node.start_byte()..end.max(node.start_byte())]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if language == Language::Python {
+        text = text.trim_end_matches(':').to_string();
+    }
+    if node.kind() == "type_spec" {
+        // Go structs and interfaces are listed without their fields.
+        text = format!(
+            "type {}",
+            text.split('{').next().unwrap_or_default().trim_end()
+        );
+    }
+    let text = text.trim_end_matches(['{', '=', ' ', ';']);
+    match text.char_indices().nth(MAX_SIGNATURE_CHARS) {
+        Some((cut, _)) => format!("{}...", &text[..cut]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(symbols: &[Symbol]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for symbol in symbols {
+            lines.push(format!(
+                "{} {}-{} {}",
+                symbol.qualified_name, symbol.start_line, symbol.end_line, symbol.signature
+            ));
+            lines.extend(outline(&symbol.children));
+        }
+        lines
+    }
+
+    #[test]
+    fn test_symbols_by_language() {
+        let rust = "struct Point { x: i32 }\n\
+                    impl Display for Point {\n    fn fmt(&self, f: &mut Formatter) -> Result {\n        fn helper() {}\n        Ok(())\n    }\n}\n\
+                    mod geometry {\n    pub fn area() -> f64 { 0.0 }\n}\n";
+        assert_eq!(
+            outline(&symbols(rust, Language::Rust).unwrap()),
+            [
+                "Point 1-1 struct Point",
+                "Point 2-7 impl Display for Point",
+                "Point::fmt 3-6 fn fmt(&self, f: &mut Formatter) -> Result",
+                "geometry 8-10 mod geometry",
+                "geometry::area 9-9 pub fn area() -> f64",
+            ]
+        );
+
+        let python = "class Shape:\n    @property\n    def area(self) -> float:\n        return 0.0\n\ndef main():\n    pass\n";
+        assert_eq!(
+            outline(&symbols(python, Language::Python).unwrap()),
+            [
+                "Shape 1-4 class Shape",
+                "Shape.area 3-4 def area(self) -> float",
+                "main 6-7 def main()",
+            ]
+        );
+
+        let typescript = "export class Store {\n  get(key: string): string { return key; }\n}\n\
+                          export const load = async (id: number) => id;\n\
+                          interface Item { id: number }\n";
+        assert_eq!(
+            outline(&symbols(typescript, Language::TypeScript).unwrap()),
+            [
+                "Store 1-3 class Store",
+                "Store.get 2-2 get(key: string): string",
+                "load 4-4 load = async (id: number) => id",
+                "Item 5-5 interface Item",
+            ]
+        );
+
+        let go = "package main\n\ntype Server struct {\n\tAddr string\n}\n\n\
+                  func (s *Server) Start() error {\n\treturn nil\n}\n";
+        assert_eq!(
+            outline(&symbols(go, Language::Go).unwrap()),
+            [
+                "Server 3-5 type Server struct",
+                "Server.Start 7-9 func (s *Server) Start() error",
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Kills every process in the process group led by `pid`.
+///
+/// Commands are spawned in their own process group so that this also stops
+/// any children they started, such as the compiler processes of a build.
+pub fn kill_process_group(pid: u32) {
+    // SAFETY: `killpg` has no memory-safety preconditions. A stale `pid`
+    // only results in `ESRCH`.
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+/// A bounded buffer for process output that keeps the beginning and the end
+/// of a stream and drops the middle.
+///
+/// Build logs and test runs put the most useful information at the start
+/// (the first error) and at the end (the summary), so this keeps both
+/// halves instead of cutting the output off at a fixed length.
+#[derive(Debug, Clone)]
+pub struct OutputCapture {
+    limit: usize,
+    head: Vec<u8>,
+    tail: Vec<u8>,
+    total: usize,
+}
+
+impl OutputCapture {
+    /// Creates an empty capture that keeps at most `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            head: Vec::new(),
+            tail: Vec::new(),
+            total: 0,
+        }
+    }
+
+    /// Appends bytes to the capture.
+    pub fn push(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len();
+
+        let head_room = (self.limit / 2).saturating_sub(self.head.len());
+        let (to_head, rest) = bytes.split_at(head_room.min(bytes.len()));
+        self.head.extend_from_slice(to_head);
+        bytes = rest;
+
+        let tail_limit = self.limit - self.limit / 2;
+        if bytes.len() >= tail_limit {
+            self.tail.clear();
+            self.tail
+                .extend_from_slice(&bytes[bytes.len() - tail_limit..]);
+        } else {
+            self.tail.extend_from_slice(bytes);
+            if self.tail.len() > tail_limit {
+                let excess = self.tail.len() - tail_limit;
+                self.tail.drain(..excess);
+            }
+        }
+    }
+
+    /// Reads a stream to the end into the capture.
+    pub async fn read_from<R: AsyncRead + Unpin>(mut self, mut reader: R) -> std::io::Result<Self> {
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(self);
+            }
+            self.push(&buf[..n]);
+        }
+    }
+
+    /// Returns the maximum number of bytes the capture keeps.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns whether any bytes were dropped.
+    pub fn is_truncated(&self) -> bool {
+        self.total > self.head.len() + self.tail.len()
+    }
+
+    /// Decodes the captured bytes, replacing invalid UTF-8 and marking where
+    /// bytes were dropped.
+    pub fn to_string_lossy(&self) -> String {
+        let head = String::from_utf8_lossy(&self.head);
+        let tail = String::from_utf8_lossy(&self.tail);
+        if self.is_truncated() {
+            let omitted = self.total - self.head.len() - self.tail.len();
+            format!("{}\n... [{} bytes omitted] ...\n{}", head, omitted, tail)
+        } else {
+            format!("{}{}", head, tail)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_keeps_head_and_tail() {
+        let mut capture = OutputCapture::new(8);
+        capture.push(b"0123");
+        capture.push(b"456789");
+        capture.push(b"abcdef");
+
+        assert!(capture.is_truncated());
+        assert_eq!(
+            capture.to_string_lossy(),
+            "0123\n... [8 bytes omitted] ...\ncdef"
+        );
+    }
+
+    #[test]
+    fn test_capture_decodes_lossily() {
+        let mut capture = OutputCapture::new(64);
+        capture.push(b"ok \xff\xfe done");
+
+        assert!(!capture.is_truncated());
+        assert_eq!(capture.to_string_lossy(), "ok \u{fffd}\u{fffd} done");
+    }
+}
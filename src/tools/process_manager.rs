@@ -0,0 +1,384 @@
+use crate::tools::{
+    command_policy::{CommandPolicy, Decision},
+    process::{kill_process_group, OutputCapture},
+    sandbox::{Sandbox, SandboxConfig},
+    workspace::Workspace,
+    Tool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    process::Stdio,
+    sync::{Arc, Mutex as SyncMutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::{Child, ChildStdin},
+    sync::Mutex,
+};
+
+/// The maximum number of processes that may be running at once.
+const MAX_PROCESSES: usize = 8;
+
+/// What to do with the background processes.
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ProcessAction {
+    /// Start `command` under `name`.
+    Start,
+    /// Return the output produced since the last poll.
+    Output,
+    /// Describe one process, or all of them.
+    Status,
+    /// Write `input` to the process's stdin.
+    Input,
+    /// Kill the process and everything it started.
+    Kill,
+}
+
+/// The arguments accepted by `ProcessManagerTool`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ProcessArgs {
+    action: ProcessAction,
+    /// The name of the process. Optional only for `status`.
+    #[serde(default)]
+    name: Option<String>,
+    /// The shell command to start, for `start`.
+    #[serde(default)]
+    command: Option<String>,
+    /// The text to write, for `input`. No newline is added.
+    #[serde(default)]
+    input: Option<String>,
+    /// How long `start` and `output` wait for output to accumulate, or for
+    /// the process to exit, before returning.
+    #[serde(default)]
+    wait_seconds: u64,
+}
+
+/// The state of a process, as returned to the model.
+#[derive(Serialize, Debug)]
+struct ProcessStatus {
+    name: String,
+    command: String,
+    running: bool,
+    /// The exit code, once the process has exited. `None` while it runs, or
+    /// if it was killed by a signal.
+    exit_code: Option<i32>,
+    uptime_seconds: u64,
+    /// The output produced since the last poll, if it was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+}
+
+/// A process started by the tool.
+struct BackgroundProcess {
+    command: String,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    /// The combined stdout and stderr not yet returned to the model.
+    output: Arc<SyncMutex<OutputCapture>>,
+    started: Instant,
+    exit_code: Option<Option<i32>>,
+}
+
+impl BackgroundProcess {
+    /// Records the exit status if the process has exited.
+    fn poll_exit(&mut self) -> Result<()> {
+        if self.exit_code.is_none() {
+            if let Some(status) = self.child.try_wait()? {
+                self.exit_code = Some(status.code());
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for the process to exit.
+    async fn wait(&mut self, timeout: Duration) -> Result<()> {
+        if self.exit_code.is_none() {
+            if let Ok(status) = tokio::time::timeout(timeout, self.child.wait()).await {
+                self.exit_code = Some(status?.code());
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the output produced since the last call.
+    fn take_output(&self) -> String {
+        let mut output = self.output.lock().unwrap();
+        let limit = output.limit();
+        std::mem::replace(&mut *output, OutputCapture::new(limit)).to_string_lossy()
+    }
+
+    fn status(&mut self, name: &str, with_output: bool) -> Result<ProcessStatus> {
+        self.poll_exit()?;
+        Ok(ProcessStatus {
+            name: name.to_string(),
+            command: self.command.clone(),
+            running: self.exit_code.is_none(),
+            exit_code: self.exit_code.flatten(),
+            uptime_seconds: self.started.elapsed().as_secs(),
+            output: with_output.then(|| self.take_output()),
+        })
+    }
+
+    /// Kills the process group and waits for the process to exit.
+    async fn kill(&mut self) -> Result<()> {
+        self.poll_exit()?;
+        if let Some(pid) = self.child.id() {
+            kill_process_group(pid);
+        }
+        if self.exit_code.is_none() {
+            self.exit_code = Some(self.child.wait().await?.code());
+        }
+        Ok(())
+    }
+}
+
+/// Copies a stream into the shared output buffer until it closes.
+async fn pump<R: AsyncRead + Unpin>(mut reader: R, output: Arc<SyncMutex<OutputCapture>>) {
+    let mut buf = [0u8; 8192];
+    while let Ok(n) = reader.read(&mut buf).await {
+        if n == 0 {
+            break;
+        }
+        output.lock().unwrap().push(&buf[..n]);
+    }
+}
+
+/// A tool for running long-lived processes, such as dev servers and file
+/// watchers, in the background.
+///
+/// Processes are started under a name chosen by the model, which can then
+/// poll their output incrementally, check whether they are still running,
+/// write to their stdin and kill them. They run in the same sandbox and
+/// under the same command policy as `SystemTool`, but are not subject to the
+/// wall-clock limit. Every process still running is killed when the agent
+/// run ends.
+///
+/// Unread output beyond `max_output_bytes` keeps only its beginning and end.
+pub struct ProcessManagerTool {
+    sandbox: Sandbox,
+    policy: CommandPolicy,
+    max_output_bytes: usize,
+    processes: Mutex<BTreeMap<String, BackgroundProcess>>,
+}
+
+impl ProcessManagerTool {
+    /// Creates a new `ProcessManagerTool` that runs processes in the given
+    /// workspace, subject to the given policy.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if the configured sandbox
+    /// backend is not available.
+    pub fn new(
+        config: &SandboxConfig,
+        workspace: Workspace,
+        policy: CommandPolicy,
+    ) -> Result<Self> {
+        Ok(Self {
+            sandbox: Sandbox::new(config, workspace)?,
+            policy,
+            max_output_bytes: config.limits.max_output_bytes,
+            processes: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Spawns `command` with piped stdio in its own process group.
+    async fn spawn(&self, command: &str) -> Result<BackgroundProcess> {
+        let mut child = self
+            .sandbox
+            .interactive(command)
+            .await?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let output = Arc::new(SyncMutex::new(OutputCapture::new(self.max_output_bytes)));
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(pump(stdout, output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(pump(stderr, output.clone()));
+        }
+
+        Ok(BackgroundProcess {
+            command: command.to_string(),
+            stdin: child.stdin.take(),
+            child,
+            output,
+            started: Instant::now(),
+            exit_code: None,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessManagerTool {
+    /// Returns the name of the tool, "ProcessManagerTool".
+    fn name(&self) -> &str {
+        "ProcessManagerTool"
+    }
+
+    /// Starts, inspects or stops a background process.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - A JSON object with an `action` (`start`, `output`,
+    ///   `status`, `input` or `kill`), the process `name`, and depending on
+    ///   the action a `command`, an `input` or `wait_seconds`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a JSON description of the process, including
+    /// its new output for `start` and `output`, a JSON array of all
+    /// processes for `status` without a name, or a JSON description of the
+    /// denial if the policy rejects the command.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args: ProcessArgs = serde_json::from_str(args.trim())
+            .map_err(|e| anyhow!("Invalid arguments for ProcessManagerTool: {}", e))?;
+        let wait = Duration::from_secs(args.wait_seconds);
+        let mut processes = self.processes.lock().await;
+
+        let Some(name) = args.name else {
+            if args.action != ProcessAction::Status {
+                return Err(anyhow!("The {:?} action requires a name", args.action));
+            }
+            let mut statuses = Vec::new();
+            for (name, process) in processes.iter_mut() {
+                statuses.push(process.status(name, false)?);
+            }
+            return Ok(serde_json::to_string_pretty(&statuses)?);
+        };
+
+        if args.action == ProcessAction::Start {
+            let command = args
+                .command
+                .filter(|command| !command.trim().is_empty())
+                .ok_or_else(|| anyhow!("The start action requires a command"))?;
+            let decision = self.policy.check(&command);
+            if let Decision::Denied { .. } = decision {
+                return Ok(serde_json::to_string_pretty(&decision)?);
+            }
+
+            for process in processes.values_mut() {
+                process.poll_exit()?;
+            }
+            if processes.get(&name).is_some_and(|p| p.exit_code.is_none()) {
+                return Err(anyhow!("A process named {} is already running", name));
+            }
+            let running = processes.values().filter(|p| p.exit_code.is_none()).count();
+            if running >= MAX_PROCESSES {
+                return Err(anyhow!(
+                    "At most {} background processes may run at once",
+                    MAX_PROCESSES
+                ));
+            }
+            processes.insert(name.clone(), self.spawn(&command).await?);
+        }
+
+        let process = processes
+            .get_mut(&name)
+            .ok_or_else(|| anyhow!("No background process named {}", name))?;
+        let status = match args.action {
+            ProcessAction::Start | ProcessAction::Output => {
+                process.wait(wait).await?;
+                process.status(&name, true)?
+            }
+            ProcessAction::Status => process.status(&name, false)?,
+            ProcessAction::Input => {
+                let input = args
+                    .input
+                    .ok_or_else(|| anyhow!("The input action requires an input"))?;
+                let stdin = process
+                    .stdin
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("The stdin of {} is closed", name))?;
+                stdin.write_all(input.as_bytes()).await?;
+                stdin.flush().await?;
+                process.status(&name, false)?
+            }
+            ProcessAction::Kill => {
+                process.kill().await?;
+                // Give the readers a moment to collect the last output.
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let status = process.status(&name, true)?;
+                processes.remove(&name);
+                status
+            }
+        };
+        Ok(serde_json::to_string_pretty(&status)?)
+    }
+
+    /// Kills every process that is still running.
+    async fn shutdown(&self) {
+        let mut processes = self.processes.lock().await;
+        for process in processes.values_mut() {
+            let _ = process.kill().await;
+        }
+        processes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::command_policy::CommandPolicyConfig;
+    use serde_json::{json, Value};
+
+    async fn call(tool: &ProcessManagerTool, args: Value) -> Value {
+        let result = tool.execute(&args.to_string()).await.unwrap();
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_process_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let policy = CommandPolicy::new(&CommandPolicyConfig::default()).unwrap();
+        let tool = ProcessManagerTool::new(&SandboxConfig::default(), workspace, policy).unwrap();
+
+        let result = call(
+            &tool,
+            json!({
+                "action": "start",
+                "name": "echo",
+                "command": "echo ready; while read line; do echo got $line; done",
+                "wait_seconds": 1,
+            }),
+        )
+        .await;
+        assert_eq!(result["running"], true);
+        assert_eq!(result["output"], "ready\n");
+
+        call(
+            &tool,
+            json!({ "action": "input", "name": "echo", "input": "ping\n" }),
+        )
+        .await;
+        let result = call(
+            &tool,
+            json!({ "action": "output", "name": "echo", "wait_seconds": 1 }),
+        )
+        .await;
+        assert_eq!(result["output"], "got ping\n");
+
+        let result = call(&tool, json!({ "action": "status" })).await;
+        assert_eq!(result[0]["name"], "echo");
+
+        let result = call(&tool, json!({ "action": "kill", "name": "echo" })).await;
+        assert_eq!(result["running"], false);
+        assert!(tool
+            .execute(&json!({ "action": "status", "name": "echo" }).to_string())
+            .await
+            .is_err());
+    }
+}
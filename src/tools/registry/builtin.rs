@@ -0,0 +1,306 @@
+use super::{RegisteredTool, ToolContext};
+use crate::{
+    memory::Memory,
+    tools::{
+        cargo::CargoTool,
+        code_search::CodeSearchTool,
+        code_writer::CodeWriterTool,
+        command_policy::{CommandPolicy, CommandPolicyConfig},
+        database::DatabaseTool,
+        directory_lister::DirectoryListerTool,
+        external::ExternalTool,
+        file_reader::FileReaderTool,
+        git::GitTool,
+        mcp::{self, McpClient, McpTool},
+        memory::{ForgetTool, RecallTool, RememberTool},
+        outline::OutlineTool,
+        process_manager::ProcessManagerTool,
+        rest_api::{
+            openapi::{self, OperationTool},
+            RestApiTool,
+        },
+        semantic_search::SemanticSearchTool,
+        shell_session::ShellSessionTool,
+        system::SystemTool,
+        web_scraper::WebScraperTool,
+        Tool,
+    },
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+crate::register_tools!(pub(super) fn register =>
+    CargoTool,
+    CodeSearchTool,
+    CodeWriterTool,
+    DatabaseTool,
+    DirectoryListerTool,
+    ExternalTool,
+    FileReaderTool,
+    ForgetTool,
+    GitTool,
+    McpTool,
+    OperationTool,
+    OutlineTool,
+    ProcessManagerTool,
+    RecallTool,
+    RememberTool,
+    RestApiTool,
+    SemanticSearchTool,
+    ShellSessionTool,
+    SystemTool,
+    WebScraperTool,
+);
+
+/// The options of tools that have none.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoOptions {}
+
+/// The options of the tools that run commands.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessOptions {
+    /// Replaces the agent's entry in `[command_policies]`.
+    command_policy: Option<CommandPolicyConfig>,
+}
+
+impl ProcessOptions {
+    fn policy(self, agent: &str, context: &ToolContext) -> Result<CommandPolicy> {
+        let config = self
+            .command_policy
+            .or_else(|| {
+                // `Config` lowercases the keys of `[command_policies]`.
+                context
+                    .command_policies
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(agent))
+                    .map(|(_, policy)| policy.clone())
+            })
+            .unwrap_or_default();
+        CommandPolicy::new(&config)
+    }
+}
+
+/// The options of the memory tools.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryOptions {
+    /// Keeps the agent's memories apart from other agents'.
+    namespace: Option<String>,
+}
+
+impl MemoryOptions {
+    fn memory(self, tool: &str, context: &ToolContext) -> Result<Memory> {
+        let memory = context
+            .memory
+            .clone()
+            .ok_or_else(|| anyhow!("{} needs a [memory] section", tool))?;
+        Ok(match self.namespace {
+            Some(namespace) => memory.with_namespace(&namespace),
+            None => memory,
+        })
+    }
+}
+
+/// The options of `DatabaseTool`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseOptions {
+    /// The databases in `[databases]` the tool can query. Defaults to all
+    /// of them.
+    databases: Option<Vec<String>>,
+}
+
+/// The options of `OpenApi`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenApiOptions {
+    /// The name of the API in `[apis]`.
+    api: String,
+}
+
+/// The options of `Mcp`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct McpOptions {
+    /// The name of the server in `[mcp_servers]`.
+    server: String,
+    /// If not empty, only the server's tools with these names are added.
+    #[serde(default)]
+    tools: Vec<String>,
+}
+
+/// Implements `RegisteredTool` for tools without options, built by an
+/// expression of the context.
+macro_rules! without_options {
+    ($($tool:ident => |$context:ident| $build:expr;)+) => {
+        $(
+            #[async_trait]
+            impl RegisteredTool for $tool {
+                const NAME: &'static str = stringify!($tool);
+                type Options = NoOptions;
+
+                async fn build(
+                    _options: NoOptions,
+                    _agent: &str,
+                    $context: &ToolContext<'_>,
+                ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+                    Ok(vec![Box::new($build)])
+                }
+            }
+        )+
+    };
+}
+
+without_options! {
+    CargoTool => |context| CargoTool::new(context.sandbox, context.workspace.clone())?;
+    CodeSearchTool => |context| CodeSearchTool::new(context.workspace.clone());
+    CodeWriterTool => |context| CodeWriterTool::new(context.workspace.clone());
+    DirectoryListerTool => |context| DirectoryListerTool::new(context.workspace.clone());
+    FileReaderTool => |context| FileReaderTool::new(context.workspace.clone());
+    GitTool => |context| GitTool::new(context.workspace.clone(), context.git.clone());
+    OutlineTool => |context| OutlineTool::new(context.workspace.clone());
+    RestApiTool => |context| RestApiTool::new(context.http.clone(), context.secrets.clone());
+    SemanticSearchTool => |context| {
+        let index = context
+            .index
+            .ok_or_else(|| anyhow!("SemanticSearchTool needs an [index] section"))?;
+        SemanticSearchTool::new(context.workspace.clone(), index)?
+    };
+    WebScraperTool => |context| WebScraperTool::new(context.http.clone());
+}
+
+/// Implements `RegisteredTool` for tools that run commands under the
+/// agent's command policy.
+macro_rules! with_command_policy {
+    ($($tool:ident),+) => {
+        $(
+            #[async_trait]
+            impl RegisteredTool for $tool {
+                const NAME: &'static str = stringify!($tool);
+                type Options = ProcessOptions;
+
+                async fn build(
+                    options: ProcessOptions,
+                    agent: &str,
+                    context: &ToolContext<'_>,
+                ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+                    let policy = options.policy(agent, context)?;
+                    Ok(vec![Box::new($tool::new(
+                        context.sandbox,
+                        context.workspace.clone(),
+                        policy,
+                    )?)])
+                }
+            }
+        )+
+    };
+}
+
+with_command_policy!(SystemTool, ShellSessionTool, ProcessManagerTool);
+
+/// Implements `RegisteredTool` for the memory tools.
+macro_rules! with_memory {
+    ($($tool:ident),+) => {
+        $(
+            #[async_trait]
+            impl RegisteredTool for $tool {
+                const NAME: &'static str = stringify!($tool);
+                type Options = MemoryOptions;
+
+                async fn build(
+                    options: MemoryOptions,
+                    _agent: &str,
+                    context: &ToolContext<'_>,
+                ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+                    Ok(vec![Box::new($tool::new(options.memory(Self::NAME, context)?))])
+                }
+            }
+        )+
+    };
+}
+
+with_memory!(RememberTool, RecallTool, ForgetTool);
+
+#[async_trait]
+impl RegisteredTool for DatabaseTool {
+    const NAME: &'static str = "DatabaseTool";
+    type Options = DatabaseOptions;
+
+    async fn build(
+        options: DatabaseOptions,
+        _agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        if context.databases.is_empty() {
+            return Err(anyhow!("DatabaseTool needs a [databases] section"));
+        }
+        let names = options
+            .databases
+            .unwrap_or_else(|| context.databases.keys().cloned().collect());
+        let mut databases = Vec::new();
+        for name in &names {
+            databases.push(
+                context.databases.get_key_value(name).ok_or_else(|| {
+                    anyhow!("there is no database named {:?} in [databases]", name)
+                })?,
+            );
+        }
+        Ok(vec![Box::new(DatabaseTool::new(databases).await?)])
+    }
+}
+
+/// `OpenApi` adds a tool for each operation of an API in `[apis]`.
+#[async_trait]
+impl RegisteredTool for OperationTool {
+    const NAME: &'static str = "OpenApi";
+    type Options = OpenApiOptions;
+
+    async fn build(
+        options: OpenApiOptions,
+        _agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        let api = context
+            .apis
+            .get(&options.api)
+            .ok_or_else(|| anyhow!("there is no API named {:?} in [apis]", options.api))?;
+        let operations = openapi::load(&options.api, api, &context.http, &context.secrets).await?;
+        Ok(operations
+            .into_iter()
+            .map(|operation| Box::new(operation) as Box<dyn Tool + Send + Sync>)
+            .collect())
+    }
+}
+
+/// `Mcp` adds a tool for each tool of a server in `[mcp_servers]`, and one
+/// that reads its resources.
+#[async_trait]
+impl RegisteredTool for McpTool {
+    const NAME: &'static str = "Mcp";
+    type Options = McpOptions;
+
+    async fn build(
+        options: McpOptions,
+        _agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        let config = context.mcp_servers.get(&options.server).ok_or_else(|| {
+            anyhow!(
+                "there is no MCP server named {:?} in [mcp_servers]",
+                options.server
+            )
+        })?;
+        let client = McpClient::connect(
+            &options.server,
+            config.clone(),
+            context.workspace.root().to_path_buf(),
+            context.http.clone(),
+            context.secrets.clone(),
+        )
+        .await?;
+        mcp::load(client, &options.tools).await
+    }
+}
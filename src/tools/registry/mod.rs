@@ -0,0 +1,280 @@
+//! The tools agents can be given in the configuration file, by name, and
+//! the factories that build them from their options.
+
+mod builtin;
+
+use super::{
+    command_policy::CommandPolicyConfig, database::DatabaseConfig, git::GitConfig,
+    http::HttpClient, mcp::McpServerConfig, rest_api::openapi::ApiConfig, sandbox::SandboxConfig,
+    secrets::Secrets, semantic_search::IndexConfig, workspace::Workspace, Tool,
+};
+use crate::memory::Memory;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, collections::HashMap, marker::PhantomData};
+
+/// What tools are built from: the workspace they work in and the sections
+/// of the configuration file they use.
+pub struct ToolContext<'a> {
+    pub workspace: Workspace,
+    pub sandbox: &'a SandboxConfig,
+    /// Command policies, keyed by agent name.
+    pub command_policies: &'a HashMap<String, CommandPolicyConfig>,
+    pub http: HttpClient,
+    pub secrets: Secrets,
+    pub apis: &'a HashMap<String, ApiConfig>,
+    pub databases: &'a HashMap<String, DatabaseConfig>,
+    pub git: &'a GitConfig,
+    pub mcp_servers: &'a HashMap<String, McpServerConfig>,
+    /// The long-term memory, if `[memory]` is configured.
+    pub memory: Option<Memory>,
+    /// The semantic index, if `[index]` is configured.
+    pub index: Option<&'a IndexConfig>,
+}
+
+/// A tool that agents can be given by name in the configuration file.
+///
+/// Implement it for a tool, then add the tool to a `ToolRegistry` with
+/// `register`, or with the `register_tools!` macro in a plugin crate.
+#[async_trait]
+pub trait RegisteredTool {
+    /// The name the configuration file refers to the tool by.
+    const NAME: &'static str;
+
+    /// The options the tool takes, from the tool's table in an agent's
+    /// `tools`. They are deserialized from an empty table when the tool is
+    /// listed by name, and should deny unknown fields so that typos are
+    /// reported.
+    type Options: DeserializeOwned + Send;
+
+    /// Builds the tool for an agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The tool's options.
+    /// * `agent` - The name of the agent the tool is for.
+    /// * `context` - The workspace and configuration to build it from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tools, which is usually one, or an error if the
+    /// options do not work, such as a section they need that is missing.
+    async fn build(
+        options: Self::Options,
+        agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>>;
+}
+
+/// Builds a registered tool from options that are not yet deserialized.
+#[async_trait]
+trait ToolFactory: Send + Sync {
+    async fn build(
+        &self,
+        options: Map<String, Value>,
+        agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>>;
+}
+
+struct Factory<T>(PhantomData<fn() -> T>);
+
+#[async_trait]
+impl<T: RegisteredTool> ToolFactory for Factory<T> {
+    async fn build(
+        &self,
+        options: Map<String, Value>,
+        agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        let options: T::Options = serde_json::from_value(Value::Object(options))
+            .map_err(|e| anyhow!("invalid options for {}: {}", T::NAME, e))?;
+        T::build(options, agent, context).await
+    }
+}
+
+/// The tools that can be referred to by name, with their factories.
+#[derive(Default)]
+pub struct ToolRegistry {
+    factories: BTreeMap<&'static str, Box<dyn ToolFactory>>,
+}
+
+impl ToolRegistry {
+    /// Creates a registry with the tools of this crate.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        builtin::register(&mut registry);
+        registry
+    }
+
+    /// Adds a tool, replacing any registered under the same name.
+    pub fn register<T: RegisteredTool + 'static>(&mut self) -> &mut Self {
+        self.factories
+            .insert(T::NAME, Box::new(Factory::<T>(PhantomData)));
+        self
+    }
+
+    /// Returns the names of the registered tools, in order.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.factories.keys().copied().collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Builds a tool for an agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The tool's registered name.
+    /// * `options` - The tool's options, as written in the configuration.
+    /// * `agent` - The name of the agent the tool is for.
+    /// * `context` - The workspace and configuration to build it from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tools, or an error if no tool has the name, the
+    /// options are invalid or the tool cannot be built.
+    pub async fn build(
+        &self,
+        name: &str,
+        options: Map<String, Value>,
+        agent: &str,
+        context: &ToolContext<'_>,
+    ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+        let factory = self
+            .factories
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown tool {:?}", name))?;
+        factory.build(options, agent, context).await
+    }
+}
+
+/// Defines a function that adds tools to a `ToolRegistry`, for a plugin
+/// crate to export.
+///
+/// ```ignore
+/// app::register_tools!(pub fn register => WeatherTool, TicketTool);
+/// ```
+#[macro_export]
+macro_rules! register_tools {
+    ($vis:vis fn $name:ident => $($tool:ty),+ $(,)?) => {
+        $vis fn $name(registry: &mut $crate::tools::registry::ToolRegistry) {
+            $(registry.register::<$tool>();)+
+        }
+    };
+}
+
+/// Defines the `main` function of a binary that runs this crate's command
+/// line with the built-in tools, plus those added by the given `register`
+/// functions.
+///
+/// ```ignore
+/// app::main!(weather_tools::register, ticket_tools::register);
+/// ```
+#[macro_export]
+macro_rules! main {
+    ($($register:path),* $(,)?) => {
+        fn main() {
+            #[allow(unused_mut)]
+            let mut registry = $crate::tools::registry::ToolRegistry::builtin();
+            $($register(&mut registry);)*
+            $crate::cli::main(registry)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{http::HttpConfig, workspace::Workspace};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    struct Greeter(String);
+
+    #[async_trait]
+    impl Tool for Greeter {
+        fn name(&self) -> &str {
+            "Greeter"
+        }
+
+        async fn execute(&self, args: &str) -> Result<String> {
+            Ok(format!("{}, {}!", self.0, args))
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct GreeterOptions {
+        greeting: String,
+    }
+
+    #[async_trait]
+    impl RegisteredTool for Greeter {
+        const NAME: &'static str = "Greeter";
+        type Options = GreeterOptions;
+
+        async fn build(
+            options: GreeterOptions,
+            _agent: &str,
+            _context: &ToolContext<'_>,
+        ) -> Result<Vec<Box<dyn Tool + Send + Sync>>> {
+            Ok(vec![Box::new(Greeter(options.greeting))])
+        }
+    }
+
+    register_tools!(fn register => Greeter);
+
+    #[tokio::test]
+    async fn test_register_and_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let (sandbox, command_policies, apis, databases, git, mcp_servers) = Default::default();
+        let context = ToolContext {
+            workspace: Workspace::new(dir.path()).unwrap(),
+            sandbox: &sandbox,
+            command_policies: &command_policies,
+            http: HttpClient::new(&HttpConfig::default()).unwrap(),
+            secrets: Secrets::new(&HashMap::new()).unwrap(),
+            apis: &apis,
+            databases: &databases,
+            git: &git,
+            mcp_servers: &mcp_servers,
+            memory: None,
+            index: None,
+        };
+
+        let mut registry = ToolRegistry::builtin();
+        assert!(!registry.contains("Greeter"));
+        register(&mut registry);
+        assert!(registry.names().contains(&"Greeter") && registry.contains("FileReaderTool"));
+
+        let options = |value: Value| value.as_object().unwrap().clone();
+        let tools = registry
+            .build(
+                "Greeter",
+                options(json!({ "greeting": "Hello" })),
+                "Agent",
+                &context,
+            )
+            .await
+            .unwrap();
+        assert_eq!(tools[0].execute("world").await.unwrap(), "Hello, world!");
+
+        let error = |name: &'static str, value: Value| {
+            let build = registry.build(name, options(value), "Agent", &context);
+            async move { build.await.err().unwrap().to_string() }
+        };
+        assert!(error("Greeter", json!({ "greting": "Hi" }))
+            .await
+            .starts_with("invalid options for Greeter: unknown field `greting`"));
+        assert_eq!(error("Greet", json!({})).await, "unknown tool \"Greet\"");
+        assert_eq!(
+            error("RecallTool", json!({})).await,
+            "RecallTool needs a [memory] section"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use crate::tools::http::HttpClient;
+use crate::tools::secrets::{redact, Secrets};
+use crate::tools::Tool;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub mod openapi;
+
+/// The maximum number of characters of a response body returned to the
+/// model.
+const MAX_BODY_CHARS: usize = 20_000;
+
+/// A request to an API, built by `RestApiTool` or by an OpenAPI operation.
+struct ApiRequest {
+    method: Method,
+    url: Url,
+    headers: BTreeMap<String, String>,
+    query: Vec<(String, String)>,
+    body: Option<Value>,
+    /// The name of the secret to authenticate with.
+    auth: Option<String>,
+}
+
+/// The response returned to the model.
+#[derive(Serialize)]
+struct ApiResponse {
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    /// The parsed body if it is JSON, otherwise the body as a string.
+    body: Value,
+}
+
+/// Sends a request, attaching its secret, and describes the response as
+/// JSON.
+///
+/// # Returns
+///
+/// A `Result` with the response, whatever its status, or an error if the
+/// request is blocked or fails.
+async fn send(client: &HttpClient, secrets: &Secrets, request: ApiRequest) -> Result<String> {
+    let mut builder = client.request(request.method, request.url.clone())?;
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if !request.query.is_empty() {
+        builder = builder.query(&request.query);
+    }
+    if let Some(body) = &request.body {
+        builder = builder.json(body);
+    }
+    let mut redacted = Vec::new();
+    if let Some(auth) = &request.auth {
+        (builder, redacted) = secrets.apply(auth, &request.url, builder)?;
+    }
+
+    let response = client.send(builder).await?;
+    let text = redact(&response.body, &redacted);
+    let is_json = response
+        .content_type
+        .as_deref()
+        .is_some_and(|content_type| content_type.contains("json"));
+    let body = match text.char_indices().nth(MAX_BODY_CHARS) {
+        Some((cut, _)) => Value::String(format!(
+            "{}\n\n... [{} more characters]",
+            &text[..cut],
+            text[cut..].chars().count()
+        )),
+        None if is_json => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        None => Value::String(text),
+    };
+    Ok(serde_json::to_string_pretty(&ApiResponse {
+        status: response.status.as_u16(),
+        content_type: response.content_type,
+        body,
+    })?)
+}
+
+/// Converts an argument to query parameter or header values. Arrays give
+/// one value per element, and `null` gives none.
+fn param_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::Null => Vec::new(),
+        Value::String(value) => vec![value.clone()],
+        Value::Array(values) => values.iter().flat_map(param_values).collect(),
+        value => vec![value.to_string()],
+    }
+}
+
+/// The arguments accepted by `RestApiTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is fetched as the `url` with a `GET` request.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RequestArgs {
+    /// The URL to request.
+    url: String,
+    /// The HTTP method.
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Query parameters added to the URL.
+    #[serde(default)]
+    query: BTreeMap<String, Value>,
+    /// A JSON request body.
+    #[serde(default)]
+    body: Option<Value>,
+    /// The name of a configured secret to authenticate with.
+    #[serde(default)]
+    auth: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl RequestArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for RestApiTool: {}", e));
+        }
+        Ok(Self {
+            url: args.to_string(),
+            method: default_method(),
+            headers: BTreeMap::new(),
+            query: BTreeMap::new(),
+            body: None,
+            auth: None,
+        })
+    }
+}
+
+/// A tool for calling HTTP APIs.
+///
+/// The model chooses the method, URL, headers, query and JSON body, and can
+/// authenticate by naming one of the configured secrets, whose value is
+/// attached to the request without being shown to the model.
+pub struct RestApiTool {
+    client: HttpClient,
+    secrets: Secrets,
+}
+
+impl RestApiTool {
+    /// Creates a new `RestApiTool`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client to send requests with.
+    /// * `secrets` - The secrets the model may authenticate with.
+    pub fn new(client: HttpClient, secrets: Secrets) -> Self {
+        Self { client, secrets }
+    }
+}
+
+#[async_trait]
+impl Tool for RestApiTool {
+    /// Returns the name of the tool, "RestApiTool".
+    fn name(&self) -> &str {
+        "RestApiTool"
+    }
+
+    fn description(&self) -> String {
+        let mut description = "Sends an HTTP request. Args: a JSON object with the `url`, and \
+            optionally the `method` (default GET), `headers`, `query` parameters, a JSON `body`, \
+            and `auth` with the name of a secret to authenticate with."
+            .to_string();
+        if !self.secrets.describe().is_empty() {
+            description.push_str(&format!(" Secrets: {}.", self.secrets.describe()));
+        }
+        description
+    }
+
+    /// Sends the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either a URL to `GET`, or a JSON object with the `url`
+    ///   and optionally the `method`, `headers`, `query`, `body` and `auth`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with a JSON object holding the response's `status`,
+    /// `content_type` and `body`, or an error if the request is blocked or
+    /// fails. Error statuses are returned rather than treated as errors.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = RequestArgs::parse(args)?;
+        let method = Method::from_bytes(args.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| anyhow!("Invalid HTTP method: {}", args.method))?;
+        let query = args
+            .query
+            .iter()
+            .flat_map(|(name, value)| {
+                param_values(value)
+                    .into_iter()
+                    .map(move |value| (name.clone(), value))
+            })
+            .collect();
+        let request = ApiRequest {
+            method,
+            url: Url::parse(&args.url)?,
+            headers: args.headers,
+            query,
+            body: args.body,
+            auth: args.auth,
+        };
+        send(&self.client, &self.secrets, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{
+        http::HttpConfig,
+        secrets::{SecretConfig, SecretKind},
+        test_server::{echo, ok, serve},
+    };
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_secrets_are_attached_and_redacted() {
+        let (base, requests) = serve(vec![
+            echo("/echo"),
+            ok("/items", "application/json", r#"{"items": [1, 2]}"#),
+        ])
+        .await;
+        std::env::set_var("REST_API_TEST_TOKEN", "s3cret-token");
+        std::env::set_var("REST_API_TEST_KEY", "s3cret-key");
+        let secrets = Secrets::new(&HashMap::from([
+            (
+                "token".to_string(),
+                SecretConfig {
+                    env: "REST_API_TEST_TOKEN".to_string(),
+                    kind: SecretKind::Bearer,
+                    name: None,
+                    hosts: vec!["127.0.0.1".to_string()],
+                },
+            ),
+            (
+                "key".to_string(),
+                SecretConfig {
+                    env: "REST_API_TEST_KEY".to_string(),
+                    kind: SecretKind::Query,
+                    name: Some("api_key".to_string()),
+                    hosts: vec!["127.0.0.1".to_string()],
+                },
+            ),
+            (
+                "elsewhere".to_string(),
+                SecretConfig {
+                    env: "REST_API_TEST_TOKEN".to_string(),
+                    kind: SecretKind::Bearer,
+                    name: None,
+                    hosts: vec!["api.example.com".to_string()],
+                },
+            ),
+        ]))
+        .unwrap();
+        let client = HttpClient::new(&HttpConfig {
+            allow_private_networks: true,
+            ..HttpConfig::default()
+        })
+        .unwrap();
+        let tool = RestApiTool::new(client, secrets);
+
+        let args = serde_json::json!({
+            "url": base.join("/echo").unwrap(),
+            "method": "post",
+            "body": {"name": "widget"},
+            "auth": "token",
+        });
+        let output: Value =
+            serde_json::from_str(&tool.execute(&args.to_string()).await.unwrap()).unwrap();
+        assert_eq!(output["status"], 200);
+        let echoed = output["body"].as_str().unwrap();
+        assert!(echoed.starts_with("POST /echo"));
+        assert!(echoed.contains("authorization: Bearer [REDACTED]"));
+        assert!(echoed.contains(r#"{"name":"widget"}"#));
+        assert!(!echoed.contains("s3cret"));
+
+        let args = serde_json::json!({
+            "url": base.join("/items").unwrap(),
+            "query": {"page": 2},
+            "auth": "key",
+        });
+        let output: Value =
+            serde_json::from_str(&tool.execute(&args.to_string()).await.unwrap()).unwrap();
+        assert_eq!(output["body"]["items"], serde_json::json!([1, 2]));
+        assert_eq!(
+            requests.lock().unwrap().last().unwrap(),
+            "/items?page=2&api_key=s3cret-key"
+        );
+
+        let args = serde_json::json!({"url": base.join("/echo").unwrap(), "auth": "elsewhere"});
+        assert!(tool.execute(&args.to_string()).await.is_err());
+    }
+}
@@ -0,0 +1,579 @@
+use super::{param_values, send, ApiRequest};
+use crate::tools::http::HttpClient;
+use crate::tools::secrets::Secrets;
+use crate::tools::Tool;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Method, Url};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+
+/// An `[apis.<name>]` section of the configuration file, which turns the
+/// operations of an OpenAPI 3 document into tools.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApiConfig {
+    /// The path or URL of the OpenAPI document, in JSON or YAML.
+    pub spec: String,
+    /// The URL the operation paths are relative to. Defaults to the first
+    /// server in the document.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// The name of the secret every operation authenticates with.
+    #[serde(default)]
+    pub auth: Option<String>,
+    /// If not empty, only the operations with these `operationId`s become
+    /// tools.
+    #[serde(default)]
+    pub operations: Vec<String>,
+}
+
+/// Where an operation parameter goes in the request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Location {
+    Path,
+    Query,
+    Header,
+}
+
+/// A parameter of an operation.
+#[derive(Debug, Clone)]
+struct Parameter {
+    name: String,
+    location: Location,
+    required: bool,
+    /// A short description of the parameter's schema, e.g. `integer`.
+    schema: String,
+    description: Option<String>,
+}
+
+/// The JSON request body of an operation.
+#[derive(Debug, Clone)]
+struct RequestBody {
+    required: bool,
+    schema: String,
+}
+
+/// A tool for one operation of an OpenAPI document.
+///
+/// The model passes the operation's parameters by name, plus its request
+/// `body`, and the tool builds the request: path parameters are substituted
+/// into the path, and the others go in the query string or headers.
+pub struct OperationTool {
+    name: String,
+    summary: String,
+    method: Method,
+    base_url: Url,
+    path: String,
+    parameters: Vec<Parameter>,
+    body: Option<RequestBody>,
+    auth: Option<String>,
+    client: HttpClient,
+    secrets: Secrets,
+}
+
+/// Loads an OpenAPI document and creates a tool for each of its
+/// operations.
+///
+/// Tools are named after the API and the operation, e.g. `petstore_listPets`.
+///
+/// # Arguments
+///
+/// * `name` - The name of the API in the configuration file.
+/// * `config` - The API's configuration.
+/// * `client` - The client to fetch the document and send requests with.
+/// * `secrets` - The secrets, which must include the API's `auth`.
+///
+/// # Returns
+///
+/// A `Result` with the tools, or an error if the document cannot be read
+/// or parsed, or has no usable base URL.
+pub async fn load(
+    name: &str,
+    config: &ApiConfig,
+    client: &HttpClient,
+    secrets: &Secrets,
+) -> Result<Vec<OperationTool>> {
+    let (text, spec_url) = match Url::parse(&config.spec) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            let response = client.get(url.clone()).await?.error_for_status()?;
+            (response.body, Some(url))
+        }
+        _ => (std::fs::read_to_string(&config.spec)?, None),
+    };
+    let spec: Value = serde_yaml::from_str(&text)
+        .map_err(|e| anyhow!("Invalid OpenAPI document {}: {}", config.spec, e))?;
+    if let Some(auth) = &config.auth {
+        if !secrets.contains(auth) {
+            return Err(anyhow!("Unknown secret `{}`", auth));
+        }
+    }
+
+    let server = config.base_url.clone().or_else(|| {
+        let url = spec.pointer("/servers/0/url")?.as_str()?;
+        Some(url.to_string())
+    });
+    let base_url = match (server, &spec_url) {
+        (Some(server), Some(spec_url)) => spec_url.join(&server)?,
+        (Some(server), None) => Url::parse(&server)
+            .map_err(|e| anyhow!("Invalid base URL {} for {}: {}", server, name, e))?,
+        (None, _) => return Err(anyhow!("The {} API needs a `base_url`", name)),
+    };
+
+    let mut tools = Vec::new();
+    let mut names = HashSet::new();
+    let paths = spec.get("paths").and_then(Value::as_object);
+    for (path, item) in paths.into_iter().flatten() {
+        let item = resolve(&spec, item);
+        let shared = item.get("parameters");
+        for method in ["get", "put", "post", "delete", "options", "head", "patch"] {
+            let Some(operation) = item.get(method) else {
+                continue;
+            };
+            let operation_id = operation.get("operationId").and_then(Value::as_str);
+            if !config.operations.is_empty()
+                && !operation_id.is_some_and(|id| config.operations.iter().any(|o| o == id))
+            {
+                continue;
+            }
+            let tool_name = format!(
+                "{}_{}",
+                name,
+                sanitize(
+                    &operation_id.map_or_else(|| format!("{} {}", method, path), String::from)
+                )
+            );
+            if !names.insert(tool_name.clone()) {
+                return Err(anyhow!(
+                    "Duplicate operation {} in {}",
+                    tool_name,
+                    config.spec
+                ));
+            }
+            tools.push(OperationTool {
+                name: tool_name,
+                summary: ["summary", "description"]
+                    .iter()
+                    .find_map(|key| operation.get(*key)?.as_str())
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                method: Method::from_bytes(method.to_ascii_uppercase().as_bytes())?,
+                base_url: base_url.clone(),
+                path: path.clone(),
+                parameters: parameters(&spec, shared, operation.get("parameters")),
+                body: request_body(&spec, operation.get("requestBody")),
+                auth: config.auth.clone(),
+                client: client.clone(),
+                secrets: secrets.clone(),
+            });
+        }
+    }
+    Ok(tools)
+}
+
+/// Follows a local `$ref` such as `#/components/schemas/Pet`, returning the
+/// value itself if it is not a reference or the reference is not found.
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    let mut value = value;
+    // Bounded, in case of references to themselves.
+    for _ in 0..8 {
+        let Some(pointer) = value.get("$ref").and_then(Value::as_str) else {
+            break;
+        };
+        match pointer.strip_prefix('#').and_then(|p| spec.pointer(p)) {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Turns an operation ID or path into a tool name, keeping letters, digits
+/// and underscores.
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Collects an operation's parameters, including those shared by its path.
+/// The operation's own take precedence, and cookie parameters are left out.
+fn parameters(spec: &Value, shared: Option<&Value>, own: Option<&Value>) -> Vec<Parameter> {
+    let mut parameters: Vec<Parameter> = Vec::new();
+    let all = [shared, own]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_array);
+    for parameter in all.flatten() {
+        let parameter = resolve(spec, parameter);
+        let (Some(name), Some(location)) = (
+            parameter.get("name").and_then(Value::as_str),
+            parameter.get("in").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let location = match location {
+            "path" => Location::Path,
+            "query" => Location::Query,
+            "header" => Location::Header,
+            _ => continue,
+        };
+        parameters.retain(|p| !(p.name == name && p.location == location));
+        parameters.push(Parameter {
+            name: name.to_string(),
+            location,
+            required: location == Location::Path
+                || parameter.get("required").and_then(Value::as_bool) == Some(true),
+            schema: parameter
+                .get("schema")
+                .map_or_else(|| "string".to_string(), |schema| describe(spec, schema, 0)),
+            description: parameter
+                .get("description")
+                .and_then(Value::as_str)
+                .map(|d| d.trim().to_string()),
+        });
+    }
+    parameters
+}
+
+/// Reads an operation's request body, if it accepts JSON.
+fn request_body(spec: &Value, body: Option<&Value>) -> Option<RequestBody> {
+    let body = resolve(spec, body?);
+    let content = body.get("content")?.as_object()?;
+    let (_, media) = content
+        .iter()
+        .find(|(media_type, _)| media_type.contains("json"))?;
+    Some(RequestBody {
+        required: body.get("required").and_then(Value::as_bool) == Some(true),
+        schema: media
+            .get("schema")
+            .map_or_else(|| "any".to_string(), |schema| describe(spec, schema, 0)),
+    })
+}
+
+/// Describes a JSON schema compactly for the prompt, e.g.
+/// `{id: integer, tags?: [string]}`, where `?` marks optional properties.
+/// Nesting beyond a few levels is abbreviated.
+fn describe(spec: &Value, schema: &Value, depth: usize) -> String {
+    let schema = resolve(spec, schema);
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return values
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(options) = schema.get(key).and_then(Value::as_array) {
+            return options
+                .iter()
+                .map(|option| describe(spec, option, depth + 1))
+                .collect::<Vec<_>>()
+                .join(" | ");
+        }
+    }
+    let kind = schema.get("type").and_then(Value::as_str);
+    match kind {
+        Some("array") => match schema.get("items") {
+            Some(items) => format!("[{}]", describe(spec, items, depth + 1)),
+            None => "array".to_string(),
+        },
+        Some("object") | None if schema.get("properties").is_some() => {
+            if depth >= 3 {
+                return "object".to_string();
+            }
+            let required: HashSet<&str> = schema
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|names| names.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            let empty = Map::new();
+            let properties = schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .unwrap_or(&empty);
+            let fields: Vec<String> = properties
+                .iter()
+                .map(|(name, property)| {
+                    let optional = if required.contains(name.as_str()) {
+                        ""
+                    } else {
+                        "?"
+                    };
+                    format!(
+                        "{}{}: {}",
+                        name,
+                        optional,
+                        describe(spec, property, depth + 1)
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Some(kind) => kind.to_string(),
+        None => "any".to_string(),
+    }
+}
+
+/// Substitutes the path parameters into a path template, percent-encoding
+/// their values.
+fn fill_path(base: &Url, template: &str, values: &BTreeMap<String, String>) -> Result<Url> {
+    let mut url = base.clone();
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| anyhow!("Invalid base URL {}", base))?;
+        segments.pop_if_empty();
+        for segment in template.split('/').filter(|segment| !segment.is_empty()) {
+            let mut filled = segment.to_string();
+            for (name, value) in values {
+                filled = filled.replace(&format!("{{{}}}", name), value);
+            }
+            segments.push(&filled);
+        }
+    }
+    Ok(url)
+}
+
+#[async_trait]
+impl Tool for OperationTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> String {
+        let mut arguments: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|parameter| {
+                let location = match parameter.location {
+                    Location::Path => "path",
+                    Location::Query => "query",
+                    Location::Header => "header",
+                };
+                let required = if parameter.required { ", required" } else { "" };
+                let mut argument = format!(
+                    "`{}` ({} {}{})",
+                    parameter.name, location, parameter.schema, required
+                );
+                if let Some(description) = &parameter.description {
+                    argument.push_str(&format!(": {}", description));
+                }
+                argument
+            })
+            .collect();
+        if let Some(body) = &self.body {
+            let required = if body.required { ", required" } else { "" };
+            arguments.push(format!("`body` (JSON {}{})", body.schema, required));
+        }
+
+        let mut description = format!("{} {}", self.method, self.path);
+        if !self.summary.is_empty() {
+            description.push_str(&format!(": {}", self.summary));
+        }
+        if arguments.is_empty() {
+            description.push_str(" Takes no arguments.");
+        } else {
+            description.push_str(&format!(
+                " Args: a JSON object with {}.",
+                arguments.join(", ")
+            ));
+        }
+        description
+    }
+
+    /// Calls the operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - A JSON object with the operation's parameters by name and
+    ///   its request `body`. May be empty if the operation takes no required
+    ///   arguments.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with a JSON object holding the response's `status`,
+    /// `content_type` and `body`, or an error if an argument is missing or
+    /// unknown, or the request fails.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = args.trim();
+        let mut args: Map<String, Value> = if args.is_empty() {
+            Map::new()
+        } else {
+            serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for {}: {}", self.name, e))?
+        };
+
+        let body = args.remove("body");
+        if body.is_some() && self.body.is_none() {
+            return Err(anyhow!("{} does not take a body", self.name));
+        }
+        if body.is_none() && self.body.as_ref().is_some_and(|body| body.required) {
+            return Err(anyhow!("{} requires a body", self.name));
+        }
+        if let Some(unknown) = args
+            .keys()
+            .find(|key| !self.parameters.iter().any(|p| &p.name == *key))
+        {
+            return Err(anyhow!("Unknown argument for {}: {}", self.name, unknown));
+        }
+
+        let mut path_values = BTreeMap::new();
+        let mut headers = BTreeMap::new();
+        let mut query = Vec::new();
+        for parameter in &self.parameters {
+            let values = args
+                .get(&parameter.name)
+                .map(param_values)
+                .unwrap_or_default();
+            if values.is_empty() {
+                if parameter.required {
+                    return Err(anyhow!(
+                        "Missing argument for {}: {}",
+                        self.name,
+                        parameter.name
+                    ));
+                }
+                continue;
+            }
+            match parameter.location {
+                Location::Path => {
+                    path_values.insert(parameter.name.clone(), values.join(","));
+                }
+                Location::Header => {
+                    headers.insert(parameter.name.clone(), values.join(","));
+                }
+                Location::Query => query.extend(
+                    values
+                        .into_iter()
+                        .map(|value| (parameter.name.clone(), value)),
+                ),
+            }
+        }
+
+        let request = ApiRequest {
+            method: self.method.clone(),
+            url: fill_path(&self.base_url, &self.path, &path_values)?,
+            headers,
+            query,
+            body,
+            auth: self.auth.clone(),
+        };
+        send(&self.client, &self.secrets, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{
+        http::HttpConfig,
+        test_server::{ok, serve},
+    };
+
+    const SPEC: &str = r##"
+openapi: 3.0.0
+info:
+  title: Pets
+  version: "1"
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      summary: List all pets
+      parameters:
+        - $ref: "#/components/parameters/limit"
+    post:
+      operationId: createPet
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        schema:
+          type: string
+    get:
+      summary: Info for a specific pet
+components:
+  parameters:
+    limit:
+      name: limit
+      in: query
+      schema:
+        type: integer
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name:
+          type: string
+        tags:
+          type: array
+          items:
+            type: string
+"##;
+
+    #[tokio::test]
+    async fn test_operations_become_tools() {
+        let (base, requests) = serve(vec![
+            ok("/v1/pets", "application/json", "[]"),
+            ok("/v1/pets/rex%2F2", "application/json", r#"{"name": "rex"}"#),
+        ])
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let spec = dir.path().join("pets.yaml");
+        std::fs::write(&spec, SPEC).unwrap();
+        let config = ApiConfig {
+            spec: spec.to_string_lossy().into_owned(),
+            base_url: Some(base.join("/v1").unwrap().to_string()),
+            auth: None,
+            operations: Vec::new(),
+        };
+        let client = HttpClient::new(&HttpConfig {
+            allow_private_networks: true,
+            ..HttpConfig::default()
+        })
+        .unwrap();
+
+        let tools = load("pets", &config, &client, &Secrets::default())
+            .await
+            .unwrap();
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name()).collect();
+        assert_eq!(
+            names,
+            ["pets_listPets", "pets_createPet", "pets_get_pets_petId"]
+        );
+        assert_eq!(
+            tools[1].description(),
+            "POST /pets Args: a JSON object with `body` (JSON {name: string, tags?: [string]}, required)."
+        );
+        assert!(tools[2]
+            .description()
+            .contains("`petId` (path string, required)"));
+
+        tools[0].execute(r#"{"limit": 5}"#).await.unwrap();
+        let output = tools[2].execute(r#"{"petId": "rex/2"}"#).await.unwrap();
+        assert!(output.contains(r#""name": "rex""#));
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["/v1/pets?limit=5", "/v1/pets/rex%2F2"]
+        );
+
+        assert!(tools[1].execute("{}").await.is_err());
+        assert!(tools[0].execute(r#"{"limt": 5}"#).await.is_err());
+    }
+}
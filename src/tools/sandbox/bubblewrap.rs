@@ -0,0 +1,113 @@
+use super::{SandboxConfig, SANDBOX_PATH, SANDBOX_WORKSPACE, SYSTEM_PATHS};
+use crate::tools::workspace::Workspace;
+use std::{path::PathBuf, process::Stdio, time::Duration};
+use tokio::process::Command;
+
+/// Returns whether the `bwrap` binary can be run on this machine.
+pub(super) fn is_available() -> bool {
+    std::process::Command::new("bwrap")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Runs commands through bubblewrap.
+///
+/// All namespaces are unshared (the network only if it is disabled in the
+/// configuration), the system directories are mounted read-only, the
+/// workspace is mounted read-write at `/workspace`, and `/tmp` is a fresh
+/// tmpfs. Resource limits are applied with `setrlimit` before `bwrap` is
+/// executed and are inherited by the sandboxed command.
+pub struct BubblewrapSandbox {
+    workspace: Workspace,
+    network: bool,
+    read_only_paths: Vec<PathBuf>,
+    cpu_seconds: u64,
+    memory_bytes: u64,
+    wall_time: Duration,
+}
+
+impl BubblewrapSandbox {
+    pub(super) fn new(config: &SandboxConfig, workspace: Workspace) -> Self {
+        let mut read_only_paths: Vec<PathBuf> = SYSTEM_PATHS.iter().map(PathBuf::from).collect();
+        read_only_paths.extend(config.read_only_paths.iter().cloned());
+        Self {
+            workspace,
+            network: config.network,
+            read_only_paths,
+            cpu_seconds: config.limits.cpu_seconds,
+            memory_bytes: config.limits.memory_mb * 1024 * 1024,
+            wall_time: Duration::from_secs(config.limits.wall_time_seconds),
+        }
+    }
+
+    pub(super) fn command(&self, script: &str) -> Command {
+        let mut command = Command::new("bwrap");
+        command.args(["--unshare-all", "--die-with-parent", "--new-session"]);
+        if self.network {
+            command.arg("--share-net");
+        }
+        for path in &self.read_only_paths {
+            command.arg("--ro-bind-try").arg(path).arg(path);
+        }
+        command
+            .arg("--bind")
+            .arg(self.workspace.root())
+            .arg(SANDBOX_WORKSPACE)
+            .args(["--tmpfs", "/tmp", "--proc", "/proc", "--dev", "/dev"])
+            .args(["--chdir", SANDBOX_WORKSPACE])
+            .args(["--clearenv", "--setenv", "PATH", SANDBOX_PATH])
+            .args(["--setenv", "HOME", "/tmp"])
+            .args(["--", "sh", "-c", script]);
+
+        let (cpu_seconds, memory_bytes) = (self.cpu_seconds, self.memory_bytes);
+        // SAFETY: the closure only calls `setrlimit`, which is
+        // async-signal-safe.
+        unsafe {
+            command.pre_exec(move || super::apply_rlimits(cpu_seconds, memory_bytes));
+        }
+        command
+    }
+
+    pub(super) fn wall_time(&self) -> Duration {
+        self.wall_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::sandbox::{isolation, SandboxBackend};
+
+    // These only run where `bwrap` is installed.
+
+    #[tokio::test]
+    async fn test_network_is_off() {
+        if is_available() {
+            isolation::network_is_off(SandboxBackend::Bubblewrap).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writes_outside_workspace_are_denied() {
+        if is_available() {
+            isolation::writes_outside_workspace_are_denied(SandboxBackend::Bubblewrap).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_processes_are_hidden() {
+        if is_available() {
+            isolation::host_processes_are_hidden(SandboxBackend::Bubblewrap).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rlimits_apply() {
+        if is_available() {
+            isolation::rlimits_apply(SandboxBackend::Bubblewrap).await;
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use super::{SandboxConfig, SANDBOX_WORKSPACE};
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{process::Command, sync::OnceCell};
+
+/// Extra time given to the Docker client on top of the in-container
+/// timeout, to account for container start-up.
+const CLIENT_GRACE: Duration = Duration::from_secs(15);
+
+/// The `[sandbox.docker]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DockerConfig {
+    /// The image commands run in. It must provide `sh` and `timeout`.
+    pub image: String,
+    /// Reuse one long-lived container for every command in a run, so that
+    /// installed packages and files outside the workspace persist between
+    /// steps.
+    pub persistent: bool,
+    /// The number of CPUs the container may use.
+    pub cpus: f64,
+    /// The maximum number of processes in the container.
+    pub pids_limit: u64,
+    /// The user to run as, e.g. `1000:1000`. Defaults to the image's user.
+    pub user: Option<String>,
+}
+
+impl Default for DockerConfig {
+    fn default() -> Self {
+        Self {
+            image: "alpine".to_string(),
+            persistent: false,
+            cpus: 1.0,
+            pids_limit: 256,
+            user: None,
+        }
+    }
+}
+
+/// Runs commands in Docker containers.
+///
+/// The workspace is mounted read-write at `/workspace`, the network is
+/// disabled unless enabled in the configuration, and the memory, CPU, process
+/// and time limits are enforced by Docker. Each command runs in a throwaway
+/// container, or with `persistent` set, in a single container that is
+/// started on first use and removed when the sandbox is dropped.
+///
+/// This requires Docker to be installed and running on the host.
+pub struct DockerSandbox {
+    workspace: Workspace,
+    config: DockerConfig,
+    network: bool,
+    memory_mb: u64,
+    cpu_seconds: u64,
+    wall_time: Duration,
+    container: OnceCell<String>,
+}
+
+impl DockerSandbox {
+    pub(super) fn new(config: &SandboxConfig, workspace: Workspace) -> Self {
+        Self {
+            workspace,
+            config: config.docker.clone(),
+            network: config.network,
+            memory_mb: config.limits.memory_mb,
+            cpu_seconds: config.limits.cpu_seconds,
+            wall_time: Duration::from_secs(config.limits.wall_time_seconds),
+            container: OnceCell::new(),
+        }
+    }
+
+    pub(super) async fn command(&self, script: &str) -> Result<Command> {
+        let mut command = self.docker(false).await?;
+
+        // Enforce the wall-clock limit inside the container, as killing the
+        // Docker client does not stop the command it started.
+        command
+            .args(["timeout", "-s", "KILL"])
+            .arg(self.wall_time.as_secs().to_string())
+            .args(["sh", "-c", script]);
+        Ok(command)
+    }
+
+    pub(super) async fn interactive(&self, script: &str) -> Result<Command> {
+        let mut command = self.docker(true).await?;
+        command.args(["sh", "-c", script]);
+        Ok(command)
+    }
+
+    pub(super) fn wall_time(&self) -> Duration {
+        self.wall_time + CLIENT_GRACE
+    }
+
+    /// Builds a `docker run` or `docker exec` command, up to the command to
+    /// run in the container. With `interactive` set, the container's stdin
+    /// is attached.
+    async fn docker(&self, interactive: bool) -> Result<Command> {
+        let mut command = Command::new("docker");
+        if self.config.persistent {
+            let container = self
+                .container
+                .get_or_try_init(|| self.start_container())
+                .await?;
+            command.arg("exec");
+            if interactive {
+                command.arg("-i");
+            }
+            command.args(["-w", SANDBOX_WORKSPACE, container]);
+        } else {
+            command.args(["run", "--rm"]);
+            if interactive {
+                command.arg("-i");
+            }
+            self.container_args(&mut command);
+            command.arg(&self.config.image);
+        }
+        Ok(command)
+    }
+
+    /// Adds the options shared by throwaway and persistent containers.
+    fn container_args(&self, command: &mut Command) {
+        if !self.network {
+            command.args(["--network", "none"]);
+        }
+        if let Some(user) = &self.config.user {
+            command.args(["--user", user]);
+        }
+        command
+            .arg("--memory")
+            .arg(format!("{}m", self.memory_mb))
+            .arg("--cpus")
+            .arg(self.config.cpus.to_string())
+            .arg("--pids-limit")
+            .arg(self.config.pids_limit.to_string())
+            .arg("--ulimit")
+            .arg(format!("cpu={}:{}", self.cpu_seconds, self.cpu_seconds))
+            .args(["--security-opt", "no-new-privileges"])
+            .arg("-v")
+            .arg(format!(
+                "{}:{}",
+                self.workspace.root().display(),
+                SANDBOX_WORKSPACE
+            ))
+            .args(["-w", SANDBOX_WORKSPACE]);
+    }
+
+    /// Starts the long-lived container used in persistent mode.
+    async fn start_container(&self) -> Result<String> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let name = format!("agent-sandbox-{}-{}", std::process::id(), nanos);
+
+        let mut command = Command::new("docker");
+        command.args(["run", "-d", "--rm", "--name", &name]);
+        self.container_args(&mut command);
+        let output = command
+            .arg(&self.config.image)
+            .args(["sleep", "infinity"])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Failed to start sandbox container: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(name)
+    }
+}
+
+impl Drop for DockerSandbox {
+    fn drop(&mut self) {
+        if let Some(container) = self.container.get() {
+            let _ = std::process::Command::new("docker")
+                .args(["rm", "-f", container])
+                .output();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_throwaway_container_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let sandbox = DockerSandbox::new(&SandboxConfig::default(), workspace.clone());
+
+        let command = sandbox.command("ls").await.unwrap();
+        let args: Vec<_> = command
+            .as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mount = format!("{}:/workspace", workspace.root().display());
+        #[rustfmt::skip]
+        assert_eq!(
+            args,
+            [
+                "run", "--rm", "--network", "none", "--memory", "1024m", "--cpus", "1",
+                "--pids-limit", "256", "--ulimit", "cpu=60:60", "--security-opt",
+                "no-new-privileges", "-v", &mount, "-w", "/workspace", "alpine",
+                "timeout", "-s", "KILL", "120", "sh", "-c", "ls",
+            ]
+        );
+    }
+}
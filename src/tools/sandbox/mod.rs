@@ -0,0 +1,308 @@
+use crate::tools::workspace::Workspace;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::process::Command;
+
+mod bubblewrap;
+mod docker;
+#[cfg(target_os = "linux")]
+mod native;
+
+pub use docker::DockerConfig;
+
+/// The mechanism used to isolate commands run by `SystemTool`.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxBackend {
+    /// Use bubblewrap if it is installed, otherwise the native backend on
+    /// Linux, otherwise Docker.
+    #[default]
+    Auto,
+    /// Linux namespaces, seccomp and rlimits, set up in-process.
+    Native,
+    /// The `bwrap` binary from the bubblewrap project.
+    Bubblewrap,
+    /// A Docker container, configured in `[sandbox.docker]`.
+    Docker,
+}
+
+/// Resource caps applied to every sandboxed command.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// The maximum CPU time, in seconds.
+    pub cpu_seconds: u64,
+    /// The maximum address space size, in megabytes.
+    pub memory_mb: u64,
+    /// The maximum wall-clock time, in seconds, before the command is killed.
+    /// This also applies when the `sandbox` feature is disabled.
+    pub wall_time_seconds: u64,
+    /// The maximum number of bytes of stdout and of stderr returned to the
+    /// model. Longer output keeps its beginning and end.
+    pub max_output_bytes: usize,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            cpu_seconds: 60,
+            memory_mb: 1024,
+            wall_time_seconds: 120,
+            max_output_bytes: 32 * 1024,
+        }
+    }
+}
+
+/// The `[sandbox]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// The backend to use.
+    pub backend: SandboxBackend,
+    /// Whether sandboxed commands may access the network.
+    pub network: bool,
+    /// Extra host paths to expose read-only inside the sandbox, e.g. a
+    /// toolchain directory. Not used by the Docker backend.
+    pub read_only_paths: Vec<PathBuf>,
+    /// Resource caps for each command.
+    pub limits: ResourceLimits,
+    /// Options specific to the Docker backend.
+    pub docker: DockerConfig,
+}
+
+/// Host paths that are always exposed read-only by the native and
+/// bubblewrap backends, so that a shell and the usual tools are available.
+const SYSTEM_PATHS: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc"];
+
+/// The path the workspace is mounted at inside the sandbox.
+const SANDBOX_WORKSPACE: &str = "/workspace";
+
+/// The `PATH` given to sandboxed commands.
+const SANDBOX_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Builds the commands that `SystemTool` runs.
+///
+/// When the `sandbox` feature is disabled, commands run directly on the host
+/// in the workspace root, subject only to the wall-clock limit. Otherwise
+/// they run through the configured backend, with the workspace mounted
+/// read-write, the network blocked unless `network` is set, and the
+/// configured resource limits applied.
+pub enum Sandbox {
+    Host {
+        workspace: Workspace,
+        wall_time: Duration,
+    },
+    #[cfg(target_os = "linux")]
+    Native(native::NativeSandbox),
+    Bubblewrap(bubblewrap::BubblewrapSandbox),
+    Docker(docker::DockerSandbox),
+}
+
+impl Sandbox {
+    /// Creates a new `Sandbox` from the configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the sandbox, or an error if the requested backend is
+    /// not available on this machine.
+    pub fn new(config: &SandboxConfig, workspace: Workspace) -> Result<Self> {
+        if !cfg!(feature = "sandbox") {
+            return Ok(Self::Host {
+                workspace,
+                wall_time: Duration::from_secs(config.limits.wall_time_seconds),
+            });
+        }
+
+        let backend = match config.backend {
+            SandboxBackend::Auto if bubblewrap::is_available() => SandboxBackend::Bubblewrap,
+            SandboxBackend::Auto if cfg!(target_os = "linux") => SandboxBackend::Native,
+            SandboxBackend::Auto => SandboxBackend::Docker,
+            backend => backend,
+        };
+
+        match backend {
+            #[cfg(target_os = "linux")]
+            SandboxBackend::Native => {
+                Ok(Self::Native(native::NativeSandbox::new(config, workspace)?))
+            }
+            #[cfg(not(target_os = "linux"))]
+            SandboxBackend::Native => Err(anyhow!("The native sandbox is only supported on Linux")),
+            SandboxBackend::Bubblewrap => {
+                if !bubblewrap::is_available() {
+                    return Err(anyhow!(
+                        "The bubblewrap sandbox requires `bwrap` on the PATH"
+                    ));
+                }
+                Ok(Self::Bubblewrap(bubblewrap::BubblewrapSandbox::new(
+                    config, workspace,
+                )))
+            }
+            SandboxBackend::Docker => {
+                Ok(Self::Docker(docker::DockerSandbox::new(config, workspace)))
+            }
+            SandboxBackend::Auto => unreachable!("resolved above"),
+        }
+    }
+
+    /// Builds a command that runs `script` with `sh -c` inside the sandbox.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the command, or an error if the sandbox could not be
+    /// prepared, e.g. because a persistent container failed to start.
+    pub async fn command(&self, script: &str) -> Result<Command> {
+        match self {
+            Self::Host { workspace, .. } => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(script).current_dir(workspace.root());
+                Ok(command)
+            }
+            #[cfg(target_os = "linux")]
+            Self::Native(sandbox) => Ok(sandbox.command(script)),
+            Self::Bubblewrap(sandbox) => Ok(sandbox.command(script)),
+            Self::Docker(sandbox) => sandbox.command(script).await,
+        }
+    }
+
+    /// Builds a command that runs `script` with `sh -c` inside the sandbox
+    /// for as long as it takes, with its standard input attached.
+    ///
+    /// Unlike `command`, this is not subject to the wall-clock limit, so it
+    /// suits long-lived shells and background processes; callers are
+    /// expected to stop them.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the command, or an error if the sandbox could not be
+    /// prepared.
+    pub async fn interactive(&self, script: &str) -> Result<Command> {
+        match self {
+            Self::Host { .. } => self.command(script).await,
+            #[cfg(target_os = "linux")]
+            Self::Native(sandbox) => Ok(sandbox.command(script)),
+            Self::Bubblewrap(sandbox) => Ok(sandbox.command(script)),
+            Self::Docker(sandbox) => sandbox.interactive(script).await,
+        }
+    }
+
+    /// Returns the wall-clock limit for a single command.
+    pub fn wall_time(&self) -> Duration {
+        match self {
+            Self::Host { wall_time, .. } => *wall_time,
+            #[cfg(target_os = "linux")]
+            Self::Native(sandbox) => sandbox.wall_time(),
+            Self::Bubblewrap(sandbox) => sandbox.wall_time(),
+            Self::Docker(sandbox) => sandbox.wall_time(),
+        }
+    }
+}
+
+/// Applies the CPU and memory limits to the current process.
+///
+/// This is called between `fork` and `exec`, so it only makes raw system
+/// calls and does not allocate.
+fn apply_rlimits(cpu_seconds: u64, memory_bytes: u64) -> std::io::Result<()> {
+    let limits = [
+        (libc::RLIMIT_CPU, cpu_seconds),
+        (libc::RLIMIT_AS, memory_bytes),
+    ];
+    for (resource, value) in limits {
+        let limit = libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: value as libc::rlim_t,
+        };
+        // SAFETY: `limit` is a valid, initialized `rlimit` struct.
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the native and bubblewrap backends isolate commands, shared
+/// by their tests.
+#[cfg(test)]
+pub(super) mod isolation {
+    use super::*;
+
+    /// Runs `script` in a sandbox of `backend` over `workspace`, and
+    /// returns its standard output.
+    async fn run(
+        backend: SandboxBackend,
+        config: SandboxConfig,
+        dir: &Path,
+        script: &str,
+    ) -> String {
+        let config = SandboxConfig { backend, ..config };
+        let sandbox = Sandbox::new(&config, Workspace::new(dir).unwrap()).unwrap();
+        let output = sandbox
+            .command(script)
+            .await
+            .unwrap()
+            .output()
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    pub async fn network_is_off(backend: SandboxBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let script = format!(
+            "bash -c 'echo > /dev/tcp/127.0.0.1/{}' 2>/dev/null && echo connected || echo refused",
+            port
+        );
+        let output = run(backend, SandboxConfig::default(), dir.path(), &script).await;
+        assert_eq!(output, "refused\n");
+    }
+
+    pub async fn writes_outside_workspace_are_denied(backend: SandboxBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = format!("sandbox-test-{}", std::process::id());
+        let script = format!(
+            "touch /usr/{0} || echo usr; touch /etc/{0} || echo etc; \
+             echo tmp > /tmp/{0} && cat /tmp/{0}; echo ok > /workspace/{0}",
+            marker
+        );
+        let output = run(backend, SandboxConfig::default(), dir.path(), &script).await;
+        assert_eq!(output, "usr\netc\ntmp\n");
+        assert!(dir.path().join(&marker).exists());
+        // `/tmp` is private to the sandbox.
+        assert!(!std::env::temp_dir().join(&marker).exists());
+    }
+
+    pub async fn host_processes_are_hidden(backend: SandboxBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut host = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let output = run(
+            backend,
+            SandboxConfig::default(),
+            dir.path(),
+            "ls /proc | grep -c '^[0-9]'; kill -9 -1; echo survived",
+        )
+        .await;
+        let mut lines = output.lines();
+        let processes: usize = lines.next().unwrap().parse().unwrap();
+        assert!(processes <= 4, "{} processes are visible", processes);
+        assert_eq!(lines.next(), Some("survived"));
+        assert!(host.try_wait().unwrap().is_none());
+        host.kill().unwrap();
+    }
+
+    pub async fn rlimits_apply(backend: SandboxBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = SandboxConfig::default();
+        config.limits.cpu_seconds = 7;
+        config.limits.memory_mb = 512;
+        let output = run(backend, config, dir.path(), "ulimit -t; ulimit -v").await;
+        assert_eq!(output, "7\n524288\n");
+    }
+}
@@ -0,0 +1,639 @@
+use super::{SandboxConfig, SANDBOX_PATH, SANDBOX_WORKSPACE, SYSTEM_PATHS};
+use crate::tools::workspace::Workspace;
+use anyhow::Result;
+use std::{
+    ffi::{CString, OsStr},
+    io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::process::Command;
+
+/// The host paths that are bind-mounted read-only as device nodes.
+const DEVICES: &[&str] = &[
+    "/dev/null",
+    "/dev/zero",
+    "/dev/full",
+    "/dev/random",
+    "/dev/urandom",
+];
+
+/// System calls that sandboxed commands are not allowed to make. They fail
+/// with `EPERM`.
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_kexec_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_userfaultfd,
+    libc::SYS_open_by_handle_at,
+];
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xC000_003E;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xC000_00B7;
+
+/// A read-only entry in the sandbox's root file system.
+enum Entry {
+    /// A directory or file bind-mounted from the host.
+    Bind {
+        source: CString,
+        target: CString,
+        is_dir: bool,
+        locked_flags: libc::c_ulong,
+    },
+    /// A symlink recreated from the host, e.g. `/bin -> usr/bin`.
+    Symlink { link: CString, target: CString },
+}
+
+/// Runs commands in Linux namespaces without any external helper.
+///
+/// Each command gets fresh user, mount, PID, IPC, UTS and (unless the
+/// network is enabled) network namespaces. Its root file system is a tmpfs
+/// containing read-only bind mounts of the system directories, the workspace
+/// mounted read-write at `/workspace`, a private `/tmp`, a minimal `/dev` and
+/// a `/proc` that only shows the sandbox's processes. A seccomp filter
+/// denies system calls that could be used to escape, and rlimits cap CPU
+/// time and memory.
+///
+/// The process that is spawned stays outside the PID namespace and waits
+/// for a small init process inside it, which in turn waits for the command.
+/// When the command exits, so does the init process, and the kernel kills
+/// whatever the command left running. Killing the spawned process kills the
+/// init process, and with it the whole namespace.
+///
+/// This requires unprivileged user namespaces to be enabled on the host.
+pub struct NativeSandbox {
+    plan: std::sync::Arc<Plan>,
+    /// Removed when the sandbox is dropped. Each sandbox has its own, as
+    /// several tools of a run may hold one at the same time.
+    _staging: TempDir,
+    wall_time: Duration,
+}
+
+/// Everything the child needs to set up the sandbox, prepared in the parent
+/// so that nothing is allocated between `fork` and `exec`.
+struct Plan {
+    network: bool,
+    staging: CString,
+    directories: Vec<CString>,
+    entries: Vec<Entry>,
+    workspace_source: CString,
+    workspace_target: CString,
+    tmp_target: CString,
+    proc_target: CString,
+    devices: Vec<(CString, CString)>,
+    uid_map: CString,
+    gid_map: CString,
+    cpu_seconds: u64,
+    memory_bytes: u64,
+    filter: Vec<libc::sock_filter>,
+}
+
+impl NativeSandbox {
+    pub(super) fn new(config: &SandboxConfig, workspace: Workspace) -> Result<Self> {
+        // The mount point for the sandbox's root file system. It only ever
+        // has something mounted on it inside the child's mount namespace, so
+        // on the host it stays an empty directory.
+        let staging_dir = tempfile::Builder::new()
+            .prefix("agent-sandbox-")
+            .tempdir()?;
+        let staging = staging_dir.path().to_path_buf();
+
+        let mut directories = Vec::new();
+        let mut entries = Vec::new();
+        let read_only_paths = SYSTEM_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .chain(config.read_only_paths.iter().cloned());
+        for path in read_only_paths {
+            let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+                continue;
+            };
+            let target = staging.join(path.strip_prefix("/").unwrap_or(&path));
+            add_parents(&mut directories, &staging, &target)?;
+            if metadata.file_type().is_symlink() {
+                entries.push(Entry::Symlink {
+                    link: c_path(&target)?,
+                    target: c_path(&std::fs::read_link(&path)?)?,
+                });
+            } else {
+                entries.push(Entry::Bind {
+                    source: c_path(&path)?,
+                    target: c_path(&target)?,
+                    is_dir: metadata.is_dir(),
+                    locked_flags: locked_flags(&path),
+                });
+            }
+        }
+
+        let mut devices = Vec::new();
+        for device in DEVICES.iter().map(Path::new).filter(|d| d.exists()) {
+            let target = staging.join(device.strip_prefix("/").unwrap_or(device));
+            add_parents(&mut directories, &staging, &target)?;
+            devices.push((c_path(device)?, c_path(&target)?));
+        }
+
+        // SAFETY: `getuid` and `getgid` cannot fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+        let plan = Plan {
+            network: config.network,
+            staging: c_path(&staging)?,
+            directories,
+            entries,
+            workspace_source: c_path(workspace.root())?,
+            workspace_target: c_path(&staging.join(SANDBOX_WORKSPACE.trim_start_matches('/')))?,
+            tmp_target: c_path(&staging.join("tmp"))?,
+            proc_target: c_path(&staging.join("proc"))?,
+            devices,
+            uid_map: CString::new(format!("{} {} 1\n", uid, uid))?,
+            gid_map: CString::new(format!("{} {} 1\n", gid, gid))?,
+            cpu_seconds: config.limits.cpu_seconds,
+            memory_bytes: config.limits.memory_mb * 1024 * 1024,
+            filter: seccomp_filter(),
+        };
+
+        Ok(Self {
+            plan: std::sync::Arc::new(plan),
+            _staging: staging_dir,
+            wall_time: Duration::from_secs(config.limits.wall_time_seconds),
+        })
+    }
+
+    pub(super) fn command(&self, script: &str) -> Command {
+        let mut command = Command::new("/bin/sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .env_clear()
+            .env("PATH", SANDBOX_PATH)
+            .env("HOME", "/tmp");
+
+        let plan = self.plan.clone();
+        // SAFETY: `Plan::enter` only makes raw system calls on data that was
+        // allocated before the fork.
+        unsafe {
+            command.pre_exec(move || plan.enter());
+        }
+        command
+    }
+
+    pub(super) fn wall_time(&self) -> Duration {
+        self.wall_time
+    }
+}
+
+impl Plan {
+    /// Moves the current (forked) process into the sandbox.
+    fn enter(&self) -> io::Result<()> {
+        let mut flags = libc::CLONE_NEWUSER
+            | libc::CLONE_NEWNS
+            | libc::CLONE_NEWIPC
+            | libc::CLONE_NEWUTS;
+        if !self.network {
+            flags |= libc::CLONE_NEWNET;
+        }
+        // SAFETY: all pointers passed below come from `CString`s owned by
+        // `self`, or are null where the kernel allows it.
+        unsafe {
+            check(libc::unshare(flags))?;
+            write_file(c"/proc/self/setgroups", c"deny")?;
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)?;
+
+            check(libc::mount(
+                std::ptr::null(),
+                c"/".as_ptr(),
+                std::ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            ))?;
+            check(libc::mount(
+                c"tmpfs".as_ptr(),
+                self.staging.as_ptr(),
+                c"tmpfs".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                c"mode=0755".as_ptr().cast(),
+            ))?;
+
+            for dir in &self.directories {
+                make_dir(dir)?;
+            }
+            for entry in &self.entries {
+                match entry {
+                    Entry::Symlink { link, target } => {
+                        check(libc::symlink(target.as_ptr(), link.as_ptr()))?;
+                    }
+                    Entry::Bind {
+                        source,
+                        target,
+                        is_dir,
+                        locked_flags,
+                    } => {
+                        if *is_dir {
+                            make_dir(target)?;
+                        } else {
+                            touch(target)?;
+                        }
+                        bind(source, target, libc::MS_RDONLY | locked_flags)?;
+                    }
+                }
+            }
+
+            make_dir(&self.workspace_target)?;
+            bind(&self.workspace_source, &self.workspace_target, 0)?;
+
+            make_dir(&self.tmp_target)?;
+            check(libc::mount(
+                c"tmpfs".as_ptr(),
+                self.tmp_target.as_ptr(),
+                c"tmpfs".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                std::ptr::null(),
+            ))?;
+
+            for (source, target) in &self.devices {
+                touch(target)?;
+                check(libc::mount(
+                    source.as_ptr(),
+                    target.as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_BIND,
+                    std::ptr::null(),
+                ))?;
+            }
+
+            // A procfs of the new PID namespace, which only shows the
+            // sandbox's processes. It can be refused on hosts with masked
+            // `/proc` entries, in which case the sandbox has no `/proc`.
+            make_dir(&self.proc_target)?;
+            let _ = libc::mount(
+                c"/proc".as_ptr(),
+                self.proc_target.as_ptr(),
+                std::ptr::null(),
+                libc::MS_BIND | libc::MS_REC,
+                std::ptr::null(),
+            );
+
+            check(libc::chdir(self.staging.as_ptr()))?;
+            check(
+                libc::syscall(libc::SYS_pivot_root, c".".as_ptr(), c".".as_ptr()) as libc::c_int,
+            )?;
+            check(libc::umount2(c".".as_ptr(), libc::MNT_DETACH))?;
+            check(libc::mount(
+                std::ptr::null(),
+                c"/".as_ptr(),
+                std::ptr::null(),
+                libc::MS_REMOUNT | libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV,
+                std::ptr::null(),
+            ))?;
+            check(libc::chdir(c"/workspace".as_ptr()))?;
+        }
+
+        super::apply_rlimits(self.cpu_seconds, self.memory_bytes)?;
+
+        let program = libc::sock_fprog {
+            len: self.filter.len() as libc::c_ushort,
+            filter: self.filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // SAFETY: `program` points to a valid BPF program that outlives the
+        // call.
+        unsafe {
+            check(libc::prctl(
+                libc::PR_SET_NO_NEW_PRIVS,
+                1 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+            ))?;
+            check(libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                &program as *const libc::sock_fprog,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// Forks twice after the PID namespace was unshared, and returns in the
+/// grandchild, which goes on to set up the sandbox and run the command.
+///
+/// The current process, which the caller waits for, waits for the child, the
+/// namespace's init process, which waits for the grandchild. Both exit with
+/// the grandchild's exit code, or 128 plus the signal that killed it, like a
+/// shell. Neither execs, so they close every file descriptor but the
+/// standard ones, including the pipe `std` uses to report a failed exec,
+/// which it would otherwise wait on until the command exits.
+///
+/// # Safety
+///
+/// Must be called in a single-threaded child between `fork` and `exec`.
+unsafe fn fork_into_pid_namespace() -> io::Result<()> {
+    // Closed when the outer process dies, which tells an init process that
+    // started too late to set its parent-death signal.
+    let mut alive = [0; 2];
+    check(libc::pipe2(alive.as_mut_ptr(), libc::O_CLOEXEC))?;
+    let [alive_read, alive_write] = alive;
+
+    let init = libc::fork();
+    check(init)?;
+    if init > 0 {
+        libc::close(alive_read);
+        close_range(3, alive_write - 1);
+        close_range(alive_write + 1, libc::c_int::MAX);
+        ignore_signals();
+        libc::_exit(wait_for(init));
+    }
+
+    libc::close(alive_write);
+    check(libc::prctl(
+        libc::PR_SET_PDEATHSIG,
+        libc::SIGKILL as libc::c_ulong,
+    ))?;
+    let mut poll = libc::pollfd {
+        fd: alive_read,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    if libc::poll(&mut poll, 1, 0) != 0 {
+        libc::_exit(1);
+    }
+    libc::close(alive_read);
+
+    let command = libc::fork();
+    check(command)?;
+    if command > 0 {
+        close_range(3, libc::c_int::MAX);
+        ignore_signals();
+        // As init, reap every orphan until the command itself exits.
+        loop {
+            let mut status = 0;
+            let pid = libc::waitpid(-1, &mut status, 0);
+            if pid == command {
+                libc::_exit(exit_code(status));
+            }
+            if pid < 0 && io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                libc::_exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Waits for `pid` and returns its exit code, as `fork_into_pid_namespace`
+/// reports it.
+unsafe fn wait_for(pid: libc::pid_t) -> libc::c_int {
+    let mut status = 0;
+    while libc::waitpid(pid, &mut status, 0) < 0 {
+        if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            return 1;
+        }
+    }
+    exit_code(status)
+}
+
+fn exit_code(status: libc::c_int) -> libc::c_int {
+    if libc::WIFSIGNALED(status) {
+        128 + libc::WTERMSIG(status)
+    } else {
+        libc::WEXITSTATUS(status)
+    }
+}
+
+/// Makes the processes that only wait immune to the signals meant for the
+/// command, such as Ctrl-C. They still die with `SIGKILL`.
+unsafe fn ignore_signals() {
+    for signal in [libc::SIGINT, libc::SIGQUIT, libc::SIGTERM, libc::SIGHUP] {
+        libc::signal(signal, libc::SIG_IGN);
+    }
+}
+
+/// Closes the file descriptors from `first` to `last`, inclusive.
+unsafe fn close_range(first: libc::c_int, last: libc::c_int) {
+    if first > last {
+        return;
+    }
+    if libc::syscall(
+        libc::SYS_close_range,
+        first as libc::c_uint,
+        last as libc::c_uint,
+        0,
+    ) != 0
+    {
+        for fd in first..=last.min(4096) {
+            libc::close(fd);
+        }
+    }
+}
+
+/// Builds a seccomp BPF program that kills the process on a foreign
+/// architecture, fails the calls in `DENIED_SYSCALLS` with `EPERM`, and
+/// allows everything else.
+fn seccomp_filter() -> Vec<libc::sock_filter> {
+    const ARCH_OFFSET: u32 = 4;
+    const NR_OFFSET: u32 = 0;
+
+    let statement = |code: u32, k: u32| libc::sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    };
+    let jump = |k: u32, jt: u8, jf: u8| libc::sock_filter {
+        code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+        jt,
+        jf,
+        k,
+    };
+    let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+    let ret = libc::BPF_RET | libc::BPF_K;
+
+    let mut filter = vec![
+        statement(load, ARCH_OFFSET),
+        jump(AUDIT_ARCH, 1, 0),
+        statement(ret, libc::SECCOMP_RET_KILL_PROCESS),
+        statement(load, NR_OFFSET),
+    ];
+    for &syscall in DENIED_SYSCALLS {
+        filter.push(jump(syscall as u32, 0, 1));
+        filter.push(statement(ret, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32));
+    }
+    filter.push(statement(ret, libc::SECCOMP_RET_ALLOW));
+    filter
+}
+
+/// Records every directory between `staging` and `target` (exclusive) that
+/// must be created, in order, without duplicates.
+fn add_parents(directories: &mut Vec<CString>, staging: &Path, target: &Path) -> Result<()> {
+    let Some(parent) = target.parent() else {
+        return Ok(());
+    };
+    let mut missing: Vec<&Path> = parent
+        .ancestors()
+        .take_while(|ancestor| *ancestor != staging)
+        .collect();
+    missing.reverse();
+    for dir in missing {
+        let dir = c_path(dir)?;
+        if !directories.contains(&dir) {
+            directories.push(dir);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the mount flags of `path` that an unprivileged user namespace is
+/// not allowed to clear when remounting a bind mount read-only.
+fn locked_flags(path: &Path) -> libc::c_ulong {
+    let Ok(path) = c_path(path) else {
+        return 0;
+    };
+    // SAFETY: `stat` is a plain C struct that `statvfs` fills in.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is a valid C string and `stat` is writable.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return 0;
+    }
+    let mut flags = 0;
+    for (st, ms) in [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ] {
+        if stat.f_flag & st != 0 {
+            flags |= ms;
+        }
+    }
+    flags
+}
+
+fn c_path(path: &Path) -> Result<CString> {
+    Ok(CString::new(OsStr::as_bytes(path.as_os_str()))?)
+}
+
+fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Bind-mounts `source` onto `target`, then remounts it with `flags` (e.g.
+/// read-only).
+unsafe fn bind(source: &CString, target: &CString, flags: libc::c_ulong) -> io::Result<()> {
+    check(libc::mount(
+        source.as_ptr(),
+        target.as_ptr(),
+        std::ptr::null(),
+        libc::MS_BIND | libc::MS_REC,
+        std::ptr::null(),
+    ))?;
+    if flags != 0 {
+        check(libc::mount(
+            std::ptr::null(),
+            target.as_ptr(),
+            std::ptr::null(),
+            libc::MS_BIND | libc::MS_REMOUNT | flags,
+            std::ptr::null(),
+        ))?;
+    }
+    Ok(())
+}
+
+unsafe fn make_dir(path: &CString) -> io::Result<()> {
+    if libc::mkdir(path.as_ptr(), 0o755) != 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EEXIST) {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+unsafe fn touch(path: &CString) -> io::Result<()> {
+    let fd = libc::open(
+        path.as_ptr(),
+        libc::O_CREAT | libc::O_WRONLY | libc::O_CLOEXEC,
+        0o644,
+    );
+    check(fd)?;
+    libc::close(fd);
+    Ok(())
+}
+
+unsafe fn write_file(path: &std::ffi::CStr, content: &std::ffi::CStr) -> io::Result<()> {
+    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+    check(fd)?;
+    let bytes = content.to_bytes();
+    let written = libc::write(fd, bytes.as_ptr().cast(), bytes.len());
+    libc::close(fd);
+    if written < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::sandbox::{isolation, SandboxBackend};
+
+    #[tokio::test]
+    async fn test_network_is_off() {
+        isolation::network_is_off(SandboxBackend::Native).await;
+    }
+
+    #[tokio::test]
+    async fn test_writes_outside_workspace_are_denied() {
+        isolation::writes_outside_workspace_are_denied(SandboxBackend::Native).await;
+    }
+
+    #[tokio::test]
+    async fn test_host_processes_are_hidden() {
+        isolation::host_processes_are_hidden(SandboxBackend::Native).await;
+    }
+
+    #[tokio::test]
+    async fn test_rlimits_apply() {
+        isolation::rlimits_apply(SandboxBackend::Native).await;
+    }
+
+    #[tokio::test]
+    async fn test_sandboxes_do_not_share_staging() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let config = SandboxConfig::default();
+        let first = NativeSandbox::new(&config, workspace.clone()).unwrap();
+        let second = NativeSandbox::new(&config, workspace).unwrap();
+        drop(first);
+
+        let output = second.command("echo ok").output().await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+use reqwest::{RequestBuilder, Url};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+/// How a secret is attached to a request.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretKind {
+    /// An `Authorization: Bearer <secret>` header.
+    #[default]
+    Bearer,
+    /// HTTP basic authentication, with the secret given as `user:password`.
+    Basic,
+    /// A header with the secret as its value, named by `name`.
+    Header,
+    /// A query parameter with the secret as its value, named by `name`.
+    Query,
+}
+
+/// A `[secrets.<name>]` section of the configuration file.
+///
+/// The value itself is read from an environment variable when a request
+/// needs it, so it never appears in the configuration or the prompt.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SecretConfig {
+    /// The environment variable holding the secret.
+    pub env: String,
+    /// How the secret is attached to requests.
+    #[serde(default)]
+    pub kind: SecretKind,
+    /// The header or query parameter for the `header` and `query` kinds.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The hosts the secret may be sent to. Each also matches its
+    /// subdomains.
+    pub hosts: Vec<String>,
+}
+
+impl SecretConfig {
+    /// Returns whether the secret may be sent to `url`.
+    fn allows(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+        self.hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            host == allowed || host.ends_with(&format!(".{}", allowed))
+        })
+    }
+}
+
+/// The configured secrets, which tools attach to requests by name so that
+/// the model can authenticate without ever seeing their values.
+///
+/// Cloning is cheap and shares the configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Secrets {
+    secrets: Arc<BTreeMap<String, SecretConfig>>,
+}
+
+impl Secrets {
+    /// Creates the secrets from the `[secrets]` section of the
+    /// configuration file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the secrets, or an error if one has no hosts or
+    /// lacks the `name` its kind requires.
+    pub fn new(config: &HashMap<String, SecretConfig>) -> Result<Self> {
+        let secrets: BTreeMap<String, SecretConfig> = config.clone().into_iter().collect();
+        for (name, secret) in &secrets {
+            if secret.hosts.is_empty() {
+                return Err(anyhow!(
+                    "Secret `{}` must list the hosts it may be sent to",
+                    name
+                ));
+            }
+            if matches!(secret.kind, SecretKind::Header | SecretKind::Query)
+                && secret.name.is_none()
+            {
+                return Err(anyhow!(
+                    "Secret `{}` needs a `name` for the header or query parameter",
+                    name
+                ));
+            }
+        }
+        Ok(Self {
+            secrets: Arc::new(secrets),
+        })
+    }
+
+    /// Returns whether a secret with this name is configured.
+    pub fn contains(&self, name: &str) -> bool {
+        self.secrets.contains_key(name)
+    }
+
+    /// Lists the secrets for the prompt, with the hosts each may be sent
+    /// to, e.g. `github (api.github.com)`.
+    pub fn describe(&self) -> String {
+        self.secrets
+            .iter()
+            .map(|(name, secret)| format!("{} ({})", name, secret.hosts.join(", ")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Attaches the secret `name` to a request for `url`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the request and the strings to redact from the
+    /// response, or an error if the secret is unknown, not set, or may not
+    /// be sent to the URL's host.
+    pub fn apply(
+        &self,
+        name: &str,
+        url: &Url,
+        request: RequestBuilder,
+    ) -> Result<(RequestBuilder, Vec<String>)> {
+        let secret = self
+            .secrets
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown secret `{}`", name))?;
+        if !secret.allows(url) {
+            return Err(anyhow!(
+                "Secret `{}` may not be sent to {}",
+                name,
+                url.host_str().unwrap_or_default()
+            ));
+        }
+        let value = std::env::var(&secret.env)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| anyhow!("Secret `{}` is not set: ${} is empty", name, secret.env))?;
+
+        let field = secret.name.as_deref().unwrap_or_default();
+        let request = match secret.kind {
+            SecretKind::Bearer => request.bearer_auth(&value),
+            SecretKind::Basic => match value.split_once(':') {
+                Some((user, password)) => request.basic_auth(user, Some(password)),
+                None => request.basic_auth(&value, None::<&str>),
+            },
+            SecretKind::Header => request.header(field, &value),
+            SecretKind::Query => request.query(&[(field, &value)]),
+        };
+        let mut redact = vec![value.clone()];
+        if let Some((_, password)) = value.split_once(':') {
+            redact.push(password.to_string());
+        }
+        Ok((request, redact))
+    }
+}
+
+/// Replaces every occurrence of the `secrets` in `text`, in case a server
+/// echoes them back.
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), "[REDACTED]")
+        })
+}
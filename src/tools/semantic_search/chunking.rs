@@ -0,0 +1,188 @@
+use crate::tools::outline::symbols::{symbols, Language, Symbol};
+use std::{ops::Range, path::Path};
+
+/// Chunks are kept to about this many lines, so that a search result
+/// points at a specific part of a file.
+const MAX_CHUNK_LINES: usize = 60;
+
+/// Chunk text beyond this many characters is not embedded, which keeps
+/// minified files within embedding models' input limits.
+const MAX_CHUNK_CHARS: usize = 6000;
+
+/// A piece of a file to embed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// The first and last lines, counting from 1.
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// Splits a file into chunks along the boundaries that suit its kind:
+/// top-level definitions for source code that `OutlineTool` can parse,
+/// headings for Markdown, and blank lines for anything else.
+///
+/// Small neighbouring pieces are merged and long ones are split, preferring
+/// blank lines, so that chunks stay under `MAX_CHUNK_LINES`.
+pub fn chunk(path: &Path, content: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let all = 0..lines.len();
+    let segments = match Language::from_path(path) {
+        Some(language) => match symbols(content, language) {
+            Ok(symbols) => code_segments(&symbols, all),
+            Err(_) => paragraph_segments(&lines),
+        },
+        None if is_markdown(path) => markdown_segments(&lines),
+        None => paragraph_segments(&lines),
+    };
+
+    let mut chunks = Vec::new();
+    for range in pack(segments, &lines) {
+        let text = lines[range.clone()].join("\n");
+        if text.trim().is_empty() {
+            continue;
+        }
+        let text = match text.char_indices().nth(MAX_CHUNK_CHARS) {
+            Some((cut, _)) => text[..cut].to_string(),
+            None => text,
+        };
+        chunks.push(Chunk {
+            start_line: range.start + 1,
+            end_line: range.end,
+            text,
+        });
+    }
+    chunks
+}
+
+fn is_markdown(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("md" | "markdown" | "mdx")
+    )
+}
+
+/// Splits the lines in `range` into one segment per definition, each
+/// starting after the previous one so that it takes along the comments,
+/// attributes and imports above it. Long definitions with members, such
+/// as classes and `impl` blocks, are split by member.
+fn code_segments(symbols: &[Symbol], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut start = range.start;
+    for symbol in symbols {
+        let end = symbol.end_line.min(range.end);
+        if end <= start {
+            continue;
+        }
+        if end - start > MAX_CHUNK_LINES && !symbol.children.is_empty() {
+            segments.extend(code_segments(&symbol.children, start..end));
+        } else {
+            segments.push(start..end);
+        }
+        start = end;
+    }
+    if start < range.end {
+        segments.push(start..range.end);
+    }
+    segments
+}
+
+/// Splits Markdown into one segment per section, at headings outside code
+/// blocks.
+fn markdown_segments(lines: &[&str]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut in_code = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        } else if !in_code && trimmed.starts_with('#') && i > start {
+            segments.push(start..i);
+            start = i;
+        }
+    }
+    segments.push(start..lines.len());
+    segments
+}
+
+/// Splits text into paragraphs, each starting at a non-blank line after a
+/// blank one.
+fn paragraph_segments(lines: &[&str]) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for i in 1..lines.len() {
+        if lines[i - 1].trim().is_empty() && !lines[i].trim().is_empty() && i > start {
+            segments.push(start..i);
+            start = i;
+        }
+    }
+    segments.push(start..lines.len());
+    segments
+}
+
+/// Merges consecutive segments while they fit in `MAX_CHUNK_LINES`, and
+/// splits segments that do not.
+fn pack(segments: Vec<Range<usize>>, lines: &[&str]) -> Vec<Range<usize>> {
+    let mut packed: Vec<Range<usize>> = Vec::new();
+    for segment in segments {
+        for piece in split(segment, lines) {
+            match packed.last_mut() {
+                Some(last) if piece.end - last.start <= MAX_CHUNK_LINES => last.end = piece.end,
+                _ => packed.push(piece),
+            }
+        }
+    }
+    packed
+}
+
+/// Splits a segment longer than `MAX_CHUNK_LINES`, at the last blank line
+/// in the second half of each piece where there is one.
+fn split(mut segment: Range<usize>, lines: &[&str]) -> Vec<Range<usize>> {
+    let mut pieces = Vec::new();
+    while segment.len() > MAX_CHUNK_LINES {
+        let limit = segment.start + MAX_CHUNK_LINES;
+        let cut = (segment.start + MAX_CHUNK_LINES / 2..limit)
+            .rev()
+            .find(|&i| lines[i].trim().is_empty())
+            .map_or(limit, |i| i + 1);
+        pieces.push(segment.start..cut);
+        segment.start = cut;
+    }
+    if !segment.is_empty() {
+        pieces.push(segment);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(chunks: &[Chunk]) -> Vec<(usize, usize)> {
+        chunks.iter().map(|c| (c.start_line, c.end_line)).collect()
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        // Two long functions each get their own chunk, with the imports and
+        // doc comment above them.
+        let body = "    let x = 1;\n".repeat(40);
+        let rust = format!(
+            "use std::io;\n\n/// Reads.\nfn read() {{\n{body}}}\n\nfn write() {{\n{body}}}\n"
+        );
+        assert_eq!(
+            ranges(&chunk(Path::new("src/io.rs"), &rust)),
+            [(1, 45), (46, 88)]
+        );
+
+        // Sections too long to merge split at headings, but not at a `#`
+        // inside a code block.
+        let section = "Some text.\n\n".repeat(20);
+        let markdown = format!("# Setup\n\n{section}```sh\n# comment\n```\n## Usage\n\n{section}");
+        assert_eq!(
+            ranges(&chunk(Path::new("README.md"), &markdown)),
+            [(1, 45), (46, 87)]
+        );
+    }
+}
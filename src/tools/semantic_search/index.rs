@@ -0,0 +1,263 @@
+use super::chunking::{chunk, Chunk};
+use crate::{
+    memory::{
+        embedding::Embedder,
+        store::{Match, Record, Source, VectorStore},
+    },
+    tools::workspace::Workspace,
+};
+use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+
+/// The namespace index records are stored under.
+const NAMESPACE: &str = "index";
+
+/// Files larger than this are skipped, as they are usually generated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// The number of chunks embedded per call to the embedder.
+const BATCH_SIZE: usize = 64;
+
+/// What an update of the index did.
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexStats {
+    /// Files that were new or changed, and were chunked and embedded.
+    pub indexed: usize,
+    /// Files whose hash had not changed.
+    pub unchanged: usize,
+    /// Files that no longer exist, whose chunks were deleted.
+    pub removed: usize,
+    /// The chunks embedded.
+    pub chunks: usize,
+}
+
+/// A file found while scanning the indexed directory.
+struct ScannedFile {
+    path: String,
+    hash: String,
+    /// The file's chunks, only when its hash changed.
+    chunks: Option<Vec<Chunk>>,
+}
+
+/// A semantic index of the text files in a workspace directory.
+///
+/// Each file is split into chunks, which are embedded and stored with their
+/// file, line range and the file's SHA-256 hash. Updating the index only
+/// re-embeds files whose hash changed, and drops the chunks of files that
+/// are gone, so it is cheap to update before every search.
+pub struct Index {
+    workspace: Workspace,
+    root: PathBuf,
+    embedder: Arc<dyn Embedder + Send + Sync>,
+    store: Arc<dyn VectorStore + Send + Sync>,
+    /// Serializes updates, so that concurrent searches do not index the same
+    /// files twice.
+    updating: Mutex<()>,
+}
+
+impl Index {
+    /// Creates a new `Index`.
+    ///
+    /// # Arguments
+    ///
+    /// * `workspace` - The workspace the indexed files are in.
+    /// * `directory` - The directory to index, relative to the workspace
+    ///   root.
+    /// * `embedder` - The embedder for chunks and queries.
+    /// * `store` - The store for the embedded chunks.
+    pub fn new(
+        workspace: Workspace,
+        directory: &str,
+        embedder: Arc<dyn Embedder + Send + Sync>,
+        store: Arc<dyn VectorStore + Send + Sync>,
+    ) -> Result<Self> {
+        let root = workspace.resolve(directory)?;
+        if !root.is_dir() {
+            return Err(anyhow!("Not a directory: {}", directory));
+        }
+        Ok(Self {
+            workspace,
+            root,
+            embedder,
+            store,
+            updating: Mutex::new(()),
+        })
+    }
+
+    /// Brings the index up to date with the files on disk.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with what changed, or an error if embedding or storing
+    /// fails. Files that fail are indexed again on the next update.
+    pub async fn update(&self) -> Result<IndexStats> {
+        let _guard = self.updating.lock().await;
+
+        // The hash and chunk ids of every indexed file.
+        let mut indexed: HashMap<String, (String, Vec<String>)> = HashMap::new();
+        for record in self.store.list(NAMESPACE).await? {
+            if let Some(source) = record.source {
+                let entry = indexed.entry(source.path).or_default();
+                entry.0 = source.hash;
+                entry.1.push(record.id);
+            }
+        }
+
+        let hashes = indexed
+            .iter()
+            .map(|(path, (hash, _))| (path.clone(), hash.clone()))
+            .collect();
+        let workspace = self.workspace.clone();
+        let root = self.root.clone();
+        let files = tokio::task::spawn_blocking(move || scan(&workspace, &root, &hashes)).await?;
+
+        let mut stats = IndexStats::default();
+        for file in files {
+            let previous = indexed.remove(&file.path);
+            let Some(chunks) = file.chunks else {
+                stats.unchanged += 1;
+                continue;
+            };
+            if chunks.is_empty() && previous.is_none() {
+                stats.unchanged += 1;
+                continue;
+            }
+            if let Some((_, ids)) = previous {
+                for id in ids {
+                    self.store.delete(NAMESPACE, &id).await?;
+                }
+            }
+            stats.indexed += 1;
+            stats.chunks += chunks.len();
+            self.store_chunks(&file.path, &file.hash, chunks).await?;
+        }
+        for (_, ids) in indexed.into_values() {
+            stats.removed += 1;
+            for id in ids {
+                self.store.delete(NAMESPACE, &id).await?;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Embeds a file's chunks and stores them.
+    ///
+    /// Every chunk is embedded before any is stored, so that a failed
+    /// embedding call does not leave part of the file indexed under its new
+    /// hash.
+    async fn store_chunks(&self, path: &str, hash: &str, chunks: Vec<Chunk>) -> Result<()> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(BATCH_SIZE) {
+            // The path tells the embedder what the chunk is part of.
+            let texts: Vec<String> = batch
+                .iter()
+                .map(|chunk| format!("{}\n{}", path, chunk.text))
+                .collect();
+            embeddings.extend(self.embedder.embed(&texts).await?);
+        }
+        for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+            self.store
+                .upsert(Record {
+                    namespace: NAMESPACE.to_string(),
+                    id: format!("{}:{}-{}", path, chunk.start_line, chunk.end_line),
+                    text: chunk.text,
+                    embedding,
+                    created_at,
+                    source: Some(Source {
+                        path: path.to_string(),
+                        start_line: chunk.start_line,
+                        end_line: chunk.end_line,
+                        hash: hash.to_string(),
+                    }),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Finds the chunks most related to `query`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - What to look for, in natural language or code.
+    /// * `limit` - The maximum number of chunks to return.
+    /// * `path` - Only return chunks from files under this path, relative
+    ///   to the workspace root.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the matches, most similar first.
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        path: Option<&str>,
+    ) -> Result<Vec<Match>> {
+        let embedding = self
+            .embedder
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("The embedder returned no embedding"))?;
+        let prefix = path
+            .map(|path| path.trim().trim_start_matches("./").trim_end_matches('/'))
+            .filter(|prefix| !prefix.is_empty() && *prefix != ".");
+        let mut matches = self.store.search(NAMESPACE, &embedding, usize::MAX).await?;
+        if let Some(prefix) = prefix {
+            matches.retain(|found| {
+                found.record.source.as_ref().is_some_and(|source| {
+                    source.path == prefix || source.path.starts_with(&format!("{}/", prefix))
+                })
+            });
+        }
+        matches.truncate(limit);
+        Ok(matches)
+    }
+}
+
+/// Hashes every text file under `root`, and chunks those whose hash is not
+/// the one in `hashes`.
+fn scan(workspace: &Workspace, root: &Path, hashes: &HashMap<String, String>) -> Vec<ScannedFile> {
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+    let mut files = Vec::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+            continue;
+        }
+        let Ok(bytes) = fs::read(entry.path()) else {
+            continue;
+        };
+        if bytes.iter().take(8192).any(|&b| b == 0) {
+            continue;
+        }
+        let Ok(content) = String::from_utf8(bytes) else {
+            continue;
+        };
+        let path = workspace.relative(entry.path()).display().to_string();
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let chunks = match hashes.get(&path) {
+            Some(indexed) if *indexed == hash => None,
+            _ => Some(chunk(entry.path(), &content)),
+        };
+        files.push(ScannedFile { path, hash, chunks });
+    }
+    files
+}
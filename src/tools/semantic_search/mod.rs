@@ -0,0 +1,243 @@
+pub mod chunking;
+pub mod index;
+
+use crate::{
+    memory::{store, EmbedderConfig},
+    tools::{workspace::Workspace, Tool},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use index::Index;
+use serde::Deserialize;
+use std::{fmt::Write, path::PathBuf};
+use tracing::info;
+
+/// Each result shows at most this many lines of its chunk.
+const MAX_SNIPPET_LINES: usize = 20;
+
+/// The `[index]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    /// The directory to index, relative to the workspace root. Defaults to
+    /// the whole workspace.
+    pub directory: String,
+    /// The JSON Lines file the index is kept in, so that later runs only
+    /// re-embed changed files. Without one, the index is rebuilt every run.
+    pub path: Option<PathBuf>,
+    pub embedder: EmbedderConfig,
+}
+
+/// The arguments accepted by `SemanticSearchTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `query`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SemanticSearchArgs {
+    /// What to look for, in natural language or code.
+    query: String,
+    /// The maximum number of snippets to return.
+    #[serde(default = "default_limit")]
+    limit: usize,
+    /// Only search files under this path, relative to the workspace root.
+    #[serde(default)]
+    path: Option<String>,
+}
+
+fn default_limit() -> usize {
+    5
+}
+
+impl SemanticSearchArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for SemanticSearchTool: {}", e));
+        }
+        if args.is_empty() {
+            return Err(anyhow!("SemanticSearchTool requires a query"));
+        }
+        Ok(Self {
+            query: args.to_string(),
+            limit: default_limit(),
+            path: None,
+        })
+    }
+}
+
+/// A tool that finds the parts of the workspace's files most related to a
+/// query, by meaning rather than exact words.
+///
+/// The index is brought up to date before every search, which only
+/// re-embeds files that changed since the last one, so results reflect
+/// edits made earlier in the run.
+pub struct SemanticSearchTool {
+    index: Index,
+}
+
+impl SemanticSearchTool {
+    /// Creates a new `SemanticSearchTool`.
+    ///
+    /// # Arguments
+    ///
+    /// * `workspace` - The workspace to index.
+    /// * `config` - The directory to index, the index file and the
+    ///   embedder.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if the directory is invalid or
+    /// the index file cannot be opened.
+    pub fn new(workspace: Workspace, config: &IndexConfig) -> Result<Self> {
+        let store = store::open(config.path.as_deref())?;
+        let index = Index::new(workspace, &config.directory, config.embedder.build(), store)?;
+        Ok(Self { index })
+    }
+
+    #[cfg(test)]
+    fn from_index(index: Index) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl Tool for SemanticSearchTool {
+    /// Returns the name of the tool, "SemanticSearchTool".
+    fn name(&self) -> &str {
+        "SemanticSearchTool"
+    }
+
+    fn description(&self) -> String {
+        "Finds the code and documentation in the workspace most related to a question or \
+         description, returning ranked snippets with their file and lines. Use it when you \
+         do not know the exact words to search for. Args: the query, or a JSON object with \
+         the `query`, an optional `limit` (default 5) and an optional `path` to search under."
+            .to_string()
+    }
+
+    /// Updates the index and searches it.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either the bare query, or a JSON object with the fields
+    ///   `query`, `limit` and `path`.
+    ///   Example: `{"query": "where are paths checked against the workspace", "path": "src"}`
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with one `path:start-end (score)` heading per snippet,
+    /// followed by its lines, or an error if indexing or the search fails.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = SemanticSearchArgs::parse(args)?;
+        let stats = self.index.update().await?;
+        info!("Updated the semantic index: {:?}", stats);
+
+        let matches = self
+            .index
+            .search(&args.query, args.limit, args.path.as_deref())
+            .await?;
+        if matches.is_empty() {
+            return Ok("No matching files are indexed.".to_string());
+        }
+        let mut output = String::new();
+        for found in matches {
+            let Some(source) = &found.record.source else {
+                continue;
+            };
+            let _ = writeln!(
+                output,
+                "{}:{}-{} ({:.2})",
+                source.path, source.start_line, source.end_line, found.score
+            );
+            let lines: Vec<&str> = found.record.text.lines().collect();
+            for (i, line) in lines.iter().take(MAX_SNIPPET_LINES).enumerate() {
+                let _ = writeln!(output, "{:>5}  {}", source.start_line + i, line);
+            }
+            if lines.len() > MAX_SNIPPET_LINES {
+                let _ = writeln!(
+                    output,
+                    "       ... [{} more lines]",
+                    lines.len() - MAX_SNIPPET_LINES
+                );
+            }
+            output.push('\n');
+        }
+        output.truncate(output.trim_end().len() + 1);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{embedding::HashEmbedder, store::InMemoryStore};
+    use index::IndexStats;
+    use std::{fs, sync::Arc};
+
+    #[tokio::test]
+    async fn test_incremental_index_and_search() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/config.rs"),
+            "/// Loads the settings file.\nfn load_settings() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/retry.rs"),
+            "/// Retries a request with exponential backoff.\nfn retry_with_backoff() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("NOTES.md"),
+            "# Notes\n\nBackoff doubles each retry.\n",
+        )
+        .unwrap();
+
+        let workspace = Workspace::new(dir.path()).unwrap();
+        let index = Index::new(
+            workspace,
+            "",
+            Arc::new(HashEmbedder::new(256)),
+            Arc::new(InMemoryStore::new()),
+        )
+        .unwrap();
+        assert_eq!(
+            index.update().await.unwrap(),
+            IndexStats {
+                indexed: 3,
+                chunks: 3,
+                ..IndexStats::default()
+            }
+        );
+
+        fs::write(
+            dir.path().join("src/config.rs"),
+            "/// Loads the settings file from disk.\nfn load_settings() {}\n",
+        )
+        .unwrap();
+        fs::remove_file(dir.path().join("NOTES.md")).unwrap();
+        assert_eq!(
+            index.update().await.unwrap(),
+            IndexStats {
+                indexed: 1,
+                unchanged: 1,
+                removed: 1,
+                chunks: 1,
+            }
+        );
+
+        let tool = SemanticSearchTool::from_index(index);
+        let result = tool
+            .execute(r#"{"query": "retry a request with backoff", "limit": 1, "path": "src"}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            "src/retry.rs:1-2 (0.55)\n    1  /// Retries a request with exponential backoff.\n    2  fn retry_with_backoff() {}\n"
+        );
+    }
+}
@@ -0,0 +1,609 @@
+use crate::tools::{
+    command_policy::{CommandPolicy, Decision},
+    process::{kill_process_group, OutputCapture},
+    sandbox::{Sandbox, SandboxConfig},
+    workspace::Workspace,
+    Tool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    process::Stdio,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    process::Child,
+    sync::{mpsc, Mutex},
+    time::Instant,
+};
+
+/// The start of the line printed after each command. The rest of the marker
+/// is unique to the command, and the marker is split in two when sent to the
+/// shell so that the command text itself can never match it.
+const MARKER_PREFIX: &str = "__agent_shell_";
+
+/// Configures the shell once it has started: no prompts, no job control, so
+/// that every command stays in the shell's process group.
+const SHELL_INIT: &str = "PS1=''; PS2=''; set +m 2>/dev/null; export TERM=dumb";
+
+/// How long to wait for the shell to respond after starting it or after
+/// interrupting a command.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do with the shell session.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ShellAction {
+    /// Run `command` and wait for it to finish.
+    #[default]
+    Run,
+    /// Keep waiting for a command that is still running.
+    Wait,
+    /// Send Ctrl-C to the running command.
+    Interrupt,
+    /// Kill the shell and start a fresh one.
+    Restart,
+}
+
+/// The arguments accepted by `ShellSessionTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is run as a command.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ShellArgs {
+    #[serde(default)]
+    action: ShellAction,
+    /// The command to run, for the `run` action.
+    #[serde(default)]
+    command: Option<String>,
+    /// How long to wait before returning with the command still running.
+    /// Defaults to the sandbox's wall-clock limit.
+    #[serde(default)]
+    timeout_seconds: Option<u64>,
+}
+
+impl ShellArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for ShellSessionTool: {}", e));
+        }
+        Ok(Self {
+            action: ShellAction::Run,
+            command: Some(args.to_string()),
+            timeout_seconds: None,
+        })
+    }
+}
+
+/// The state of the shell when a call returns.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// The command finished.
+    Completed,
+    /// The command is still running; use `wait` or `interrupt`.
+    Running,
+    /// The command was interrupted and the shell is ready again.
+    Interrupted,
+    /// The shell exited or had to be restarted; its state was lost.
+    Exited,
+    /// Another command is still running, so nothing was sent.
+    Busy,
+    /// The shell is idle, so there was nothing to wait for or interrupt.
+    Idle,
+}
+
+/// The result of a call, as returned to the model.
+#[derive(Serialize, Debug)]
+struct ShellOutput {
+    status: Status,
+    /// The exit code of the command, once it has completed.
+    exit_code: Option<i32>,
+    /// The shell's working directory after the command.
+    cwd: Option<String>,
+    /// The combined stdout and stderr produced since the previous call.
+    output: String,
+}
+
+/// How a wait for the completion marker ended.
+enum Outcome {
+    Completed { exit_code: Option<i32>, cwd: String },
+    Running,
+    Exited,
+}
+
+/// Opens a pseudo-terminal, returning the master and slave ends.
+///
+/// Echo and output post-processing are turned off, so the master reads back
+/// exactly what the shell and its commands write.
+fn open_pty() -> std::io::Result<(OwnedFd, OwnedFd)> {
+    let (mut master, mut slave) = (-1, -1);
+    let size = libc::winsize {
+        ws_row: 50,
+        ws_col: 200,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: The out-pointers are valid, and null is allowed for the name
+    // and terminal attributes.
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            &size,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `openpty` succeeded, so both descriptors are open and owned by
+    // nobody else.
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+    // SAFETY: `termios` is plain data, fully initialized by `tcgetattr`
+    // before it is read.
+    unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(slave.as_raw_fd(), &mut termios) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        termios.c_lflag &= !(libc::ECHO | libc::ECHOE | libc::ECHOK | libc::ECHONL);
+        termios.c_oflag &= !libc::ONLCR;
+        if libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok((master, slave))
+}
+
+/// A running shell attached to a pseudo-terminal.
+struct Session {
+    child: Child,
+    writer: File,
+    /// Output chunks read from the terminal by a background thread. The
+    /// channel closes when the shell exits.
+    output: mpsc::UnboundedReceiver<Vec<u8>>,
+    /// Output that has been read but may still contain the start of a
+    /// completion marker.
+    pending: Vec<u8>,
+    /// Output of the current command that has not been returned yet.
+    capture: OutputCapture,
+    /// The marker that ends the running command, if any.
+    running: Option<String>,
+}
+
+impl Session {
+    /// Starts a shell through the sandbox and waits until it is ready.
+    async fn start(sandbox: &Sandbox, max_output_bytes: usize) -> Result<Self> {
+        let (master, slave) = open_pty()?;
+        let mut command = sandbox.interactive("exec sh").await?;
+        command
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave))
+            .kill_on_drop(true);
+        // SAFETY: `setsid` and `ioctl` are async-signal-safe.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                // Make the terminal the controlling terminal, so that Ctrl-C
+                // reaches the running command. Some sandboxes start a new
+                // session of their own, in which case this has no effect.
+                libc::ioctl(0, libc::TIOCSCTTY, 0);
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        // Close our copies of the slave end, so that reads fail once the
+        // shell and its children have exited.
+        drop(command);
+
+        let mut reader = File::from(master.try_clone()?);
+        let (sender, output) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            while let Ok(n) = reader.read(&mut buf) {
+                if n == 0 || sender.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut session = Self {
+            child,
+            writer: File::from(master),
+            output,
+            pending: Vec::new(),
+            capture: OutputCapture::new(max_output_bytes),
+            running: None,
+        };
+        session.send(SHELL_INIT)?;
+        if !session.sync().await? {
+            return Err(anyhow!("The shell did not start"));
+        }
+        Ok(session)
+    }
+
+    /// Sends a command to the shell, followed by a line that prints its exit
+    /// code and the working directory behind a unique marker.
+    fn send(&mut self, command: &str) -> Result<()> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let id = format!("done_{}", nanos);
+        // The command runs inside a group, so the marker line is parsed
+        // together with it and cannot be read as input by the command.
+        let script = format!(
+            "{{ {}\n}}; __agent_status=$?; printf '\\n%s%s:%s:%s\\n' '{}' '{}' \"$__agent_status\" \"$PWD\"\n",
+            command, MARKER_PREFIX, id
+        );
+        self.writer.write_all(script.as_bytes())?;
+        self.running = Some(format!("\n{}{}:", MARKER_PREFIX, id));
+        Ok(())
+    }
+
+    /// Waits for the running command's marker until `deadline`, moving the
+    /// command's output into the capture.
+    async fn wait(&mut self, deadline: Instant) -> Outcome {
+        loop {
+            if let Some(outcome) = self.take_completion() {
+                return outcome;
+            }
+            match tokio::time::timeout_at(deadline, self.output.recv()).await {
+                Ok(Some(chunk)) => self.pending.extend_from_slice(&chunk),
+                Ok(None) => {
+                    let pending = std::mem::take(&mut self.pending);
+                    self.capture.push(&pending);
+                    self.running = None;
+                    return Outcome::Exited;
+                }
+                Err(_) => return Outcome::Running,
+            }
+        }
+    }
+
+    /// Moves pending output into the capture, up to the completion marker
+    /// if it has been read in full.
+    fn take_completion(&mut self) -> Option<Outcome> {
+        let marker = self.running.as_deref()?.as_bytes();
+        let Some(start) = find(&self.pending, marker) else {
+            // Keep back the start of a marker split across reads.
+            let keep = (1..marker.len().min(self.pending.len() + 1))
+                .rev()
+                .find(|&n| self.pending.ends_with(&marker[..n]))
+                .unwrap_or(0);
+            let flushed: Vec<u8> = self.pending.drain(..self.pending.len() - keep).collect();
+            self.capture.push(&flushed);
+            return None;
+        };
+
+        let rest = &self.pending[start + marker.len()..];
+        let end = rest.iter().position(|&b| b == b'\n')?;
+        let line = String::from_utf8_lossy(&rest[..end]).into_owned();
+        let (code, cwd) = line.split_once(':').unwrap_or((&line, ""));
+        let outcome = Outcome::Completed {
+            exit_code: code.parse().ok(),
+            cwd: cwd.to_string(),
+        };
+
+        let after: Vec<u8> = rest[end + 1..].to_vec();
+        self.capture.push(&self.pending[..start]);
+        self.pending = after;
+        self.running = None;
+        Some(outcome)
+    }
+
+    /// Runs an empty command to check that the shell responds, discarding
+    /// its output.
+    ///
+    /// # Returns
+    ///
+    /// Whether the shell responded within `SYNC_TIMEOUT`.
+    async fn sync(&mut self) -> Result<bool> {
+        self.send(":")?;
+        let outcome = self.wait(Instant::now() + SYNC_TIMEOUT).await;
+        self.take_output();
+        Ok(matches!(outcome, Outcome::Completed { .. }))
+    }
+
+    /// Sends Ctrl-C to the terminal's foreground process group.
+    fn interrupt(&mut self) -> Result<()> {
+        self.writer.write_all(&[0x03])?;
+        Ok(())
+    }
+
+    /// Returns the output captured since the last call and resets the
+    /// capture.
+    fn take_output(&mut self) -> String {
+        let limit = self.capture.limit();
+        std::mem::replace(&mut self.capture, OutputCapture::new(limit)).to_string_lossy()
+    }
+
+    /// Kills the shell and everything it started.
+    async fn kill(mut self) {
+        if let Some(pid) = self.child.id() {
+            kill_process_group(pid);
+        }
+        let _ = self.child.kill().await;
+    }
+}
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A tool that keeps a shell running for the whole agent run.
+///
+/// Unlike `SystemTool`, where every call is a separate `sh -c`, commands run
+/// in the same shell, so the working directory, variables and activated
+/// environments carry over between calls. The shell is attached to a
+/// pseudo-terminal and started on first use, in the same sandbox and under
+/// the same command policy as `SystemTool`. It is killed when the run ends.
+///
+/// A command that runs past its timeout keeps running; the model can wait
+/// for it or interrupt it with Ctrl-C. If the shell does not respond to the
+/// interrupt, as can happen with the bubblewrap and Docker backends, it is
+/// replaced with a fresh one.
+pub struct ShellSessionTool {
+    sandbox: Sandbox,
+    policy: CommandPolicy,
+    max_output_bytes: usize,
+    session: Mutex<Option<Session>>,
+}
+
+impl ShellSessionTool {
+    /// Creates a new `ShellSessionTool` that runs commands in the given
+    /// workspace, subject to the given policy.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if the configured sandbox
+    /// backend is not available.
+    pub fn new(
+        config: &SandboxConfig,
+        workspace: Workspace,
+        policy: CommandPolicy,
+    ) -> Result<Self> {
+        Ok(Self {
+            sandbox: Sandbox::new(config, workspace)?,
+            policy,
+            max_output_bytes: config.limits.max_output_bytes,
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Waits for the running command and describes the result.
+    async fn wait(&self, slot: &mut Option<Session>, timeout: Duration) -> ShellOutput {
+        let Some(session) = slot.as_mut() else {
+            return idle();
+        };
+        let outcome = session.wait(Instant::now() + timeout).await;
+        let output = session.take_output();
+        match outcome {
+            Outcome::Completed { exit_code, cwd } => ShellOutput {
+                status: Status::Completed,
+                exit_code,
+                cwd: Some(cwd),
+                output,
+            },
+            Outcome::Running => ShellOutput {
+                status: Status::Running,
+                exit_code: None,
+                cwd: None,
+                output,
+            },
+            Outcome::Exited => {
+                if let Some(session) = slot.take() {
+                    session.kill().await;
+                }
+                ShellOutput {
+                    status: Status::Exited,
+                    exit_code: None,
+                    cwd: None,
+                    output,
+                }
+            }
+        }
+    }
+
+    /// Interrupts the running command, restarting the shell if it does not
+    /// recover.
+    async fn interrupt(&self, slot: &mut Option<Session>) -> Result<ShellOutput> {
+        let Some(session) = slot.as_mut().filter(|s| s.running.is_some()) else {
+            return Ok(idle());
+        };
+        session.interrupt()?;
+        // Collect whatever the command printed before it stopped.
+        session
+            .wait(Instant::now() + Duration::from_millis(200))
+            .await;
+        let output = session.take_output();
+
+        if session.sync().await? {
+            return Ok(ShellOutput {
+                status: Status::Interrupted,
+                exit_code: None,
+                cwd: None,
+                output,
+            });
+        }
+        if let Some(session) = slot.take() {
+            session.kill().await;
+        }
+        Ok(ShellOutput {
+            status: Status::Exited,
+            exit_code: None,
+            cwd: None,
+            output,
+        })
+    }
+}
+
+/// The result of waiting for or interrupting an idle shell.
+fn idle() -> ShellOutput {
+    ShellOutput {
+        status: Status::Idle,
+        exit_code: None,
+        cwd: None,
+        output: String::new(),
+    }
+}
+
+#[async_trait]
+impl Tool for ShellSessionTool {
+    /// Returns the name of the tool, "ShellSessionTool".
+    fn name(&self) -> &str {
+        "ShellSessionTool"
+    }
+
+    /// Runs a command in the persistent shell, or manages the running one.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either a bare command, or a JSON object with an `action`
+    ///   (`run`, `wait`, `interrupt` or `restart`), the `command` to run,
+    ///   and an optional `timeout_seconds`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a JSON object with the shell's `status`, the
+    /// command's `exit_code` and the working directory once it completes,
+    /// and the output produced since the previous call; or a JSON
+    /// description of the denial if the policy rejects the command.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = ShellArgs::parse(args)?;
+        let timeout = args
+            .timeout_seconds
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.sandbox.wall_time());
+        let mut slot = self.session.lock().await;
+
+        let output = match args.action {
+            ShellAction::Run => {
+                let command = args
+                    .command
+                    .filter(|command| !command.trim().is_empty())
+                    .ok_or_else(|| anyhow!("The run action requires a command"))?;
+                let decision = self.policy.check(&command);
+                if let Decision::Denied { .. } = decision {
+                    return Ok(serde_json::to_string_pretty(&decision)?);
+                }
+
+                if slot.as_ref().is_some_and(|s| s.running.is_some()) {
+                    ShellOutput {
+                        status: Status::Busy,
+                        exit_code: None,
+                        cwd: None,
+                        output: String::new(),
+                    }
+                } else {
+                    if slot.is_none() {
+                        *slot = Some(Session::start(&self.sandbox, self.max_output_bytes).await?);
+                    }
+                    if let Some(session) = slot.as_mut() {
+                        session.send(&command)?;
+                    }
+                    self.wait(&mut slot, timeout).await
+                }
+            }
+            ShellAction::Wait => self.wait(&mut slot, timeout).await,
+            ShellAction::Interrupt => self.interrupt(&mut slot).await?,
+            ShellAction::Restart => {
+                if let Some(session) = slot.take() {
+                    session.kill().await;
+                }
+                *slot = Some(Session::start(&self.sandbox, self.max_output_bytes).await?);
+                idle()
+            }
+        };
+        Ok(serde_json::to_string_pretty(&output)?)
+    }
+
+    /// Kills the shell, if one was started.
+    async fn shutdown(&self) {
+        if let Some(session) = self.session.lock().await.take() {
+            session.kill().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::command_policy::CommandPolicyConfig;
+    use serde_json::{json, Value};
+
+    fn tool(dir: &std::path::Path) -> ShellSessionTool {
+        let workspace = Workspace::new(dir).unwrap();
+        let policy = CommandPolicy::new(&CommandPolicyConfig::default()).unwrap();
+        ShellSessionTool::new(&SandboxConfig::default(), workspace, policy).unwrap()
+    }
+
+    async fn call(tool: &ShellSessionTool, args: Value) -> Value {
+        let result = tool.execute(&args.to_string()).await.unwrap();
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_state_persists_between_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let tool = tool(dir.path());
+
+        let result = call(&tool, json!({ "command": "cd sub && export GREETING=hi" })).await;
+        assert_eq!(result["status"], "completed");
+        assert_eq!(result["exit_code"], 0);
+
+        let result = call(
+            &tool,
+            json!({ "command": "echo $GREETING; basename $PWD; false" }),
+        )
+        .await;
+        assert_eq!(result["output"], "hi\nsub\n");
+        assert_eq!(result["exit_code"], 1);
+
+        tool.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_long_running_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = tool(dir.path());
+
+        let result = call(
+            &tool,
+            json!({ "command": "echo started; sleep 30", "timeout_seconds": 1 }),
+        )
+        .await;
+        assert_eq!(result["status"], "running");
+        assert!(result["output"].as_str().unwrap().starts_with("started"));
+
+        let result = call(&tool, json!({ "command": "echo again" })).await;
+        assert_eq!(result["status"], "busy");
+
+        let result = call(&tool, json!({ "action": "interrupt" })).await;
+        assert!(result["status"] == "interrupted" || result["status"] == "exited");
+
+        let result = call(&tool, json!({ "command": "echo ok" })).await;
+        assert_eq!(result["status"], "completed");
+        assert_eq!(result["output"], "ok\n");
+
+        tool.shutdown().await;
+    }
+}
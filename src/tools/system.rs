@@ -1,13 +1,95 @@
-use crate::tools::Tool;
-use anyhow::Result;
+use crate::tools::{
+    command_policy::{CommandPolicy, Decision},
+    process::{kill_process_group, OutputCapture},
+    sandbox::{Sandbox, SandboxConfig},
+    workspace::Workspace,
+    Tool,
+};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use tokio::process::Command;
+use serde::Serialize;
+use std::{process::Stdio, time::Duration};
+use tokio::task::JoinHandle;
+
+/// How long to keep reading output after the command has exited, in case a
+/// background child still holds the pipes open.
+const DRAIN_GRACE: Duration = Duration::from_secs(2);
+
+/// The result of a command, as returned to the model.
+#[derive(Serialize, Debug)]
+struct CommandOutput<'a> {
+    command: &'a str,
+    /// The exit code, or `None` if the command was killed by a signal.
+    exit_code: Option<i32>,
+    timed_out: bool,
+    stdout: String,
+    stderr: String,
+}
 
 /// A tool for executing system commands.
 ///
-/// This tool allows the agent to run arbitrary shell commands. It captures
-/// both stdout and stderr and returns them as a single string.
-pub struct SystemTool;
+/// This tool allows the agent to run arbitrary shell commands. With the
+/// `sandbox` feature enabled, commands run inside the sandbox backend
+/// selected in the configuration; otherwise they run directly on the host.
+///
+/// Each command runs in its own process group, which is killed as a whole
+/// once the wall-clock limit is reached. Output is decoded lossily and, past
+/// `max_output_bytes`, reduced to its beginning and end.
+///
+/// Every command is first checked against a `CommandPolicy`. Denied commands
+/// are not run; instead the model receives a JSON object explaining which
+/// part of the command was rejected and why.
+pub struct SystemTool {
+    sandbox: Sandbox,
+    policy: CommandPolicy,
+    max_output_bytes: usize,
+}
+
+impl SystemTool {
+    /// Creates a new `SystemTool` that runs commands in the given workspace,
+    /// subject to the given policy.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the tool, or an error if the configured sandbox
+    /// backend is not available.
+    pub fn new(
+        config: &SandboxConfig,
+        workspace: Workspace,
+        policy: CommandPolicy,
+    ) -> Result<Self> {
+        Ok(Self {
+            sandbox: Sandbox::new(config, workspace)?,
+            policy,
+            max_output_bytes: config.limits.max_output_bytes,
+        })
+    }
+
+    /// Starts reading a child's output stream in the background.
+    fn capture<R>(&self, reader: Option<R>) -> JoinHandle<std::io::Result<OutputCapture>>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        let capture = OutputCapture::new(self.max_output_bytes);
+        tokio::spawn(async move {
+            match reader {
+                Some(reader) => capture.read_from(reader).await,
+                None => Ok(capture),
+            }
+        })
+    }
+}
+
+/// Waits for an output reader, giving up once `deadline` has passed.
+async fn collect(
+    reader: JoinHandle<std::io::Result<OutputCapture>>,
+    deadline: tokio::time::Instant,
+) -> Result<String> {
+    let capture = tokio::time::timeout_at(deadline, reader)
+        .await
+        .map_err(|_| anyhow!("Timed out reading command output"))???;
+    Ok(capture.to_string_lossy())
+}
 
 #[async_trait]
 impl Tool for SystemTool {
@@ -24,59 +106,103 @@ impl Tool for SystemTool {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the combined stdout and stderr of the command,
-    /// or an error if the command fails to execute.
+    /// A `Result` containing a JSON object with the command's exit code,
+    /// whether it timed out, and its stdout and stderr, or a JSON
+    /// description of the denial if the policy rejects the command. A
+    /// non-zero exit code is not an error; an error is returned only if the
+    /// command could not be started.
     #[tracing::instrument(skip(self))]
     async fn execute(&self, args: &str) -> Result<String> {
-        #[cfg(feature = "sandbox")]
-        {
-            // Run the command inside a Docker container for sandboxing.
-            // This requires Docker to be installed and running on the host.
-            let output = Command::new("docker")
-                .arg("run")
-                .arg("--rm")
-                .arg("alpine")
-                .arg("sh")
-                .arg("-c")
-                .arg(args)
-                .output()
-                .await?;
-
-            // Combine stdout and stderr to capture all output.
-            let stdout = String::from_utf8(output.stdout)?;
-            let stderr = String::from_utf8(output.stderr)?;
-            let combined_output = format!("{}\n{}", stdout, stderr);
-
-            if output.status.success() {
-                Ok(combined_output)
-            } else {
-                Err(anyhow::anyhow!(
-                    "Command failed: {}. Output:\n{}",
-                    args,
-                    combined_output
-                ))
-            }
+        let decision = self.policy.check(args);
+        if let Decision::Denied { .. } = decision {
+            return Ok(serde_json::to_string_pretty(&decision)?);
         }
-        #[cfg(not(feature = "sandbox"))]
-        {
-            // Run the command directly on the host.
-            // This is not secure and should only be used for testing.
-            let output = Command::new("sh").arg("-c").arg(args).output().await?;
-
-            // Combine stdout and stderr to capture all output.
-            let stdout = String::from_utf8(output.stdout)?;
-            let stderr = String::from_utf8(output.stderr)?;
-            let combined_output = format!("{}\n{}", stdout, stderr);
-
-            if output.status.success() {
-                Ok(combined_output)
-            } else {
-                Err(anyhow::anyhow!(
-                    "Command failed: {}. Output:\n{}",
-                    args,
-                    combined_output
-                ))
-            }
+
+        let mut command = self.sandbox.command(args).await?;
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+        let pid = child.id();
+
+        let stdout = self.capture(child.stdout.take());
+        let stderr = self.capture(child.stderr.take());
+
+        let (status, timed_out) =
+            match tokio::time::timeout(self.sandbox.wall_time(), child.wait()).await {
+                Ok(status) => (status?, false),
+                Err(_) => {
+                    if let Some(pid) = pid {
+                        kill_process_group(pid);
+                    }
+                    (child.wait().await?, true)
+                }
+            };
+
+        // Stop anything the command left running in the background, so
+        // that it cannot hold the output pipes open.
+        let deadline = tokio::time::Instant::now() + DRAIN_GRACE;
+        let (stdout, stderr) = tokio::join!(collect(stdout, deadline), collect(stderr, deadline));
+        if let Some(pid) = pid {
+            kill_process_group(pid);
         }
+
+        let output = CommandOutput {
+            command: args,
+            exit_code: status.code(),
+            timed_out,
+            stdout: stdout?,
+            stderr: stderr?,
+        };
+        Ok(serde_json::to_string_pretty(&output)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::command_policy::CommandPolicyConfig;
+
+    fn tool(dir: &std::path::Path, config: SandboxConfig) -> SystemTool {
+        let workspace = Workspace::new(dir).unwrap();
+        let policy = CommandPolicy::new(&CommandPolicyConfig::default()).unwrap();
+        SystemTool::new(&config, workspace, policy).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reports_exit_code_and_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = tool(dir.path(), SandboxConfig::default());
+
+        let result = tool
+            .execute("echo out; echo err >&2; exit 3")
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(result["exit_code"], 3);
+        assert_eq!(result["timed_out"], false);
+        assert_eq!(result["stdout"], "out\n");
+        assert_eq!(result["stderr"], "err\n");
+    }
+
+    #[tokio::test]
+    async fn test_kills_process_group_on_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = SandboxConfig::default();
+        config.limits.wall_time_seconds = 1;
+        let tool = tool(dir.path(), config);
+
+        let start = std::time::Instant::now();
+        let result = tool
+            .execute("sleep 30 & sleep 30; echo done")
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(result["timed_out"], true);
+        assert_eq!(result["exit_code"], serde_json::Value::Null);
+        assert!(start.elapsed() < Duration::from_secs(10));
     }
-}
\ No newline at end of file
+}
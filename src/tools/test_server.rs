@@ -0,0 +1,145 @@
+//! A minimal HTTP server for tests of the tools that access the web.
+
+use reqwest::Url;
+use std::sync::{Arc, Mutex};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A canned response to requests for one path.
+pub struct Fixture {
+    path: String,
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+    /// Respond with the request itself instead of `body`.
+    echo: bool,
+    /// Respond with what this returns for the request instead of `body`.
+    handler: Option<fn(&str) -> String>,
+}
+
+/// A `200 OK` response with the given content type.
+pub fn ok(path: &str, content_type: &str, body: &str) -> Fixture {
+    Fixture {
+        path: path.to_string(),
+        status: "200 OK",
+        headers: vec![("Content-Type", content_type.to_string())],
+        body: body.to_string(),
+        echo: false,
+        handler: None,
+    }
+}
+
+/// A `302 Found` redirect to `location`.
+pub fn redirect(path: &str, location: &str) -> Fixture {
+    Fixture {
+        path: path.to_string(),
+        status: "302 Found",
+        headers: vec![("Location", location.to_string())],
+        body: String::new(),
+        echo: false,
+        handler: None,
+    }
+}
+
+/// A `200 OK` response whose body is the request, head and body, as
+/// received.
+pub fn echo(path: &str) -> Fixture {
+    Fixture {
+        path: path.to_string(),
+        status: "200 OK",
+        headers: vec![("Content-Type", "text/plain".to_string())],
+        body: String::new(),
+        echo: true,
+        handler: None,
+    }
+}
+
+/// A `200 OK` response whose body `handler` makes from the request, head
+/// and body, as received.
+pub fn handle(path: &str, content_type: &str, handler: fn(&str) -> String) -> Fixture {
+    Fixture {
+        path: path.to_string(),
+        status: "200 OK",
+        headers: vec![("Content-Type", content_type.to_string())],
+        body: String::new(),
+        echo: false,
+        handler: Some(handler),
+    }
+}
+
+/// Serves the fixtures on a local port until the test ends. Fixtures match
+/// the request path with or without its query string, and other paths get
+/// `404 Not Found`.
+///
+/// # Returns
+///
+/// The base URL of the server, and a log of the paths requested, in order.
+pub async fn serve(fixtures: Vec<Fixture>) -> (Url, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await;
+            let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+            log.lock().unwrap().push(path.clone());
+
+            let without_query = path.split('?').next().unwrap_or_default();
+            let fixture = fixtures
+                .iter()
+                .find(|f| f.path == path || f.path == without_query);
+            let mut response = match fixture {
+                Some(fixture) => {
+                    let handled;
+                    let body = match fixture.handler {
+                        Some(handler) => {
+                            handled = handler(&request);
+                            &handled
+                        }
+                        None if fixture.echo => &request,
+                        None => &fixture.body,
+                    };
+                    let mut head = format!("HTTP/1.1 {}\r\n", fixture.status);
+                    for (name, value) in &fixture.headers {
+                        head.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    head + &format!("Content-Length: {}\r\n", body.len()) + "\r\n" + body
+                }
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            };
+            response = response.replacen("\r\n", "\r\nConnection: close\r\n", 1);
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    (base, requests)
+}
+
+/// Reads a request head and, if it has a `Content-Length`, its body.
+async fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        let Some(end) = text.find("\r\n\r\n") else {
+            if n == 0 {
+                return text.into_owned();
+            }
+            continue;
+        };
+        let length = text[..end]
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        if n == 0 || request.len() >= end + 4 + length {
+            return text.into_owned();
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use super::robots::Robots;
+use crate::tools::http::HttpClient;
+use anyhow::Result;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// The options for a crawl, given as the `crawl` argument of
+/// `WebScraperTool`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlOptions {
+    /// The maximum number of pages to fetch.
+    pub max_pages: usize,
+    /// How many links away from the start page to follow. The start page is
+    /// at depth 0.
+    pub max_depth: usize,
+    /// Only follow links to the start page's host.
+    pub same_domain: bool,
+    /// The minimum delay between requests to the same host, in
+    /// milliseconds. A longer `Crawl-delay` in robots.txt takes precedence.
+    pub delay_ms: u64,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        Self {
+            max_pages: 10,
+            max_depth: 2,
+            same_domain: true,
+            delay_ms: 1000,
+        }
+    }
+}
+
+/// The upper bound on the number of pages a single crawl may fetch.
+pub const MAX_PAGES: usize = 100;
+
+/// A crawled page, or the reason it could not be crawled.
+pub enum Visit<T> {
+    Page(T),
+    Failed { url: Url, error: String },
+}
+
+/// Crawls outward from `start` breadth-first.
+///
+/// Robots.txt is fetched once per host and honored, and requests to the
+/// same host are spaced by the configured delay. Each fetched page is passed
+/// to `visit`, which returns what to keep for it together with the links it
+/// contains.
+///
+/// # Returns
+///
+/// A `Result` with the visits in crawl order, and the URLs skipped because
+/// robots.txt disallows them. Errors fetching individual pages are recorded
+/// as failed visits rather than ending the crawl.
+pub async fn crawl<T, F, Fut>(
+    client: &HttpClient,
+    user_agent: &str,
+    start: Url,
+    options: &CrawlOptions,
+    mut visit: F,
+) -> Result<(Vec<Visit<T>>, Vec<Url>)>
+where
+    F: FnMut(Url) -> Fut,
+    Fut: Future<Output = Result<(T, Vec<Url>)>>,
+{
+    let max_pages = options.max_pages.min(MAX_PAGES);
+    let delay = Duration::from_millis(options.delay_ms);
+    let mut robots: HashMap<String, Robots> = HashMap::new();
+    let mut last_request: HashMap<String, Instant> = HashMap::new();
+    let mut seen: HashSet<Url> = HashSet::from([normalize(&start)]);
+    let mut queue = VecDeque::from([(start.clone(), 0)]);
+    let mut visits = Vec::new();
+    let mut blocked = Vec::new();
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if visits.len() >= max_pages {
+            break;
+        }
+        let origin = url.origin().ascii_serialization();
+        if !robots.contains_key(&origin) {
+            let rules = fetch_robots(client, &url, user_agent).await;
+            robots.insert(origin.clone(), rules);
+        }
+        let rules = &robots[&origin];
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        if !rules.allows(&path) {
+            blocked.push(url);
+            continue;
+        }
+
+        let delay = rules.crawl_delay.map_or(delay, |crawl| crawl.max(delay));
+        if let Some(last) = last_request.get(&origin) {
+            tokio::time::sleep_until(*last + delay).await;
+        }
+        last_request.insert(origin, Instant::now());
+
+        match visit(url.clone()).await {
+            Ok((page, links)) => {
+                visits.push(Visit::Page(page));
+                if depth >= options.max_depth {
+                    continue;
+                }
+                for link in links {
+                    if !matches!(link.scheme(), "http" | "https") {
+                        continue;
+                    }
+                    if options.same_domain && link.host_str() != start.host_str() {
+                        continue;
+                    }
+                    if seen.insert(normalize(&link)) {
+                        queue.push_back((link, depth + 1));
+                    }
+                }
+            }
+            Err(error) => visits.push(Visit::Failed {
+                url,
+                error: error.to_string(),
+            }),
+        }
+    }
+    Ok((visits, blocked))
+}
+
+/// Strips the fragment, which does not change the page fetched.
+fn normalize(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url
+}
+
+/// Fetches and parses the robots.txt for the host of `url`.
+///
+/// As RFC 9309 asks, a missing file allows everything, while a server
+/// error or an unreachable host disallows everything.
+async fn fetch_robots(client: &HttpClient, url: &Url, user_agent: &str) -> Robots {
+    let Ok(robots_url) = url.join("/robots.txt") else {
+        return Robots::allow_all();
+    };
+    let response = match client.get(robots_url).await {
+        Ok(response) => response,
+        Err(_) => return Robots::disallow_all(),
+    };
+    let status = response.status;
+    if status.is_success() {
+        Robots::parse(&response.body, user_agent)
+    } else if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+        Robots::allow_all()
+    } else {
+        Robots::disallow_all()
+    }
+}
@@ -0,0 +1,377 @@
+use super::readability::is_noise;
+use ego_tree::NodeRef;
+use reqwest::Url;
+use scraper::{ElementRef, Node};
+
+/// Converts HTML elements to Markdown.
+///
+/// Headings, paragraphs, emphasis, links, images, lists, block quotes,
+/// tables and code blocks are preserved; other elements contribute only
+/// their text. Noise such as scripts and navigation is dropped. Relative
+/// links and image sources are resolved against `base`.
+///
+/// # Returns
+///
+/// The Markdown for all elements, separated by blank lines.
+pub fn to_markdown(elements: &[ElementRef], base: Option<&Url>) -> String {
+    let mut renderer = Renderer::new(base);
+    for element in elements {
+        renderer.element(*element);
+        renderer.block_break();
+    }
+    tidy(&renderer.out)
+}
+
+struct Renderer<'a> {
+    base: Option<&'a Url>,
+    out: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(base: Option<&'a Url>) -> Self {
+        Self {
+            base,
+            out: String::new(),
+        }
+    }
+
+    /// Renders the children of `element` on their own and returns the
+    /// result, for content that needs post-processing such as list items.
+    fn render_children(&self, element: ElementRef) -> String {
+        let mut renderer = Renderer::new(self.base);
+        renderer.children(element);
+        tidy(&renderer.out)
+    }
+
+    /// Renders the children of `element` on a single line.
+    fn render_inline(&self, element: ElementRef) -> String {
+        self.render_children(element)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            self.node(child);
+        }
+    }
+
+    fn node(&mut self, node: NodeRef<Node>) {
+        match node.value() {
+            Node::Text(text) => self.text(text),
+            Node::Element(_) => {
+                if let Some(element) = ElementRef::wrap(node) {
+                    self.element(element);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Appends text with its whitespace collapsed, as a browser would.
+    fn text(&mut self, text: &str) {
+        let mut collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.starts_with(char::is_whitespace) && !collapsed.is_empty() {
+            collapsed.insert(0, ' ');
+        }
+        if text.ends_with(char::is_whitespace) && !collapsed.ends_with(' ') {
+            collapsed.push(' ');
+        }
+        if self.out.is_empty() || self.out.ends_with([' ', '\n']) {
+            self.out.push_str(collapsed.trim_start());
+        } else {
+            self.out.push_str(&collapsed);
+        }
+    }
+
+    /// Ends the current block, so that the next output starts a new
+    /// paragraph.
+    fn block_break(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\n']).len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() {
+            self.out.push_str("\n\n");
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        if is_noise(element.value()) {
+            return;
+        }
+        let name = element.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let text = self.render_inline(element);
+                if !text.is_empty() {
+                    let level = name[1..].parse().unwrap_or(1);
+                    self.block_break();
+                    self.out.push_str(&"#".repeat(level));
+                    self.out.push(' ');
+                    self.out.push_str(&text);
+                    self.block_break();
+                }
+            }
+            "p" | "div" | "section" | "article" | "main" | "header" | "figure" | "figcaption"
+            | "details" | "summary" | "dl" | "dt" | "dd" | "address" => {
+                self.block_break();
+                self.children(element);
+                self.block_break();
+            }
+            "br" => {
+                let trimmed = self.out.trim_end_matches(' ').len();
+                self.out.truncate(trimmed);
+                self.out.push('\n');
+            }
+            "hr" => {
+                self.block_break();
+                self.out.push_str("---");
+                self.block_break();
+            }
+            "strong" | "b" => self.wrap(element, "**"),
+            "em" | "i" => self.wrap(element, "*"),
+            "del" | "s" | "strike" => self.wrap(element, "~~"),
+            "code" | "kbd" | "samp" => {
+                let code: String = element.text().collect();
+                if !code.trim().is_empty() {
+                    let fence = if code.contains('`') { "``" } else { "`" };
+                    self.out.push_str(&format!("{fence}{}{fence}", code.trim()));
+                }
+            }
+            "a" => self.link(element),
+            "img" => self.image(element),
+            "pre" => self.code_block(element),
+            "ul" | "ol" => self.list(element, name == "ol"),
+            "blockquote" => {
+                let quote = self.render_children(element);
+                if !quote.is_empty() {
+                    self.block_break();
+                    for line in quote.lines() {
+                        self.out.push_str(if line.is_empty() { ">" } else { "> " });
+                        self.out.push_str(line);
+                        self.out.push('\n');
+                    }
+                    self.block_break();
+                }
+            }
+            "table" => self.table(element),
+            _ => self.children(element),
+        }
+    }
+
+    fn wrap(&mut self, element: ElementRef, marker: &str) {
+        let text = self.render_inline(element);
+        if !text.is_empty() {
+            self.out.push_str(marker);
+            self.out.push_str(&text);
+            self.out.push_str(marker);
+        }
+    }
+
+    /// Resolves a link or image source against the page URL.
+    fn resolve(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with("javascript:") {
+            return None;
+        }
+        match self.base {
+            Some(base) => base.join(href).ok().map(String::from),
+            None => Some(href.to_string()),
+        }
+    }
+
+    fn link(&mut self, element: ElementRef) {
+        let text = self.render_inline(element);
+        if text.is_empty() {
+            return;
+        }
+        match element
+            .value()
+            .attr("href")
+            .and_then(|href| self.resolve(href))
+        {
+            Some(href) if !href.starts_with('#') => {
+                self.out.push_str(&format!("[{}]({})", text, href));
+            }
+            _ => self.out.push_str(&text),
+        }
+    }
+
+    fn image(&mut self, element: ElementRef) {
+        let alt = element.value().attr("alt").unwrap_or_default().trim();
+        if let Some(src) = element
+            .value()
+            .attr("src")
+            .and_then(|src| self.resolve(src))
+        {
+            if !src.starts_with("data:") {
+                self.out.push_str(&format!("![{}]({})", alt, src));
+            }
+        }
+    }
+
+    fn code_block(&mut self, element: ElementRef) {
+        let code: String = element.text().collect();
+        let code = code.trim_matches('\n');
+        if code.trim().is_empty() {
+            return;
+        }
+        // Highlighters put the language in a class like `language-rust`,
+        // on either the `pre` or the `code` inside it.
+        let language = std::iter::once(element)
+            .chain(element.children().filter_map(ElementRef::wrap))
+            .flat_map(|e| e.value().classes())
+            .find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+            })
+            .unwrap_or_default();
+
+        self.block_break();
+        self.out
+            .push_str(&format!("```{}\n{}\n```", language, code));
+        self.block_break();
+    }
+
+    fn list(&mut self, element: ElementRef, ordered: bool) {
+        let start = element
+            .value()
+            .attr("start")
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(1);
+        let items: Vec<String> = element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li")
+            .map(|item| self.render_children(item))
+            .filter(|item| !item.is_empty())
+            .collect();
+        if items.is_empty() {
+            return;
+        }
+
+        self.block_break();
+        for (index, item) in items.iter().enumerate() {
+            let marker = if ordered {
+                format!("{}. ", start + index)
+            } else {
+                "- ".to_string()
+            };
+            let indent = " ".repeat(marker.len());
+            for (line_number, line) in item.lines().filter(|l| !l.is_empty()).enumerate() {
+                self.out
+                    .push_str(if line_number == 0 { &marker } else { &indent });
+                self.out.push_str(line);
+                self.out.push('\n');
+            }
+        }
+        self.block_break();
+    }
+
+    fn table(&mut self, element: ElementRef) {
+        let rows: Vec<Vec<String>> = rows(element)
+            .into_iter()
+            .map(|row| {
+                row.children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                    .map(|cell| self.render_inline(cell).replace('|', "\\|"))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty())
+            .collect();
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        self.block_break();
+        for (index, row) in rows.iter().enumerate() {
+            let cells = (0..columns).map(|i| row.get(i).map_or("", String::as_str));
+            self.out.push_str("| ");
+            self.out.push_str(&cells.collect::<Vec<_>>().join(" | "));
+            self.out.push_str(" |\n");
+            if index == 0 {
+                self.out.push('|');
+                self.out.push_str(&" --- |".repeat(columns));
+                self.out.push('\n');
+            }
+        }
+        self.block_break();
+    }
+}
+
+/// Returns the rows of a table, excluding those of nested tables.
+fn rows(table: ElementRef) -> Vec<ElementRef> {
+    let mut rows = Vec::new();
+    for child in table.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "tr" => rows.push(child),
+            "thead" | "tbody" | "tfoot" => rows.extend(
+                child
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|row| row.value().name() == "tr"),
+            ),
+            _ => {}
+        }
+    }
+    rows
+}
+
+/// Removes trailing whitespace and runs of blank lines, except inside code
+/// blocks.
+fn tidy(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut in_code = false;
+    let mut blank = false;
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            in_code = !in_code;
+        }
+        let line = if in_code { line } else { line.trim_end() };
+        if line.is_empty() && !in_code {
+            if !blank && !out.is_empty() {
+                out.push('\n');
+            }
+            blank = true;
+            continue;
+        }
+        blank = false;
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::Html;
+
+    #[test]
+    fn test_to_markdown() {
+        let html = r#"<div>
+            <h2>Install <em>now</em></h2>
+            <p>Read the <a href="/docs/guide">guide</a> first.<br>Then run:</p>
+            <pre><code class="language-sh">cargo build
+cargo test</code></pre>
+            <ol><li>One</li><li>Two<ul><li>Nested</li></ul></li></ol>
+            <table><tr><th>Name</th><th>Value</th></tr><tr><td>a|b</td><td><code>1</code></td></tr></table>
+            <script>alert(1)</script>
+        </div>"#;
+        let document = Html::parse_fragment(html);
+        let base = Url::parse("https://example.com/docs/").unwrap();
+
+        let markdown = to_markdown(&[document.root_element()], Some(&base));
+        assert_eq!(
+            markdown,
+            "## Install *now*\n\n\
+             Read the [guide](https://example.com/docs/guide) first.\nThen run:\n\n\
+             ```sh\ncargo build\ncargo test\n```\n\n\
+             1. One\n2. Two\n   - Nested\n\n\
+             | Name | Value |\n| --- | --- |\n| a\\|b | `1` |"
+        );
+    }
+}
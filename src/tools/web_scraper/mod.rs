@@ -0,0 +1,359 @@
+use crate::tools::http::{HttpClient, USER_AGENT};
+use crate::tools::Tool;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use crawler::{CrawlOptions, Visit};
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+mod crawler;
+mod markdown;
+mod readability;
+mod robots;
+
+/// The maximum number of links listed for a page.
+const MAX_LINKS: usize = 200;
+
+/// The arguments accepted by `WebScraperTool`.
+///
+/// The model can either pass a JSON object with these fields, or a bare
+/// string which is used as the `url` with all other options defaulted.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ScrapeArgs {
+    /// The URL of the page to scrape.
+    url: String,
+    /// A CSS selector for the elements to extract. Overrides the automatic
+    /// main-content detection.
+    #[serde(default)]
+    selector: Option<String>,
+    /// Convert the whole page body rather than just its main content.
+    #[serde(default)]
+    full_page: bool,
+    /// The maximum number of characters of Markdown to return per page.
+    #[serde(default = "default_max_chars")]
+    max_chars: usize,
+    /// List the links on the page after its content.
+    #[serde(default)]
+    links: bool,
+    /// Follow links from the page, returning every page crawled.
+    #[serde(default)]
+    crawl: Option<CrawlOptions>,
+}
+
+fn default_max_chars() -> usize {
+    20_000
+}
+
+impl ScrapeArgs {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        if args.starts_with('{') {
+            return serde_json::from_str(args)
+                .map_err(|e| anyhow!("Invalid arguments for WebScraperTool: {}", e));
+        }
+        Ok(Self {
+            url: args.to_string(),
+            selector: None,
+            full_page: false,
+            max_chars: default_max_chars(),
+            links: false,
+            crawl: None,
+        })
+    }
+}
+
+/// A link found on a page.
+struct Link {
+    url: Url,
+    text: String,
+}
+
+/// Returns the links on a page, resolved against `base`, without fragments
+/// and without duplicates. Links to non-HTTP schemes are left out.
+fn extract_links(document: &Html, base: &Url) -> Vec<Link> {
+    let anchors = Selector::parse("a[href]").unwrap();
+    let mut seen = HashSet::new();
+    document
+        .select(&anchors)
+        .filter_map(|anchor| {
+            let mut url = base.join(anchor.value().attr("href")?.trim()).ok()?;
+            url.set_fragment(None);
+            if !matches!(url.scheme(), "http" | "https") || !seen.insert(url.clone()) {
+                return None;
+            }
+            let text = anchor.text().collect::<String>();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            Some(Link { url, text })
+        })
+        .collect()
+}
+
+/// Cuts `text` down to `max_chars` characters, noting how many were left
+/// out.
+fn truncate(text: &mut String, max_chars: usize) {
+    if let Some((cut, _)) = text.char_indices().nth(max_chars) {
+        let omitted = text[cut..].chars().count();
+        text.truncate(cut);
+        text.push_str(&format!("\n\n... [{} more characters]", omitted));
+    }
+}
+
+/// Extracts the requested part of a page and converts it to Markdown,
+/// headed by the page title and URL.
+///
+/// # Returns
+///
+/// A `Result` with the Markdown and the links on the page, or an error if
+/// the selector is invalid or matches nothing.
+fn extract(html: &str, url: &Url, args: &ScrapeArgs) -> Result<(String, Vec<Link>)> {
+    let document = Html::parse_document(html);
+    let body = Selector::parse("body").unwrap();
+
+    let elements = match &args.selector {
+        Some(selector) => {
+            let selector = Selector::parse(selector)
+                .map_err(|e| anyhow!("Invalid CSS selector {:?}: {}", selector, e))?;
+            let elements: Vec<_> = document.select(&selector).collect();
+            if elements.is_empty() {
+                return Err(anyhow!("No elements match {:?}", args.selector));
+            }
+            elements
+        }
+        None => {
+            let content = if args.full_page {
+                Vec::new()
+            } else {
+                readability::main_content(&document)
+            };
+            if content.is_empty() {
+                document.select(&body).collect()
+            } else {
+                content
+            }
+        }
+    };
+
+    let mut output = String::new();
+    let title = Selector::parse("title").unwrap();
+    if let Some(title) = document.select(&title).next() {
+        let title = title.text().collect::<String>();
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !title.is_empty() {
+            output.push_str(&format!("# {}\n\n", title));
+        }
+    }
+    output.push_str(&format!("Source: {}\n\n", url));
+    output.push_str(&markdown::to_markdown(&elements, Some(url)));
+    truncate(&mut output, args.max_chars);
+    Ok((output, extract_links(&document, url)))
+}
+
+/// A tool for scraping websites.
+///
+/// This tool allows the agent to fetch a web page and read it as Markdown.
+/// By default only the main content is kept, found by Readability-style
+/// scoring so that navigation, sidebars and footers are left out; a CSS
+/// selector can target specific elements instead.
+///
+/// The tool can also list the links on a page, or crawl outward from it
+/// within limits on the number of pages and the link depth, honoring
+/// robots.txt and waiting between requests to the same host.
+pub struct WebScraperTool {
+    client: HttpClient,
+}
+
+impl WebScraperTool {
+    /// Creates a new `WebScraperTool` that fetches pages with the given
+    /// client.
+    pub fn new(client: HttpClient) -> Self {
+        Self { client }
+    }
+
+    /// Fetches a page and converts it to Markdown.
+    ///
+    /// Responses that are not HTML are returned as they are, without links.
+    async fn scrape(&self, url: Url, args: &ScrapeArgs) -> Result<(String, Vec<Link>)> {
+        let response = self.client.get(url).await?.error_for_status()?;
+        let is_html = response
+            .content_type
+            .as_deref()
+            .is_none_or(|value| value.contains("html"));
+
+        if is_html {
+            extract(&response.body, &response.url, args)
+        } else {
+            let mut output = format!("Source: {}\n\n{}", response.url, response.body.trim());
+            truncate(&mut output, args.max_chars);
+            Ok((output, Vec::new()))
+        }
+    }
+
+    /// Crawls outward from `start` and joins the pages found.
+    async fn crawl(&self, start: Url, args: &ScrapeArgs, options: &CrawlOptions) -> Result<String> {
+        let (visits, blocked) = crawler::crawl(
+            &self.client,
+            USER_AGENT,
+            start.clone(),
+            options,
+            |url| async move {
+                let (page, links) = self.scrape(url, args).await?;
+                Ok((page, links.into_iter().map(|link| link.url).collect()))
+            },
+        )
+        .await?;
+
+        let mut pages = Vec::new();
+        let mut failures = Vec::new();
+        for visit in visits {
+            match visit {
+                Visit::Page(page) => pages.push(page),
+                Visit::Failed { url, error } => failures.push(format!("- {}: {}", url, error)),
+            }
+        }
+
+        let mut output = format!("Crawled {} pages starting at {}.", pages.len(), start);
+        for page in pages {
+            output.push_str("\n\n---\n\n");
+            output.push_str(&page);
+        }
+        if !failures.is_empty() {
+            output.push_str("\n\n---\n\nFailed to fetch:\n");
+            output.push_str(&failures.join("\n"));
+        }
+        if !blocked.is_empty() {
+            output.push_str("\n\n---\n\nSkipped, disallowed by robots.txt:\n");
+            for url in blocked {
+                output.push_str(&format!("- {}\n", url));
+            }
+        }
+        Ok(output.trim_end().to_string())
+    }
+}
+
+#[async_trait]
+impl Tool for WebScraperTool {
+    /// Returns the name of the tool, "WebScraperTool".
+    fn name(&self) -> &str {
+        "WebScraperTool"
+    }
+
+    /// Executes the web scraping command.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Either the URL of the page to scrape, or a JSON object
+    ///   with the `url` and optionally a CSS `selector`, `full_page` to
+    ///   convert the whole body, `max_chars`, `links` to list the page's
+    ///   links, and `crawl` with `max_pages`, `max_depth`, `same_domain` and
+    ///   `delay_ms` to follow them.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the page content as Markdown, or every crawled page
+    /// in turn, or an error if the page cannot be fetched or the selector is
+    /// invalid.
+    #[tracing::instrument(skip(self))]
+    async fn execute(&self, args: &str) -> Result<String> {
+        let args = ScrapeArgs::parse(args)?;
+        let url = Url::parse(&args.url)?;
+        if let Some(options) = &args.crawl {
+            return self.crawl(url, &args, options).await;
+        }
+
+        let (mut output, links) = self.scrape(url, &args).await?;
+        if args.links && !links.is_empty() {
+            output.push_str("\n\n## Links\n\n");
+            for link in links.iter().take(MAX_LINKS) {
+                output.push_str(&format!("- [{}]({})\n", link.text, link.url));
+            }
+            if links.len() > MAX_LINKS {
+                output.push_str(&format!("- ... {} more\n", links.len() - MAX_LINKS));
+            }
+        }
+        Ok(output.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{
+        http::HttpConfig,
+        test_server::{ok, serve},
+    };
+
+    #[test]
+    fn test_extract_with_selector() {
+        let html = r#"<html><head><title> Docs </title></head><body>
+            <div class="a"><p>First</p></div><div class="b"><p>Second</p></div>
+        </body></html>"#;
+        let url = Url::parse("https://example.com/").unwrap();
+        let args =
+            ScrapeArgs::parse(r#"{"url": "https://example.com/", "selector": ".b"}"#).unwrap();
+
+        let (output, _) = extract(html, &url, &args).unwrap();
+        assert_eq!(output, "# Docs\n\nSource: https://example.com/\n\nSecond");
+    }
+
+    fn tool() -> WebScraperTool {
+        let config = HttpConfig {
+            allow_private_networks: true,
+            ..HttpConfig::default()
+        };
+        WebScraperTool::new(HttpClient::new(&config).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_links_are_resolved() {
+        let (base, _) = serve(vec![ok(
+            "/docs/",
+            "text/html",
+            r#"<p>See <a href="guide#intro">the guide</a>, <a href="/api">the API</a>,
+               <a href="mailto:a@b.c">mail</a> and <a href="guide">the guide again</a>.</p>"#,
+        )])
+        .await;
+
+        let tool = tool();
+        let args = serde_json::json!({ "url": base.join("/docs/").unwrap(), "links": true });
+        let output = tool.execute(&args.to_string()).await.unwrap();
+        assert!(output.ends_with(&format!(
+            "## Links\n\n- [the guide]({}docs/guide)\n- [the API]({}api)",
+            base, base
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_honors_depth_and_robots() {
+        let (base, requests) = serve(vec![
+            ok(
+                "/robots.txt",
+                "text/plain",
+                "User-agent: *\nDisallow: /private\n",
+            ),
+            ok(
+                "/",
+                "text/html",
+                r#"<title>Home</title><a href="/a">A</a> <a href="/private/x">P</a>
+                   <a href="http://other.invalid/">Elsewhere</a>"#,
+            ),
+            ok("/a", "text/html", r#"<title>A</title><a href="/b">B</a>"#),
+            ok("/b", "text/html", "<title>B</title>"),
+        ])
+        .await;
+
+        let tool = tool();
+        let args = serde_json::json!({
+            "url": base,
+            "crawl": { "max_depth": 1, "delay_ms": 0 },
+        });
+        let output = tool.execute(&args.to_string()).await.unwrap();
+
+        assert!(output.starts_with("Crawled 2 pages"));
+        assert!(output.contains("# Home") && output.contains("# A"));
+        assert!(output.contains(&format!("disallowed by robots.txt:\n- {}private/x", base)));
+        assert_eq!(*requests.lock().unwrap(), ["/robots.txt", "/", "/a"]);
+    }
+}
@@ -0,0 +1,298 @@
+use ego_tree::NodeId;
+use regex::Regex;
+use scraper::{node::Element, ElementRef, Html, Node, Selector};
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Elements that never hold the main content of a page.
+const NOISE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "embed", "nav",
+    "footer", "aside", "dialog", "button", "input", "select", "textarea", "link", "meta",
+];
+
+/// ARIA roles of page chrome rather than content.
+const NOISE_ROLES: &[&str] = &[
+    "navigation",
+    "banner",
+    "contentinfo",
+    "complementary",
+    "dialog",
+    "menu",
+    "menubar",
+];
+
+/// Class and id fragments that suggest an element is page chrome.
+fn unlikely() -> &'static Regex {
+    static UNLIKELY: OnceLock<Regex> = OnceLock::new();
+    UNLIKELY.get_or_init(|| {
+        Regex::new(
+            r"(?i)-ad-|advert|banner|breadcrumb|combx|comment|cookie|consent|disqus|footer|gdpr|masthead|menu|modal|navbar|newsletter|pager|popup|promo|related|share|sidebar|social|sponsor|subscribe|widget",
+        )
+        .unwrap()
+    })
+}
+
+/// Class and id fragments that suggest an element holds the content.
+fn likely() -> &'static Regex {
+    static LIKELY: OnceLock<Regex> = OnceLock::new();
+    LIKELY.get_or_init(|| {
+        Regex::new(r"(?i)article|body|content|entry|main|page|post|text|blog|story").unwrap()
+    })
+}
+
+/// Returns whether an element is page chrome, hidden, or otherwise not worth
+/// rendering: scripts, navigation, footers, cookie banners and the like.
+pub fn is_noise(element: &Element) -> bool {
+    if NOISE_TAGS.contains(&element.name()) {
+        return true;
+    }
+    if element.attr("hidden").is_some() || element.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    if let Some(style) = element.attr("style") {
+        let style = style.replace(' ', "").to_ascii_lowercase();
+        if style.contains("display:none") || style.contains("visibility:hidden") {
+            return true;
+        }
+    }
+    if element
+        .attr("role")
+        .is_some_and(|role| NOISE_ROLES.contains(&role))
+    {
+        return true;
+    }
+    if matches!(element.name(), "html" | "body" | "main" | "article") {
+        return false;
+    }
+    let names = class_and_id(element);
+    unlikely().is_match(&names) && !likely().is_match(&names)
+}
+
+fn class_and_id(element: &Element) -> String {
+    format!(
+        "{} {}",
+        element.attr("class").unwrap_or_default(),
+        element.attr("id").unwrap_or_default()
+    )
+}
+
+/// Returns the text of an element, skipping noise, with whitespace collapsed.
+pub fn visible_text(element: ElementRef) -> String {
+    let mut text = String::new();
+    collect_text(element, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn collect_text(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => {
+                out.push_str(text);
+                out.push(' ');
+            }
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    if !is_noise(child.value()) {
+                        collect_text(child, out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The fraction of an element's text that is inside links.
+fn link_density(element: ElementRef, text_len: usize) -> f64 {
+    if text_len == 0 {
+        return 0.0;
+    }
+    let links = Selector::parse("a").unwrap();
+    let link_len: usize = element
+        .select(&links)
+        .map(|link| visible_text(link).len())
+        .sum();
+    link_len as f64 / text_len as f64
+}
+
+/// The starting score of a candidate container, based on its tag and on
+/// its class and id.
+fn initial_score(element: &Element) -> f64 {
+    let tag = match element.name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = class_and_id(element);
+    let mut class = 0.0;
+    if likely().is_match(&names) {
+        class += 25.0;
+    }
+    if unlikely().is_match(&names) {
+        class -= 25.0;
+    }
+    tag + class
+}
+
+/// Returns whether an element is a paragraph for scoring purposes: a `p`,
+/// `pre`, `td` or `blockquote`, or a `div` used like one, without
+/// block-level children.
+fn is_paragraph(element: ElementRef) -> bool {
+    match element.value().name() {
+        "p" | "pre" | "td" | "blockquote" => true,
+        "div" => !element
+            .children()
+            .filter_map(ElementRef::wrap)
+            .any(|child| {
+                matches!(
+                    child.value().name(),
+                    "div"
+                        | "p"
+                        | "pre"
+                        | "table"
+                        | "ul"
+                        | "ol"
+                        | "blockquote"
+                        | "section"
+                        | "article"
+                )
+            }),
+        _ => false,
+    }
+}
+
+/// Collects the paragraphs under `element`, skipping noise.
+fn paragraphs<'a>(element: ElementRef<'a>, out: &mut Vec<ElementRef<'a>>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        if is_noise(child.value()) {
+            continue;
+        }
+        if is_paragraph(child) {
+            out.push(child);
+        }
+        paragraphs(child, out);
+    }
+}
+
+/// Finds the elements holding the main content of a page, in document
+/// order.
+///
+/// This follows the approach of Arc90's Readability: every paragraph adds a
+/// score based on its length and number of commas to its parent and, at a
+/// reduced weight, to its grandparent and great-grandparent. The container
+/// with the highest score, discounted by its link density, wins, together
+/// with any siblings that also score well.
+///
+/// # Returns
+///
+/// The content elements, or an empty vector if the page has no paragraphs
+/// of meaningful length.
+pub fn main_content(document: &Html) -> Vec<ElementRef<'_>> {
+    let root = document.root_element();
+    let mut found = Vec::new();
+    paragraphs(root, &mut found);
+
+    // Candidates in the order they were first scored, so that ties are
+    // broken deterministically.
+    let mut candidates: Vec<NodeId> = Vec::new();
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+    for paragraph in found {
+        let text = visible_text(paragraph);
+        if text.len() < 25 {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() / 100).min(3) as f64;
+
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(3);
+        for (level, ancestor) in ancestors.enumerate() {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                _ => 6.0,
+            };
+            *scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor.id());
+                initial_score(ancestor.value())
+            }) += score / divider;
+        }
+    }
+
+    let scored: Vec<(ElementRef, f64)> = candidates
+        .into_iter()
+        .filter_map(|id| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            let text_len = visible_text(element).len();
+            Some((
+                element,
+                scores[&id] * (1.0 - link_density(element, text_len)),
+            ))
+        })
+        .collect();
+    let Some(&(top, top_score)) = scored.iter().reduce(|best, candidate| {
+        if candidate.1 > best.1 {
+            candidate
+        } else {
+            best
+        }
+    }) else {
+        return Vec::new();
+    };
+
+    // Content is sometimes split across sibling containers, so keep those
+    // that score close to the winner, and long paragraphs next to it.
+    let threshold = (top_score * 0.2).max(10.0);
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return vec![top];
+    };
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == top.id() {
+                return true;
+            }
+            if is_noise(sibling.value()) {
+                return false;
+            }
+            let score = scored
+                .iter()
+                .find(|(element, _)| element.id() == sibling.id())
+                .map_or(0.0, |(_, score)| *score);
+            if score >= threshold {
+                return true;
+            }
+            if sibling.value().name() == "p" {
+                let text = visible_text(*sibling);
+                return text.len() > 80 && link_density(*sibling, text.len()) < 0.25;
+            }
+            false
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_main_content_skips_navigation_and_comments() {
+        let html = r#"<html><body>
+            <nav><ul><li><a href="/">Home</a></li><li><a href="/blog">Blog, news, and other things</a></li></ul></nav>
+            <div class="sidebar"><p>Subscribe to our newsletter, it is great, really, truly great.</p></div>
+            <div id="post">
+                <h1>Title</h1>
+                <p>The first paragraph of the article, which is long enough, has commas, and counts.</p>
+                <p>The second paragraph continues the story, adding detail, nuance, and more words.</p>
+            </div>
+            <div class="comments"><p>First comment, which says something, anything, at all really.</p></div>
+        </body></html>"#;
+        let document = Html::parse_document(html);
+
+        let content = main_content(&document);
+        assert_eq!(content.len(), 1);
+        assert_eq!(content[0].value().attr("id"), Some("post"));
+        assert!(!visible_text(content[0]).contains("Subscribe"));
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+/// An `Allow` or `Disallow` line from a robots.txt file.
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// A group of rules, applying to the user agents it names.
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// The rules from a robots.txt file that apply to one user agent, following
+/// RFC 9309.
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    /// The delay between requests asked for with `Crawl-delay`, if any.
+    pub crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// A policy that allows every path, used when a site has no robots.txt.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// A policy that disallows every path, used when a site's robots.txt
+    /// cannot be fetched because of a server error.
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parses a robots.txt file, keeping the groups that apply to
+    /// `user_agent`, or the `*` groups if none name it.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share one group.
+                    if in_rules || groups.is_empty() {
+                        groups.push(Group::default());
+                        in_rules = false;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // An empty `Disallow` allows everything.
+                    if let (Some(group), false) = (groups.last_mut(), value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    if let (Some(group), Ok(seconds)) = (groups.last_mut(), value.parse::<f64>()) {
+                        group.crawl_delay = Duration::try_from_secs_f64(seconds).ok();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Groups name the product token, e.g. `app` for `app/0.1.0`.
+        let token = user_agent.split('/').next().unwrap_or_default();
+        let token = token.to_ascii_lowercase();
+        let named = |group: &&Group| group.agents.contains(&token);
+        let matching: Vec<&Group> = if groups.iter().any(|g| named(&g)) {
+            groups.iter().filter(named).collect()
+        } else {
+            groups
+                .iter()
+                .filter(|group| group.agents.iter().any(|agent| agent == "*"))
+                .collect()
+        };
+
+        Self {
+            rules: matching
+                .iter()
+                .flat_map(|group| group.rules.iter().cloned())
+                .collect(),
+            crawl_delay: matching.iter().filter_map(|group| group.crawl_delay).max(),
+        }
+    }
+
+    /// Returns whether `path` (including any query string) may be fetched.
+    ///
+    /// The rule with the longest matching pattern wins, and `Allow` wins a
+    /// tie. Paths no rule matches are allowed.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Matches a robots.txt path pattern, in which `*` matches any sequence of
+/// characters and a trailing `$` anchors the end of the path. Patterns
+/// otherwise match any path they are a prefix of.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (index, part) in parts.iter().enumerate() {
+        let last = index == parts.len() - 1;
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_rules() {
+        let robots = Robots::parse(
+            "# comment\n\
+             User-agent: other\n\
+             Disallow: /\n\
+             \n\
+             User-agent: *\n\
+             Disallow: /private\n\
+             Allow: /private/public\n\
+             Disallow: /*.pdf$\n\
+             Crawl-delay: 2\n",
+            "app/0.1.0",
+        );
+
+        assert!(robots.allows("/"));
+        assert!(!robots.allows("/private/notes"));
+        assert!(robots.allows("/private/public/page"));
+        assert!(!robots.allows("/files/report.pdf"));
+        assert!(robots.allows("/files/report.pdf?download=1"));
+        assert_eq!(robots.crawl_delay, Some(Duration::from_secs(2)));
+
+        let robots = Robots::parse("User-agent: app\nDisallow: /\n", "app/0.1.0");
+        assert!(!robots.allows("/anything"));
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// The directory that file-oriented tools are confined to.
+///
+/// Every path handed to a tool by the model is resolved through
+/// `Workspace::resolve`, which rejects paths that would end up outside the
+/// workspace root, either lexically (`..`, absolute paths) or through a
+/// symlink.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    /// Creates a new `Workspace` rooted at the given directory.
+    ///
+    /// The root is canonicalized so that later containment checks compare
+    /// like with like.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root
+            .as_ref()
+            .canonicalize()
+            .map_err(|e| anyhow!("Invalid workspace root {}: {}", root.as_ref().display(), e))?;
+        Ok(Self { root })
+    }
+
+    /// Creates a `Workspace` rooted at the current working directory.
+    pub fn current() -> Result<Self> {
+        Self::new(std::env::current_dir()?)
+    }
+
+    /// Returns the canonical root directory of the workspace.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves a path supplied by the model to an absolute path inside the
+    /// workspace.
+    ///
+    /// Relative paths are interpreted relative to the workspace root. Absolute
+    /// paths are accepted only if they already point inside the root. The
+    /// path does not need to exist, so this can also be used for files that
+    /// are about to be created.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` with the resolved path, or an error if the path escapes the
+    /// workspace.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let path = path.trim();
+        let candidate = if path.is_empty() {
+            self.root.clone()
+        } else {
+            self.root.join(path)
+        };
+
+        let normalized = normalize(&candidate);
+        if !normalized.starts_with(&self.root) {
+            return Err(anyhow!("Path is outside the workspace: {}", path));
+        }
+
+        // Follow symlinks on the longest existing prefix of the path so that
+        // a link inside the workspace cannot be used to reach outside of it.
+        // A dangling symlink counts as existing, as writing through it would
+        // create its target.
+        let mut existing = normalized.as_path();
+        let mut rest = Vec::new();
+        while existing.symlink_metadata().is_err() {
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    rest.push(name.to_owned());
+                    existing = parent;
+                }
+                _ => break,
+            }
+        }
+        let mut resolved = existing.canonicalize().map_err(|e| {
+            if existing.is_symlink() {
+                anyhow!("Path goes through a broken symlink: {}", path)
+            } else {
+                e.into()
+            }
+        })?;
+        if !resolved.starts_with(&self.root) {
+            return Err(anyhow!("Path is outside the workspace: {}", path));
+        }
+        resolved.extend(rest.iter().rev());
+        Ok(resolved)
+    }
+
+    /// Returns `path` relative to the workspace root, for display to the
+    /// model.
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+}
+
+/// Lexically normalizes a path by removing `.` components and resolving `..`
+/// against the preceding component.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+
+        assert!(workspace.resolve("src/main.rs").is_ok());
+        assert!(workspace.resolve("src/../Cargo.toml").is_ok());
+        assert!(workspace.resolve("../outside.txt").is_err());
+        assert!(workspace.resolve("/etc/passwd").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_symlink_escapes() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+
+        assert!(workspace.resolve("link/secret.txt").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_dangling_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().join("created.txt");
+        std::os::unix::fs::symlink(&target, dir.path().join("link.txt")).unwrap();
+        let workspace = Workspace::new(dir.path()).unwrap();
+
+        assert_eq!(
+            workspace.resolve("link.txt").unwrap_err().to_string(),
+            "Path goes through a broken symlink: link.txt"
+        );
+        assert!(workspace.resolve("link.txt/inner").is_err());
+        assert!(!target.exists());
+    }
+}
@@ -0,0 +1,378 @@
+pub mod render;
+pub mod replay;
+
+use crate::{
+    llm::Usage,
+    run_log::{self, Entry, Event, RunLog},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// How a run ended.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Finished,
+    Failed,
+    /// The run is still going, or stopped without recording how.
+    Incomplete,
+}
+
+/// The structured record of a run: the tree of agents that worked on it,
+/// and every step they took, with timings and token counts.
+///
+/// Trajectories are built from run logs, and written next to them as
+/// `<run id>.trajectory.json` when a run ends.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    pub run_id: String,
+    pub task: String,
+    pub status: Status,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    /// When the run started, in milliseconds since the Unix epoch.
+    pub started_at_ms: u64,
+    /// The time from the start of the run to its last recorded event,
+    /// including any time it spent stopped before being resumed.
+    pub duration_ms: u64,
+    pub llm_calls: usize,
+    pub tool_calls: usize,
+    /// The tokens used by the LLM calls that reported them.
+    pub usage: Usage,
+    /// The agents the run was given to, normally just the supervisor.
+    pub agents: Vec<AgentRun>,
+}
+
+/// An agent working on a task.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AgentRun {
+    pub agent: String,
+    pub task: String,
+    /// When the agent started, in milliseconds since the run started.
+    pub offset_ms: u64,
+    /// How long the agent took, if it finished.
+    pub duration_ms: Option<u64>,
+    pub result: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+/// A step of an agent, in the order they happened. Offsets are in
+/// milliseconds since the run started.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Step {
+    /// The agent called its LLM.
+    Llm {
+        offset_ms: u64,
+        duration_ms: u64,
+        prompt: String,
+        response: String,
+        usage: Option<Usage>,
+    },
+    /// A supervisor routed a task to a worker.
+    Route {
+        offset_ms: u64,
+        worker: String,
+        task: String,
+    },
+    /// An executor decided on its next action.
+    Thought {
+        offset_ms: u64,
+        thought: String,
+        tool: String,
+        args: String,
+    },
+    /// An executor ran a tool.
+    Tool {
+        offset_ms: u64,
+        duration_ms: u64,
+        tool: String,
+        args: String,
+        observation: String,
+    },
+    /// The agent delegated a task to another agent.
+    Agent(AgentRun),
+    /// The run stopped with an error while this agent was working.
+    Failed { offset_ms: u64, error: String },
+    /// The run was resumed.
+    Resumed { offset_ms: u64 },
+}
+
+impl Trajectory {
+    /// Builds the trajectory of the run recorded in `entries`.
+    pub fn from_entries(entries: &[Entry]) -> Result<Self> {
+        let (run_id, task, started_at_ms) = match entries.first() {
+            Some(Entry {
+                time_ms,
+                event: Event::Started { run_id, task },
+            }) => (run_id.clone(), task.clone(), *time_ms),
+            _ => return Err(anyhow!("The run log does not start with a started event")),
+        };
+        let mut trajectory = Self {
+            run_id,
+            task,
+            status: Status::Incomplete,
+            result: None,
+            error: None,
+            started_at_ms,
+            duration_ms: 0,
+            llm_calls: 0,
+            tool_calls: 0,
+            usage: Usage::default(),
+            agents: Vec::new(),
+        };
+
+        // The agents that are working, innermost last.
+        let mut stack: Vec<AgentRun> = Vec::new();
+        for entry in &entries[1..] {
+            let offset_ms = entry.time_ms.saturating_sub(started_at_ms);
+            trajectory.duration_ms = trajectory.duration_ms.max(offset_ms);
+            match entry.event.clone() {
+                Event::Started { .. } => {}
+                Event::Resumed => {
+                    trajectory.status = Status::Incomplete;
+                    trajectory.error = None;
+                    if let Some(current) = stack.last_mut() {
+                        current.steps.push(Step::Resumed { offset_ms });
+                    }
+                }
+                Event::AgentStarted { agent, task } => stack.push(AgentRun {
+                    agent,
+                    task,
+                    offset_ms,
+                    duration_ms: None,
+                    result: None,
+                    steps: Vec::new(),
+                }),
+                Event::AgentFinished { agent, result } => {
+                    // Agents that did not record finishing end with the
+                    // first agent above them that did.
+                    let Some(position) = stack.iter().rposition(|run| run.agent == agent) else {
+                        continue;
+                    };
+                    while stack.len() > position + 1 {
+                        close(&mut stack, &mut trajectory.agents);
+                    }
+                    let current = stack.last_mut().unwrap();
+                    current.duration_ms = Some(offset_ms.saturating_sub(current.offset_ms));
+                    current.result = Some(result);
+                    close(&mut stack, &mut trajectory.agents);
+                }
+                Event::Llm {
+                    agent,
+                    prompt,
+                    response,
+                    duration_ms,
+                    usage,
+                } => {
+                    trajectory.llm_calls += 1;
+                    if let Some(usage) = usage {
+                        trajectory.usage.prompt_tokens += usage.prompt_tokens;
+                        trajectory.usage.completion_tokens += usage.completion_tokens;
+                    }
+                    // The call is recorded when it returns.
+                    let offset_ms = offset_ms.saturating_sub(duration_ms);
+                    current(&mut stack, &agent, &trajectory.task, offset_ms).push(Step::Llm {
+                        offset_ms,
+                        duration_ms,
+                        prompt,
+                        response,
+                        usage,
+                    });
+                }
+                Event::Route {
+                    agent,
+                    worker,
+                    task,
+                } => current(&mut stack, &agent, &trajectory.task, offset_ms).push(Step::Route {
+                    offset_ms,
+                    worker,
+                    task,
+                }),
+                Event::Thought {
+                    agent,
+                    thought,
+                    tool,
+                    args,
+                } => current(&mut stack, &agent, &trajectory.task, offset_ms).push(Step::Thought {
+                    offset_ms,
+                    thought,
+                    tool,
+                    args,
+                }),
+                Event::Tool {
+                    agent,
+                    tool,
+                    args,
+                    observation,
+                    duration_ms,
+                } => {
+                    trajectory.tool_calls += 1;
+                    let offset_ms = offset_ms.saturating_sub(duration_ms);
+                    current(&mut stack, &agent, &trajectory.task, offset_ms).push(Step::Tool {
+                        offset_ms,
+                        duration_ms,
+                        tool,
+                        args,
+                        observation,
+                    });
+                }
+                Event::Finished { result } => {
+                    trajectory.status = Status::Finished;
+                    trajectory.result = Some(result);
+                }
+                Event::Failed { error } => {
+                    if let Some(current) = stack.last_mut() {
+                        current.steps.push(Step::Failed {
+                            offset_ms,
+                            error: error.clone(),
+                        });
+                    }
+                    trajectory.status = Status::Failed;
+                    trajectory.error = Some(error);
+                }
+            }
+        }
+        while !stack.is_empty() {
+            close(&mut stack, &mut trajectory.agents);
+        }
+        Ok(trajectory)
+    }
+}
+
+/// Returns the steps of the innermost working agent, or of a new one for
+/// `agent` if it ran outside of `run_log::agent`.
+fn current<'a>(
+    stack: &'a mut Vec<AgentRun>,
+    agent: &str,
+    task: &str,
+    offset_ms: u64,
+) -> &'a mut Vec<Step> {
+    if stack.is_empty() {
+        stack.push(AgentRun {
+            agent: agent.to_string(),
+            task: task.to_string(),
+            offset_ms,
+            duration_ms: None,
+            result: None,
+            steps: Vec::new(),
+        });
+    }
+    &mut stack.last_mut().unwrap().steps
+}
+
+/// Moves the innermost working agent into the steps of the agent that
+/// delegated to it, or into `roots`.
+fn close(stack: &mut Vec<AgentRun>, roots: &mut Vec<AgentRun>) {
+    let Some(run) = stack.pop() else {
+        return;
+    };
+    match stack.last_mut() {
+        Some(parent) => parent.steps.push(Step::Agent(run)),
+        None => roots.push(run),
+    }
+}
+
+/// Returns where the trajectory of the run logged at `log` is written.
+fn trajectory_path(log: &Path) -> PathBuf {
+    log.with_extension("trajectory.json")
+}
+
+/// Writes the trajectory of the run logged at `log` next to the log.
+///
+/// # Returns
+///
+/// A `Result` with the path of the trajectory file, or an error if the log
+/// cannot be read or the file cannot be written.
+pub fn export(log: &Path) -> Result<PathBuf> {
+    let trajectory = Trajectory::from_entries(&run_log::read(log)?)?;
+    let path = trajectory_path(log);
+    fs::write(&path, serde_json::to_string_pretty(&trajectory)?)?;
+    Ok(path)
+}
+
+/// Loads a trajectory.
+///
+/// # Arguments
+///
+/// * `directory` - The directory run logs are kept in.
+/// * `run` - A run ID, the path of a run log, or the path of a trajectory
+///   file.
+///
+/// # Returns
+///
+/// A `Result` with the trajectory, which is built from the run log when
+/// there is one, or an error if the run cannot be found or read.
+pub fn load(directory: &Path, run: &str) -> Result<Trajectory> {
+    let path = Path::new(run);
+    if path.is_file() {
+        if run.ends_with(".json") {
+            let json = fs::read_to_string(path)?;
+            return serde_json::from_str(&json)
+                .map_err(|e| anyhow!("Invalid trajectory {}: {}", path.display(), e));
+        }
+        return Trajectory::from_entries(&run_log::read(path)?);
+    }
+    Trajectory::from_entries(&run_log::read(&RunLog::path(directory, run)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::Agent, executor::ExecutorAgent, llm::MockLlm, orchestrator::Orchestrator,
+        supervisor::SupervisorAgent,
+    };
+    use serde_json::json;
+    use std::{collections::HashMap, sync::Arc};
+
+    #[tokio::test]
+    async fn test_run_writes_trajectory() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = Arc::new(RunLog::create(dir.path(), "Say hi").unwrap());
+
+        let finish = json!({ "thought": "Done.", "action": { "tool": "Finish", "args": "hi" } });
+        let worker = ExecutorAgent::new(
+            Box::new(MockLlm::new(&finish.to_string())),
+            Vec::new(),
+            "Worker",
+            "A worker",
+        );
+        let mut workers: HashMap<String, Box<dyn Agent + Send + Sync>> = HashMap::new();
+        workers.insert(worker.name(), Box::new(worker));
+        let route = json!({ "worker": "Worker", "task": "Greet" }).to_string();
+        let supervisor = SupervisorAgent::new(Box::new(MockLlm::new(&route)), workers);
+        let orchestrator = Orchestrator::new(Box::new(supervisor)).with_run_log(log.clone());
+        assert_eq!(orchestrator.run("Say hi").await.unwrap(), "hi");
+
+        let path = dir.path().join(format!("{}.trajectory.json", log.id()));
+        let written: Trajectory = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(written, load(dir.path(), log.id()).unwrap());
+        assert_eq!(
+            (written.status, written.result.as_deref(), written.llm_calls),
+            (Status::Finished, Some("hi"), 2)
+        );
+
+        let [supervisor] = &written.agents[..] else {
+            panic!("expected one top-level agent: {:?}", written.agents);
+        };
+        assert_eq!(
+            (supervisor.agent.as_str(), supervisor.result.as_deref()),
+            ("SupervisorAgent", Some("hi"))
+        );
+        let [Step::Llm { .. }, Step::Route { worker, .. }, Step::Agent(run)] =
+            &supervisor.steps[..]
+        else {
+            panic!("unexpected supervisor steps: {:?}", supervisor.steps);
+        };
+        assert_eq!((worker.as_str(), run.task.as_str()), ("Worker", "Greet"));
+        assert!(matches!(
+            &run.steps[..],
+            [Step::Llm { .. }, Step::Thought { tool, .. }] if tool == "Finish"
+        ));
+    }
+}
@@ -0,0 +1,390 @@
+use super::{AgentRun, Status, Step, Trajectory};
+use crate::llm::Usage;
+use std::fmt::Write;
+
+/// Text in the timeline is cut to this many characters, so that each step
+/// takes one line.
+const MAX_LINE_CHARS: usize = 120;
+
+/// Renders a trajectory as a plain-text timeline, one step per line, each
+/// prefixed with its time since the run started and indented under the
+/// agent that took it.
+pub fn timeline(trajectory: &Trajectory) -> String {
+    let mut output = String::new();
+    let _ = writeln!(output, "Run {}: {}", trajectory.run_id, trajectory.task);
+    let _ = writeln!(output, "{}\n", summary(trajectory));
+    for run in &trajectory.agents {
+        agent_timeline(&mut output, run, 0);
+    }
+    match (&trajectory.result, &trajectory.error) {
+        (Some(result), _) => {
+            let _ = writeln!(output, "\nResult: {}", result);
+        }
+        (None, Some(error)) => {
+            let _ = writeln!(output, "\nError: {}", error);
+        }
+        (None, None) => {}
+    }
+    output
+}
+
+fn agent_timeline(output: &mut String, run: &AgentRun, depth: usize) {
+    line(
+        output,
+        run.offset_ms,
+        depth,
+        &format!("{}: {}", run.agent, run.task),
+    );
+    let depth = depth + 1;
+    for step in &run.steps {
+        match step {
+            Step::Llm {
+                offset_ms,
+                duration_ms,
+                usage,
+                ..
+            } => line(
+                output,
+                *offset_ms,
+                depth,
+                &format!("LLM call ({})", cost(*duration_ms, *usage)),
+            ),
+            Step::Route {
+                offset_ms,
+                worker,
+                task,
+            } => line(
+                output,
+                *offset_ms,
+                depth,
+                &format!("Route to {}: {}", worker, task),
+            ),
+            Step::Thought {
+                offset_ms,
+                thought,
+                tool,
+                args,
+            } => {
+                line(output, *offset_ms, depth, &format!("Thought: {}", thought));
+                line(
+                    output,
+                    *offset_ms,
+                    depth,
+                    &format!("Action: {} {}", tool, args),
+                );
+            }
+            Step::Tool {
+                offset_ms,
+                duration_ms,
+                observation,
+                ..
+            } => line(
+                output,
+                *offset_ms + duration_ms,
+                depth,
+                &format!("Observation ({}): {}", seconds(*duration_ms), observation),
+            ),
+            Step::Agent(child) => agent_timeline(output, child, depth),
+            Step::Failed { offset_ms, error } => {
+                line(output, *offset_ms, depth, &format!("Failed: {}", error))
+            }
+            Step::Resumed { offset_ms } => line(output, *offset_ms, depth, "Resumed"),
+        }
+    }
+    if let (Some(duration_ms), Some(result)) = (run.duration_ms, &run.result) {
+        line(
+            output,
+            run.offset_ms + duration_ms,
+            depth,
+            &format!("Returned after {}: {}", seconds(duration_ms), result),
+        );
+    }
+}
+
+/// Writes one line of the timeline, collapsing `text` onto one line.
+fn line(output: &mut String, offset_ms: u64, depth: usize, text: &str) {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let text = match text.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}...", &text[..cut]),
+        None => text,
+    };
+    let _ = writeln!(
+        output,
+        "{:>8}  {}{}",
+        seconds(offset_ms),
+        "  ".repeat(depth),
+        text
+    );
+}
+
+/// Describes how a run ended and what it used.
+fn summary(trajectory: &Trajectory) -> String {
+    let status = match trajectory.status {
+        Status::Finished => "Finished",
+        Status::Failed => "Failed",
+        Status::Incomplete => "Incomplete",
+    };
+    format!(
+        "{} after {}, {} LLM calls ({} tokens), {} tool calls",
+        status,
+        seconds(trajectory.duration_ms),
+        trajectory.llm_calls,
+        trajectory.usage.prompt_tokens + trajectory.usage.completion_tokens,
+        trajectory.tool_calls
+    )
+}
+
+fn cost(duration_ms: u64, usage: Option<Usage>) -> String {
+    match usage {
+        Some(usage) => format!(
+            "{}, {} prompt + {} completion tokens",
+            seconds(duration_ms),
+            usage.prompt_tokens,
+            usage.completion_tokens
+        ),
+        None => seconds(duration_ms),
+    }
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.0)
+}
+
+/// Renders a trajectory as a self-contained HTML report, in which each
+/// agent's steps are nested under it and prompts, responses and
+/// observations can be expanded.
+pub fn html(trajectory: &Trajectory) -> String {
+    let mut body = String::new();
+    for run in &trajectory.agents {
+        agent_html(&mut body, run);
+    }
+    let outcome = match (&trajectory.result, &trajectory.error) {
+        (Some(result), _) => format!("<h2>Result</h2>\n<pre>{}</pre>", escape(result)),
+        (None, Some(error)) => format!(
+            "<h2>Error</h2>\n<pre class=\"error\">{}</pre>",
+            escape(error)
+        ),
+        (None, None) => String::new(),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Run {id}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; max-width: 70em; }}
+.agent {{ border-left: 3px solid #6a8caf; margin: 0.5em 0; padding-left: 1em; }}
+.step {{ margin: 0.3em 0; }}
+.time {{ color: #777; font-family: monospace; display: inline-block; width: 5em; }}
+.error {{ color: #b00020; }}
+pre {{ background: #f5f5f5; padding: 0.5em; white-space: pre-wrap; }}
+</style>
+</head>
+<body>
+<h1>{task}</h1>
+<p>Run {id}. {summary}.</p>
+{body}{outcome}
+</body>
+</html>
+"#,
+        id = escape(&trajectory.run_id),
+        task = escape(&trajectory.task),
+        summary = escape(&summary(trajectory)),
+        body = body,
+        outcome = outcome,
+    )
+}
+
+fn agent_html(output: &mut String, run: &AgentRun) {
+    let _ = writeln!(
+        output,
+        "<div class=\"agent\">\n<div class=\"step\">{}<b>{}</b>: {}</div>",
+        time(run.offset_ms),
+        escape(&run.agent),
+        escape(&run.task)
+    );
+    for step in &run.steps {
+        match step {
+            Step::Llm {
+                offset_ms,
+                duration_ms,
+                prompt,
+                response,
+                usage,
+            } => {
+                let _ = writeln!(
+                    output,
+                    "<details class=\"step\"><summary>{}LLM call ({})</summary>\n\
+                     <p>Prompt</p><pre>{}</pre>\n<p>Response</p><pre>{}</pre></details>",
+                    time(*offset_ms),
+                    cost(*duration_ms, *usage),
+                    escape(prompt),
+                    escape(response)
+                );
+            }
+            Step::Route {
+                offset_ms,
+                worker,
+                task,
+            } => {
+                let _ = writeln!(
+                    output,
+                    "<div class=\"step\">{}Route to <b>{}</b>: {}</div>",
+                    time(*offset_ms),
+                    escape(worker),
+                    escape(task)
+                );
+            }
+            Step::Thought {
+                offset_ms,
+                thought,
+                tool,
+                args,
+            } => {
+                let _ = writeln!(
+                    output,
+                    "<div class=\"step\">{}Thought: {}<br>{}Action: <code>{}</code> <code>{}</code></div>",
+                    time(*offset_ms),
+                    escape(thought),
+                    time(*offset_ms),
+                    escape(tool),
+                    escape(args)
+                );
+            }
+            Step::Tool {
+                offset_ms,
+                duration_ms,
+                observation,
+                ..
+            } => {
+                let _ = writeln!(
+                    output,
+                    "<details class=\"step\"><summary>{}Observation ({})</summary><pre>{}</pre></details>",
+                    time(offset_ms + duration_ms),
+                    seconds(*duration_ms),
+                    escape(observation)
+                );
+            }
+            Step::Agent(child) => agent_html(output, child),
+            Step::Failed { offset_ms, error } => {
+                let _ = writeln!(
+                    output,
+                    "<div class=\"step error\">{}Failed: {}</div>",
+                    time(*offset_ms),
+                    escape(error)
+                );
+            }
+            Step::Resumed { offset_ms } => {
+                let _ = writeln!(
+                    output,
+                    "<div class=\"step\">{}Resumed</div>",
+                    time(*offset_ms)
+                );
+            }
+        }
+    }
+    if let (Some(duration_ms), Some(result)) = (run.duration_ms, &run.result) {
+        let _ = writeln!(
+            output,
+            "<div class=\"step\">{}Returned after {}: {}</div>",
+            time(run.offset_ms + duration_ms),
+            seconds(duration_ms),
+            escape(result)
+        );
+    }
+    output.push_str("</div>\n");
+}
+
+fn time(offset_ms: u64) -> String {
+    format!("<span class=\"time\">{}</span>", seconds(offset_ms))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_and_html() {
+        let trajectory = Trajectory {
+            run_id: "1760790000-3fa2c1".to_string(),
+            task: "Read <main>".to_string(),
+            status: Status::Finished,
+            result: Some("fn main() {}".to_string()),
+            error: None,
+            started_at_ms: 0,
+            duration_ms: 2500,
+            llm_calls: 2,
+            tool_calls: 1,
+            usage: Usage {
+                prompt_tokens: 300,
+                completion_tokens: 40,
+            },
+            agents: vec![AgentRun {
+                agent: "FileSystemAgent".to_string(),
+                task: "Read <main>".to_string(),
+                offset_ms: 0,
+                duration_ms: Some(2500),
+                result: Some("fn main() {}".to_string()),
+                steps: vec![
+                    Step::Llm {
+                        offset_ms: 0,
+                        duration_ms: 1200,
+                        prompt: "...".to_string(),
+                        response: "...".to_string(),
+                        usage: Some(Usage {
+                            prompt_tokens: 300,
+                            completion_tokens: 40,
+                        }),
+                    },
+                    Step::Thought {
+                        offset_ms: 1200,
+                        thought: "Read the\nfile.".to_string(),
+                        tool: "FileReaderTool".to_string(),
+                        args: "src/main.rs".to_string(),
+                    },
+                    Step::Tool {
+                        offset_ms: 1200,
+                        duration_ms: 100,
+                        tool: "FileReaderTool".to_string(),
+                        args: "src/main.rs".to_string(),
+                        observation: "fn main() {}".to_string(),
+                    },
+                ],
+            }],
+        };
+        assert_eq!(
+            timeline(&trajectory),
+            "Run 1760790000-3fa2c1: Read <main>
+Finished after 2.5s, 2 LLM calls (340 tokens), 1 tool calls
+
+    0.0s  FileSystemAgent: Read <main>
+    0.0s    LLM call (1.2s, 300 prompt + 40 completion tokens)
+    1.2s    Thought: Read the file.
+    1.2s    Action: FileReaderTool src/main.rs
+    1.3s    Observation (0.1s): fn main() {}
+    2.5s    Returned after 2.5s: fn main() {}
+
+Result: fn main() {}
+"
+        );
+        let html = html(&trajectory);
+        assert!(html.contains("<h1>Read &lt;main&gt;</h1>"));
+        assert!(!html.contains("<main>"));
+    }
+}
@@ -0,0 +1,254 @@
+use super::{AgentRun, Step, Trajectory};
+use crate::{
+    agent::Agent, executor::ExecutorAgent, llm::Llm, orchestrator::Orchestrator,
+    supervisor::SupervisorAgent, tools::Tool,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// What an agent did across every time it ran in a trajectory.
+#[derive(Default)]
+struct Recording {
+    responses: VecDeque<String>,
+    /// The arguments and observation of each tool call, keyed by tool.
+    tool_calls: BTreeMap<String, VecDeque<(String, String)>>,
+    /// The agents it routed tasks to, if it is a supervisor.
+    workers: Vec<String>,
+    is_supervisor: bool,
+}
+
+fn collect(run: &AgentRun, recordings: &mut HashMap<String, Recording>) {
+    let recording = recordings.entry(run.agent.clone()).or_default();
+    let mut children = Vec::new();
+    for step in &run.steps {
+        match step {
+            Step::Llm { response, .. } => recording.responses.push_back(response.clone()),
+            Step::Route { worker, .. } => {
+                recording.is_supervisor = true;
+                if !recording.workers.contains(worker) {
+                    recording.workers.push(worker.clone());
+                }
+            }
+            Step::Tool {
+                tool,
+                args,
+                observation,
+                ..
+            } => recording
+                .tool_calls
+                .entry(tool.clone())
+                .or_default()
+                .push_back((args.clone(), observation.clone())),
+            Step::Agent(child) => children.push(child),
+            Step::Thought { .. } | Step::Failed { .. } | Step::Resumed { .. } => {}
+        }
+    }
+    for child in children {
+        collect(child, recordings);
+    }
+}
+
+/// An LLM that returns an agent's recorded responses in order.
+struct ReplayLlm {
+    agent: String,
+    responses: Mutex<VecDeque<String>>,
+}
+
+#[async_trait]
+impl Llm for ReplayLlm {
+    async fn call(&self, _prompt: &str) -> Result<String> {
+        self.responses.lock().unwrap().pop_front().ok_or_else(|| {
+            anyhow!(
+                "{} called its LLM more times than were recorded",
+                self.agent
+            )
+        })
+    }
+}
+
+/// A tool that returns its recorded observations in order, as long as it
+/// is called with the recorded arguments.
+struct ReplayTool {
+    name: String,
+    calls: Mutex<VecDeque<(String, String)>>,
+}
+
+#[async_trait]
+impl Tool for ReplayTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn execute(&self, args: &str) -> Result<String> {
+        let (recorded, observation) = self
+            .calls
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow!("{} ran more times than were recorded", self.name))?;
+        if recorded != args {
+            return Err(anyhow!(
+                "{} was called with {:?}, but the recording has {:?}",
+                self.name,
+                args,
+                recorded
+            ));
+        }
+        Ok(observation)
+    }
+}
+
+/// Builds the agent `name` and the agents it routes to from their
+/// recordings. `path` holds the agents above it, to stop at cycles.
+fn build(
+    name: &str,
+    recordings: &mut HashMap<String, Recording>,
+    path: &mut Vec<String>,
+) -> Box<dyn Agent + Send + Sync> {
+    let recording = recordings.remove(name).unwrap_or_default();
+    let llm = Box::new(ReplayLlm {
+        agent: name.to_string(),
+        responses: Mutex::new(recording.responses),
+    });
+    if !recording.is_supervisor {
+        let tools = recording
+            .tool_calls
+            .into_iter()
+            .map(|(tool, calls)| {
+                Box::new(ReplayTool {
+                    name: tool,
+                    calls: Mutex::new(calls),
+                }) as Box<dyn Tool + Send + Sync>
+            })
+            .collect();
+        return Box::new(ExecutorAgent::new(llm, tools, name, ""));
+    }
+
+    path.push(name.to_string());
+    let mut workers: HashMap<String, Box<dyn Agent + Send + Sync>> = HashMap::new();
+    for worker in recording.workers {
+        if !path.contains(&worker) {
+            let agent = build(&worker, recordings, path);
+            workers.insert(worker, agent);
+        }
+    }
+    path.pop();
+    Box::new(SupervisorAgent::new(llm, workers).with_name(name, ""))
+}
+
+/// Runs a trajectory's task again with the same kinds of agents, whose LLMs
+/// return the recorded responses and whose tools return the recorded
+/// observations, without calling an LLM or touching the workspace.
+///
+/// The agents' own logic, such as parsing responses and picking tools, runs
+/// for real, so a bug in it fails the replay the way it failed the run. A
+/// replay that asks for a step that was not recorded fails at that step.
+///
+/// # Returns
+///
+/// A `Result` with the replay's result, or the error it stopped with.
+pub async fn replay(trajectory: &Trajectory) -> Result<String> {
+    let root = trajectory
+        .agents
+        .first()
+        .ok_or_else(|| anyhow!("Run {} recorded no agent steps", trajectory.run_id))?;
+    let mut recordings = HashMap::new();
+    for run in &trajectory.agents {
+        collect(run, &mut recordings);
+    }
+    let agent = build(&root.agent, &mut recordings, &mut Vec::new());
+    Orchestrator::new(agent).run(&trajectory.task).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::Usage,
+        trajectory::{AgentRun, Status},
+    };
+    use serde_json::json;
+
+    fn llm(response: serde_json::Value) -> Step {
+        Step::Llm {
+            offset_ms: 0,
+            duration_ms: 0,
+            prompt: String::new(),
+            response: response.to_string(),
+            usage: None,
+        }
+    }
+
+    fn tool(args: &str, observation: &str) -> Step {
+        Step::Tool {
+            offset_ms: 0,
+            duration_ms: 0,
+            tool: "FileReaderTool".to_string(),
+            args: args.to_string(),
+            observation: observation.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_uses_recorded_steps() {
+        let worker = AgentRun {
+            agent: "FileSystemAgent".to_string(),
+            task: "Read the file".to_string(),
+            offset_ms: 0,
+            duration_ms: None,
+            result: None,
+            steps: vec![
+                llm(
+                    json!({ "thought": "Read it.", "action": { "tool": "FileReaderTool", "args": "a.txt" } }),
+                ),
+                tool("a.txt", "hello"),
+                llm(json!({ "thought": "Done.", "action": { "tool": "Finish", "args": "hello" } })),
+            ],
+        };
+        let mut trajectory = Trajectory {
+            run_id: "1760790000-3fa2c1".to_string(),
+            task: "What does a.txt say?".to_string(),
+            status: Status::Finished,
+            result: Some("hello".to_string()),
+            error: None,
+            started_at_ms: 0,
+            duration_ms: 0,
+            llm_calls: 3,
+            tool_calls: 1,
+            usage: Usage::default(),
+            agents: vec![AgentRun {
+                agent: "SupervisorAgent".to_string(),
+                task: "What does a.txt say?".to_string(),
+                offset_ms: 0,
+                duration_ms: None,
+                result: None,
+                steps: vec![
+                    llm(json!({ "worker": "FileSystemAgent", "task": "Read the file" })),
+                    Step::Route {
+                        offset_ms: 0,
+                        worker: "FileSystemAgent".to_string(),
+                        task: "Read the file".to_string(),
+                    },
+                    Step::Agent(worker),
+                ],
+            }],
+        };
+        assert_eq!(replay(&trajectory).await.unwrap(), "hello");
+
+        // A replay that takes a different action fails at that step.
+        let Step::Agent(worker) = &mut trajectory.agents[0].steps[2] else {
+            unreachable!();
+        };
+        worker.steps[0] = llm(
+            json!({ "thought": "Read it.", "action": { "tool": "FileReaderTool", "args": "b.txt" } }),
+        );
+        assert_eq!(
+            replay(&trajectory).await.unwrap_err().to_string(),
+            "FileReaderTool was called with \"b.txt\", but the recording has \"a.txt\""
+        );
+    }
+}